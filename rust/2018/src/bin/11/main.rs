@@ -1,47 +1,114 @@
-use std::{env, error::Error, fmt, fs};
+use aoc_2018_rust::{
+    input::read_input,
+    summed_area_table::{NonRectError, SummedAreaTable},
+    timing::{self, Timer},
+};
+use clap::{App, Arg};
+use std::error::Error;
 
 const GRID_SIZE: (usize, usize) = (300, 300);
 
 pub fn main() -> Result<(), Box<dyn Error>> {
-    let args = env::args().collect::<Vec<String>>();
+    let matches = App::new("2018-11")
+        .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
+        .arg(
+            Arg::from_usage("[size] -s --size 'Only search for squares of this size'")
+                .takes_value(true),
+        )
+        .arg(Arg::from_usage(
+            "[time] --time 'Print how long parsing and solving take'",
+        ))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
+        .get_matches();
 
-    let input_filename = if args.len() == 2 {
-        &args[1]
-    } else {
-        "input.txt"
-    };
+    let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| "Provided repeat count is not a positive number")?;
+
+    let parse_timer = Timer::start("Parsing", time);
+    let grid_serial_number: usize = read_input(input_filename)?.trim().parse()?;
+    drop(parse_timer);
 
-    let grid_serial_number: usize = fs::read_to_string(input_filename)?.trim().parse()?;
+    if let Some(size_str) = matches.value_of("size") {
+        let size = size_str.parse()?;
 
+        let best_square =
+            timing::repeat("Solving", repeat, time, || -> Result<_, NonRectError> {
+                let summed_area_table = power_summed_area_table(grid_serial_number)?;
+
+                Ok(find_best_square(&summed_area_table, size..=size))
+            })?;
+
+        println!(
+            "Best {0}x{0} square: {1:?}",
+            size,
+            best_square.ok_or("No square of that size fits")?
+        );
+
+        return Ok(());
+    }
+
+    let (best_3x3_square, best_square) =
+        timing::repeat("Solving", repeat, time, || -> Result<_, NonRectError> {
+            let summed_area_table = power_summed_area_table(grid_serial_number)?;
+
+            Ok((
+                find_best_square(&summed_area_table, 3..=3),
+                find_best_square(&summed_area_table, 1..=GRID_SIZE.0),
+            ))
+        })?;
+
+    println!(
+        "Best 3x3 square: {:?}",
+        best_3x3_square.ok_or("No 3x3 square fits")?
+    );
+
+    println!(
+        "Best square of any size: {:?}",
+        best_square.ok_or("No square fits")?
+    );
+
+    Ok(())
+}
+
+fn power_summed_area_table(grid_serial_number: usize) -> Result<SummedAreaTable, NonRectError> {
     // Technically, I could compute the grid along with the SAT, and
     // it might be faster since it would be one pass, but for the sake
     // of cleanliness and overall generality I decided to split them
     // both up, so that I could test them separately.
     let grid = construct_grid(grid_serial_number, GRID_SIZE);
-    let summed_area_table = compute_summed_area_table(&grid)?;
-
-    let mut grid_sums = vec![];
 
-    for size in 1..=GRID_SIZE.0 {
-        for yi in 0..GRID_SIZE.1 {
-            for xi in 0..GRID_SIZE.0 {
-                if xi.checked_sub(size).is_none() || yi.checked_sub(size).is_none() {
-                    continue;
-                }
-
-                let square_sum = summed_area_table[yi][xi]
-                    - summed_area_table[yi][xi - size]
-                    - summed_area_table[yi - size][xi]
-                    + summed_area_table[yi - size][xi - size];
+    SummedAreaTable::from_grid(&grid)
+}
 
-                grid_sums.push((square_sum, (xi - size) + 2, (yi - size) + 2, size));
+// Gives the (x, y, size) of the square with the largest total power, where
+// (x, y) is the top-left corner in the 1-indexed coordinates of the problem.
+fn find_best_square(
+    summed_area_table: &SummedAreaTable,
+    sizes: impl IntoIterator<Item = usize>,
+) -> Option<(usize, usize, usize)> {
+    let mut best_square = None;
+    let mut best_sum = isize::MIN;
+
+    for size in sizes {
+        for ((xi, yi), square_sum) in summed_area_table.iter_rects(size, size) {
+            if square_sum > best_sum {
+                best_sum = square_sum;
+                best_square = Some((xi + 1, yi + 1, size));
             }
         }
     }
 
-    println!("{:?}", grid_sums.iter().max_by_key(|v| v.0).unwrap());
-
-    Ok(())
+    best_square
 }
 
 fn construct_grid(grid_serial_number: usize, grid_size: (usize, usize)) -> Vec<Vec<isize>> {
@@ -58,76 +125,39 @@ fn construct_grid(grid_serial_number: usize, grid_size: (usize, usize)) -> Vec<V
         .collect()
 }
 
-fn compute_summed_area_table(grid: &[Vec<isize>]) -> Result<Vec<Vec<isize>>, NonRectError> {
-    // Asumming the grid is actually rectangular, we can assign all
-    // the Vecs with the same row-length capacity to help optimize
-    // with memory a teeny bit.
-    let mut summed_area_table = vec![Vec::with_capacity(grid[0].len()); grid.len()];
-
-    for (yi, row) in grid.iter().enumerate() {
-        for (xi, &value) in row.iter().enumerate() {
-            // The value of the summed-area table at (x, y) is simply (where I
-            // provides previous values in the table, and i provides values in
-            // the original grid):
-            //
-            // I(x, y) = i(x, y) + I(x - 1, y) + I(x, y - 1) - I(x - 1, y - 1)
-            //
-            // If any of these values do not exist, they are replaced with 0.
-
-            // I(x, y - 1)
-            let north = match yi {
-                0 => &0,
-                _ => {
-                    // However, if this particular value doesn't exist, then we
-                    // know that we have an x-index that's not accessible on a
-                    // previous row. This means the grid were working with is
-                    // actually non-rectangular, which means we should return an
-                    // error here.
-                    summed_area_table
-                        .get(yi - 1)
-                        .and_then(|row| row.get(xi))
-                        .ok_or(NonRectError { xi, yi })?
-                }
-            };
-
-            // I(x - 1, y)
-            let west = match xi {
-                0 => &0,
-                _ => &summed_area_table[yi][xi - 1],
-            };
-
-            // I(x - 1, y - 1)
-            let northwest = match (xi, yi) {
-                (0, _) => &0,
-                (_, 0) => &0,
-                (_, _) => summed_area_table
-                    .get(yi - 1)
-                    .and_then(|row| row.get(xi - 1))
-                    .unwrap_or(&0),
-            };
-
-            let summed_values = value + north + west - northwest;
-
-            summed_area_table[yi].push(summed_values);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_levels() {
+        // Coordinates are 1-indexed in the problem, but not in the grid.
+        let power_level = |grid_serial_number, (x, y): (usize, usize)| {
+            construct_grid(grid_serial_number, GRID_SIZE)[y - 1][x - 1]
+        };
+
+        assert_eq!(power_level(8, (3, 5)), 4);
+        assert_eq!(power_level(57, (122, 79)), -5);
+        assert_eq!(power_level(39, (217, 196)), 0);
+        assert_eq!(power_level(71, (101, 153)), 4);
     }
 
-    Ok(summed_area_table)
-}
-
-#[derive(Debug, Clone)]
-struct NonRectError {
-    xi: usize,
-    yi: usize,
-}
-
-impl fmt::Display for NonRectError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f,
-            "grid is not a rectangular 2d Vec: column {} is not valid on row {}, but it is on row {}",
-            self.xi, self.yi - 1, self.yi
-        )
+    #[test]
+    fn best_squares() {
+        for &(grid_serial_number, best_3x3_square, best_square) in &[
+            (18, (33, 45, 3), (90, 269, 16)),
+            (42, (21, 61, 3), (232, 251, 12)),
+        ] {
+            let summed_area_table = power_summed_area_table(grid_serial_number).unwrap();
+
+            assert_eq!(
+                find_best_square(&summed_area_table, 3..=3),
+                Some(best_3x3_square)
+            );
+            assert_eq!(
+                find_best_square(&summed_area_table, 1..=GRID_SIZE.0),
+                Some(best_square)
+            );
+        }
     }
 }
-
-impl Error for NonRectError {}
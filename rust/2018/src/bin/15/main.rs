@@ -1,99 +1,167 @@
+use anyhow::bail;
+use aoc_2018_rust::{
+    geometry::Point,
+    grid,
+    input::read_input,
+    timing::{self, Timer},
+};
+use clap::{App, Arg};
+use itertools::Itertools;
 use std::{
-    cmp::{Ordering, Reverse},
-    collections::{BinaryHeap, HashMap},
-    env,
+    cmp::Ordering,
+    collections::{HashMap, VecDeque},
     error::Error,
-    fmt, fs,
+    fmt, mem,
 };
 use unit::*;
 
 pub fn main() -> Result<(), Box<dyn Error>> {
-    let args = env::args().collect::<Vec<String>>();
+    let matches = App::new("2018-15")
+        .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
+        .arg(Arg::from_usage("[p2] -2 --part2 'Solves Part 2'"))
+        .arg(Arg::from_usage(
+            "[time] --time 'Print how long parsing and solving take'",
+        ))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
+        .get_matches();
+
+    let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| "Provided repeat count is not a positive number")?;
 
-    let input_filename = if args.len() == 2 {
-        &args[1]
-    } else {
-        "input.txt"
-    };
+    let parse_timer = Timer::start("Parsing", time);
+    let string_grid = read_input(input_filename)?;
 
-    let string_grid = fs::read_to_string(input_filename)?;
+    let combat_grid = parse_input(&string_grid)?;
+    drop(parse_timer);
 
-    let mut combat_grid = parse_input(&string_grid)?;
-    let mut full_rounds: usize = 0;
+    if matches.is_present("p2") {
+        let (attack_power, outcome) =
+            timing::repeat("Solving", repeat, time, || minimum_elf_power(&combat_grid));
 
-    println!("Start");
-    print!("{}", combat_grid);
-    println!("\n");
+        println!(
+            "Elves need an attack power of {} to win without losses, with an outcome of {}",
+            attack_power, outcome
+        );
 
-    while combat_grid.tick() {
-        full_rounds += 1;
-        println!("\n");
-        println!("Round {}", full_rounds);
-        print!("{}", combat_grid);
-        println!("\n");
+        return Ok(());
     }
 
-    println!("Final");
-    print!("{}", combat_grid);
-    println!("\n");
+    let mut first_run = true;
+
+    let outcome = timing::repeat("Solving", repeat, time, || {
+        // Only the first battle gets drawn, since the rest are the same.
+        let draw = mem::take(&mut first_run);
+        let mut combat_grid = combat_grid.clone();
+        let mut full_rounds: usize = 0;
+
+        if draw {
+            println!("Start");
+            print!("{}", combat_grid);
+            println!("\n");
+        }
 
-    println!(
-        "Outcome: {}",
-        full_rounds * combat_grid.units.values().map(|u| u.hp).sum::<usize>()
-    );
+        while combat_grid.tick() == RoundOutcome::FullRound {
+            full_rounds += 1;
+
+            if draw {
+                println!("\n");
+                println!("Round {}", full_rounds);
+                print!("{}", combat_grid);
+                println!("\n");
+            }
+        }
+
+        if draw {
+            println!("Final");
+            print!("{}", combat_grid);
+            println!("\n");
+        }
+
+        combat_grid.outcome(full_rounds)
+    });
+
+    println!("Outcome: {}", outcome);
 
     Ok(())
 }
 
-pub fn parse_input(string_grid: &str) -> Result<CombatGrid, String> {
-    let mut grid = HashMap::new();
-    let mut units = HashMap::new();
-    let mut dimensions = (0, 0);
-
-    for (y, row) in string_grid.lines().enumerate() {
-        dimensions.1 += 1;
-
-        for (x, character) in row.chars().enumerate() {
-            dimensions.0 += 1;
-
-            let current_location = Location { x, y };
-
-            grid.insert(
-                current_location,
-                match character {
-                    '#' => Environment::Wall,
-                    '.' => Environment::Open,
-                    'G' | 'E' => {
-                        units.insert(
-                            current_location,
-                            Unit {
-                                team: if character == 'G' {
-                                    UnitTeam::Goblin
-                                } else {
-                                    UnitTeam::Elf
-                                },
-                                location: current_location,
-                                hp: 200,
-                                attack_power: 3,
-                            },
-                        );
-
-                        Environment::Open
-                    }
-                    _ => {
-                        return Err(format!("Invalid input character: {}", character));
-                    }
-                },
-            );
+// The outcome isn't guaranteed to get better as the Elves get stronger (an Elf
+// might survive with some attack power but die with a higher one, thanks to
+// the different ways the battle plays out) so we can't binary search here.
+pub fn minimum_elf_power(grid: &CombatGrid) -> (usize, usize) {
+    let num_elves = grid.team_size(UnitTeam::Elf);
+
+    for attack_power in 4..=usize::MAX {
+        let mut grid = grid.clone();
+
+        for unit in grid.units.values_mut() {
+            if unit.team == UnitTeam::Elf {
+                unit.attack_power = attack_power;
+            }
+        }
+
+        let full_rounds = grid.battle_to_end();
+
+        if grid.team_size(UnitTeam::Elf) == num_elves {
+            return (attack_power, grid.outcome(full_rounds));
         }
     }
 
-    dimensions.0 /= dimensions.1;
+    unreachable!("Elves with enough attack power kill anything in one hit");
+}
+
+pub fn parse_input(string_grid: &str) -> Result<CombatGrid, anyhow::Error> {
+    let mut units = HashMap::new();
+
+    // Editors love to leave trailing whitespace around, which would otherwise
+    // make the grid look ragged. A row that's actually too short is still an
+    // error though, since there's no way to know what should be there.
+    let string_grid = string_grid.lines().map(str::trim_end).join("\n");
+
+    let parsed_grid = grid::parse(&string_grid, |character, Point([x, y])| {
+        let current_location = Location { x, y };
+
+        Ok(match character {
+            '#' => Environment::Wall,
+            '.' => Environment::Open,
+            'G' | 'E' => {
+                units.insert(
+                    current_location,
+                    Unit {
+                        team: if character == 'G' {
+                            UnitTeam::Goblin
+                        } else {
+                            UnitTeam::Elf
+                        },
+                        location: current_location,
+                        hp: 200,
+                        attack_power: 3,
+                    },
+                );
+
+                Environment::Open
+            }
+            _ => bail!("Invalid input character: {}", character),
+        })
+    })?;
 
     Ok(CombatGrid {
-        grid,
+        grid: parsed_grid
+            .iter_points()
+            .map(|(Point([x, y]), env)| (Location { x, y }, env.clone()))
+            .collect(),
         units,
-        dimensions,
+        dimensions: (parsed_grid.width(), parsed_grid.height()),
     })
 }
 
@@ -104,25 +172,11 @@ pub struct Location {
 }
 
 impl Location {
-    fn adjacent(&self) -> [Self; 4] {
-        [
-            Location {
-                x: self.x,
-                y: self.y - 1,
-            },
-            Location {
-                x: self.x,
-                y: self.y + 1,
-            },
-            Location {
-                x: self.x - 1,
-                y: self.y,
-            },
-            Location {
-                x: self.x + 1,
-                y: self.y,
-            },
-        ]
+    // Squares off the top or left edge are left out rather than underflowing.
+    fn adjacent(self) -> impl Iterator<Item = Self> {
+        Point([self.x, self.y])
+            .orthogonal_neighbors()
+            .map(|Point([x, y])| Location { x, y })
     }
 }
 
@@ -144,6 +198,7 @@ impl PartialOrd for Location {
     }
 }
 
+#[derive(Clone)]
 pub struct CombatGrid {
     pub grid: HashMap<Location, Environment>,
     pub units: HashMap<Location, Unit>,
@@ -179,8 +234,17 @@ impl fmt::Display for CombatGrid {
     }
 }
 
+// Combat can end partway through a round, as soon as some unit can't find any
+// targets at the start of its turn. A round like that doesn't count towards the
+// outcome, even if some units have already taken their turns in it.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum RoundOutcome {
+    FullRound,
+    CombatEnded,
+}
+
 impl CombatGrid {
-    pub fn tick(&mut self) -> bool {
+    pub fn tick(&mut self) -> RoundOutcome {
         let mut unit_locations = self.units.keys().cloned().collect::<Vec<_>>();
         unit_locations.sort_unstable();
 
@@ -200,7 +264,7 @@ impl CombatGrid {
                 .collect::<HashMap<_, _>>();
 
             if enemy_units.is_empty() {
-                return false; // Combat has ended, one team has won.
+                return RoundOutcome::CombatEnded; // One team has won.
             }
 
             if let Some(attacked_unit_location) = unit.maybe_attack(&enemy_units) {
@@ -221,7 +285,26 @@ impl CombatGrid {
             }
         }
 
-        true
+        RoundOutcome::FullRound
+    }
+
+    // Returns the number of full rounds that were completed.
+    pub fn battle_to_end(&mut self) -> usize {
+        let mut full_rounds = 0;
+
+        while self.tick() == RoundOutcome::FullRound {
+            full_rounds += 1;
+        }
+
+        full_rounds
+    }
+
+    pub fn outcome(&self, full_rounds: usize) -> usize {
+        full_rounds * self.units.values().map(|u| u.hp).sum::<usize>()
+    }
+
+    pub fn team_size(&self, team: UnitTeam) -> usize {
+        self.units.values().filter(|u| u.team == team).count()
     }
 
     fn attack_unit(&mut self, current_unit_location: &Location, attacked_unit_location: &Location) {
@@ -257,7 +340,7 @@ impl CombatGrid {
     }
 }
 
-#[derive(Eq, PartialEq)]
+#[derive(Eq, PartialEq, Clone)]
 pub enum Environment {
     Wall,
     Open,
@@ -317,7 +400,7 @@ mod unit {
         pub fn maybe_attack(&self, enemy_units: &HashMap<Location, Unit>) -> Option<Location> {
             let mut adjacent_enemy_units = enemy_units
                 .values()
-                .filter(|u| self.location.adjacent().contains(&u.location))
+                .filter(|u| self.location.adjacent().any(|l| l == u.location))
                 .collect::<Vec<_>>();
 
             adjacent_enemy_units.sort_unstable_by_key(|unit| (unit.hp, unit.location));
@@ -326,61 +409,233 @@ mod unit {
             adjacent_enemy_units.pop().map(|u| u.location)
         }
 
+        // Moves one step towards the closest square that's in range of an enemy.
+        // Ties are broken by reading order twice over: first when choosing which
+        // of the closest squares to go to, and then again when choosing which of
+        // the shortest paths to that square to start walking down.
         pub fn maybe_move(
             &self,
             enemy_units: &HashMap<Location, Unit>,
             is_open_fn: impl Fn(&Location) -> bool,
         ) -> Option<Location> {
-            let mut frontier = self
-                .location
+            let distances_from_unit = distances_from(self.location, &is_open_fn);
+
+            let (_, target) = enemy_units
+                .keys()
+                .flat_map(|l| l.adjacent())
+                .filter_map(|l| distances_from_unit.get(&l).map(|&distance| (distance, l)))
+                .min()?;
+
+            // Whichever adjacent square is the closest to the target is the first
+            // step on some shortest path to it.
+            let distances_from_target = distances_from(target, &is_open_fn);
+
+            self.location
                 .adjacent()
-                .iter()
-                .cloned()
-                .filter(&is_open_fn)
-                .map(|l| {
-                    Reverse(SearchNode {
-                        distance: 1,
-                        current_location: l,
-                        starting_location: l,
-                    })
-                })
-                .collect::<BinaryHeap<_>>();
-
-            let mut explored = Vec::new();
-
-            while let Some(Reverse(next)) = frontier.pop() {
-                for next_adjacent in next.current_location.adjacent().iter().cloned() {
-                    if explored.contains(&next_adjacent) {
-                        continue;
-                    }
-
-                    if !is_open_fn(&next_adjacent) {
-                        if enemy_units.contains_key(&next_adjacent) {
-                            return Some(next.starting_location);
-                        }
-
-                        continue;
-                    }
-
-                    frontier.push(Reverse(SearchNode {
-                        distance: next.distance + 1,
-                        current_location: next_adjacent,
-                        starting_location: next.starting_location,
-                    }));
-
-                    explored.push(next_adjacent);
+                .filter_map(|l| distances_from_target.get(&l).map(|&distance| (distance, l)))
+                .min()
+                .map(|(_, l)| l)
+        }
+    }
+
+    // A plain BFS, giving the distance to every square reachable from start
+    // through open squares. start itself doesn't need to be open, since it's
+    // usually occupied by the unit that's searching.
+    //
+    // This doesn't use search::astar, since that stops once it reaches a
+    // single goal, and the reading order tie-breaks in maybe_move need the
+    // distance to every square at once. Every step costs the same anyway, so
+    // a priority queue wouldn't buy anything over a VecDeque.
+    fn distances_from(
+        start: Location,
+        is_open_fn: impl Fn(&Location) -> bool,
+    ) -> HashMap<Location, usize> {
+        let mut distances = HashMap::new();
+        let mut frontier = VecDeque::new();
+
+        distances.insert(start, 0);
+        frontier.push_back(start);
+
+        while let Some(current) = frontier.pop_front() {
+            let distance = distances[&current];
+
+            for next in current.adjacent() {
+                if is_open_fn(&next) && !distances.contains_key(&next) {
+                    distances.insert(next, distance + 1);
+                    frontier.push_back(next);
                 }
             }
+        }
+
+        distances
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_locations(combat_grid: &CombatGrid) -> Vec<(Location, UnitTeam)> {
+        let mut unit_locations = combat_grid
+            .units
+            .values()
+            .map(|u| (u.location, u.team))
+            .collect::<Vec<_>>();
+
+        unit_locations.sort_unstable_by_key(|&(l, _)| l);
+        unit_locations
+    }
+
+    fn elf_move(string_grid: &str) -> Option<Location> {
+        let combat_grid = parse_input(string_grid).unwrap();
+        let elf = combat_grid
+            .units
+            .values()
+            .find(|u| u.team == UnitTeam::Elf)
+            .unwrap();
+        let enemy_units = combat_grid
+            .units
+            .iter()
+            .filter(|(_, u)| u.is_enemy(elf))
+            .map(|(l, u)| (*l, u.clone()))
+            .collect();
+
+        elf.maybe_move(&enemy_units, |l| combat_grid.is_open_fn(l))
+    }
+
+    #[test]
+    fn closest_target_in_reading_order() {
+        // (3, 1), (2, 2) and (1, 3) are all 2 steps away, and (3, 1) comes
+        // first in reading order. (5, 1) can't be reached at all.
+        let string_grid = "#######\n\
+                           #E..G.#\n\
+                           #...#.#\n\
+                           #.G.#G#\n\
+                           #######";
+
+        assert_eq!(elf_move(string_grid), Some(Location { x: 2, y: 1 }));
+    }
 
-            None
+    #[test]
+    fn first_step_in_reading_order() {
+        // The only target is (4, 2), and going right or down both start a
+        // shortest path to it, but right comes first in reading order.
+        let string_grid = "#######\n\
+                           #.E...#\n\
+                           #.....#\n\
+                           #...G.#\n\
+                           #######";
+
+        assert_eq!(elf_move(string_grid), Some(Location { x: 3, y: 1 }));
+    }
+
+    #[test]
+    fn no_reachable_target() {
+        let string_grid = "#######\n\
+                           #E.#G.#\n\
+                           #######";
+
+        assert_eq!(elf_move(string_grid), None);
+    }
+
+    #[test]
+    fn adjacent_on_the_edge() {
+        let mut adjacent = Location { x: 0, y: 0 }.adjacent().collect::<Vec<_>>();
+        adjacent.sort_unstable();
+
+        assert_eq!(adjacent, [Location { x: 1, y: 0 }, Location { x: 0, y: 1 }]);
+    }
+
+    #[test]
+    fn battle_without_walls() {
+        // Nothing stops the units from looking past the edges of the grid
+        // here, so they'd underflow if anything was going to.
+        let mut combat_grid = parse_input("G..\n..E").unwrap();
+        combat_grid.battle_to_end();
+
+        assert_eq!(combat_grid.units.len(), 1);
+    }
+
+    #[test]
+    fn movement_rounds() {
+        let mut combat_grid = parse_input(include_str!("input_small.txt")).unwrap();
+
+        let rounds = [
+            "#########\n\
+             #.G...G.#\n\
+             #...G...#\n\
+             #...E..G#\n\
+             #.G.....#\n\
+             #.......#\n\
+             #G..G..G#\n\
+             #.......#\n\
+             #########",
+            "#########\n\
+             #..G.G..#\n\
+             #...G...#\n\
+             #.G.E.G.#\n\
+             #.......#\n\
+             #G..G..G#\n\
+             #.......#\n\
+             #.......#\n\
+             #########",
+            "#########\n\
+             #.......#\n\
+             #..GGG..#\n\
+             #..GEG..#\n\
+             #G..G...#\n\
+             #......G#\n\
+             #.......#\n\
+             #.......#\n\
+             #########",
+        ];
+
+        for expected in rounds.iter() {
+            assert_eq!(combat_grid.tick(), RoundOutcome::FullRound);
+            assert_eq!(
+                unit_locations(&combat_grid),
+                unit_locations(&parse_input(expected).unwrap())
+            );
         }
+
+        // Everyone's in place by now, so nobody should move any further.
+        let settled = unit_locations(&combat_grid);
+        combat_grid.tick();
+        assert_eq!(unit_locations(&combat_grid), settled);
+    }
+
+    #[test]
+    fn combat_outcome() {
+        let mut combat_grid = parse_input(include_str!("input_small_2.txt")).unwrap();
+        let full_rounds = combat_grid.battle_to_end();
+
+        assert_eq!(full_rounds, 47);
+        assert_eq!(combat_grid.outcome(full_rounds), 27730);
+    }
+
+    #[test]
+    fn ragged_grid() {
+        // The first row's trailing spaces are fine, but the last row really
+        // is a character short.
+        let err = parse_input(include_str!("input_ragged.txt")).err().unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "Row 6 is 6 characters wide, but the rows above it are 7"
+        );
     }
 
-    // Private helper to make maybe_move easier to keep track of
-    #[derive(Debug, Eq, PartialEq, Copy, Clone, Ord, PartialOrd)]
-    struct SearchNode {
-        distance: usize,
-        current_location: Location,
-        starting_location: Location,
+    #[test]
+    fn trailing_whitespace() {
+        let combat_grid = parse_input("#####  \n#G.E#\t\n##### \n").unwrap();
+
+        assert_eq!(combat_grid.dimensions, (5, 3));
+        assert_eq!(
+            unit_locations(&combat_grid),
+            [
+                (Location { x: 1, y: 1 }, UnitTeam::Goblin),
+                (Location { x: 3, y: 1 }, UnitTeam::Elf)
+            ]
+        );
     }
 }
@@ -1,44 +1,167 @@
-#![feature(fn_traits)]
-
 use anyhow::{anyhow, bail};
+use aoc_2018_rust::{
+    elfcode::{parse_program, Instruction, Value, NUM_REGISTERS},
+    input::read_input,
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
 use itertools::Itertools;
-use std::fmt;
-use std::fs;
-use std::ops;
+use std::{mem, ops::Range};
 
 pub fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2018-21")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
         .arg(Arg::from_usage("[p1] -1 --part1 'Solves Part 1'"))
-        .arg(Arg::from_usage("[p2] -2 --part2 'Solves Part 2'").requires("special_reg"))
+        .arg(Arg::from_usage("[p2] -2 --part2 'Solves Part 2'"))
         .arg(Arg::from_usage(
             "[debug] -d --problem-debug 'Whether to execute the input program line by line'",
         ))
         .arg(
             Arg::from_usage(
-                "[special_reg] -s --special-reg 'Which register in the input is the special one that is checked for equality in Part 2 problems'"
-            ).default_value("5")
+                "[special_reg] -s --special-reg 'Which register in the input is the special one that is checked for equality, if it can't be worked out from the program'"
+            ).takes_value(true)
         )
         .arg(
             Arg::from_usage("[reg0] -0 --reg-0 'Overrides the value of register 0'")
                 .takes_value(true)
                 .conflicts_with_all(&["p1", "p2"]),
         )
+        .arg(
+            Arg::from_usage("[sweep] --sweep 'Runs the program for every value of register 0 in a range like 0..100, and reports which ones halt'")
+                .takes_value(true)
+                .conflicts_with_all(&["p1", "p2", "reg0", "debug"]),
+        )
+        .arg(
+            Arg::from_usage("[max_steps] --max-steps 'How many instructions each value of register 0 gets to halt in while sweeping'")
+                .default_value("1000000"),
+        )
+        .arg(Arg::from_usage("[time] --time 'Print how long parsing and solving take'"))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
+
+    let parse_timer = Timer::start("Parsing", time);
+    let code_str = read_input(input_filename)?;
+    let (ins_pointer, code) = parse_program(&code_str)?;
+    drop(parse_timer);
+
+    // Repeated runs would only print the same thing over again.
+    let mut first_run = true;
+
+    if let Some(sweep_str) = matches.value_of("sweep") {
+        let reg0_range = parse_range(sweep_str)?;
+        let max_steps = matches
+            .value_of("max_steps")
+            .unwrap()
+            .parse()
+            .map_err(|_| anyhow!("Provided max steps is not a number"))?;
 
-    let code_str = fs::read_to_string(input_filename)?;
-    let (ins_pointer, code) = parse_input(&code_str)?;
+        let num_halting = timing::repeat("Solving", repeat, time, || {
+            let show = mem::take(&mut first_run);
+            let mut num_halting = 0;
+
+            for reg0 in reg0_range.clone() {
+                if let Some(steps) = run_until_halt(ins_pointer, &code, reg0, max_steps) {
+                    if show {
+                        println!("Register 0 = {} halts after {} instructions", reg0, steps);
+                    }
+
+                    num_halting += 1;
+                }
+            }
+
+            num_halting
+        });
+
+        println!(
+            "{} of {} values halt within {} instructions",
+            num_halting,
+            reg0_range.count(),
+            max_steps
+        );
+
+        return Ok(());
+    }
 
     let debug = matches.is_present("debug");
     let p1 = matches.is_present("p1");
     let p2 = matches.is_present("p2");
     let reg0 = matches.value_of("reg0").unwrap_or("0").parse()?;
-    let special_reg = matches.value_of("special_reg").unwrap().parse::<usize>()?;
+    // Only the parts need to know which register is the special one, and
+    // other programs (like day 19's) might not have one at all.
+    let special_reg = if p1 || p2 {
+        match matches.value_of("special_reg") {
+            Some(special_reg_str) => {
+                let special_reg = special_reg_str
+                    .parse()
+                    .map_err(|_| anyhow!("Provided special register is not a number"))?;
+
+                if special_reg >= NUM_REGISTERS {
+                    bail!("Provided special register doesn't exist");
+                }
+
+                special_reg
+            }
+            None => find_special_reg(&code)?,
+        }
+    } else {
+        0
+    };
 
+    timing::repeat("Solving", repeat, time, || {
+        let show = mem::take(&mut first_run);
+        // Stepping through by hand only makes sense once.
+        let debug = debug && show;
+
+        let (part1, part2, regs) =
+            run_watching_special_reg(ins_pointer, &code, reg0, special_reg, p1, p2, debug);
+
+        if show {
+            if let Some(part1) = part1 {
+                println!("Part 1: {:?}", part1);
+            }
+
+            if let Some(part2) = part2 {
+                println!("Part 2: {:?}", part2);
+            }
+
+            println!("Final registers: {:?}", regs);
+        }
+    });
+
+    Ok(())
+}
+
+// Runs the program, watching what's in the special register whenever an eqrr
+// compares it to register 0. The first value it has there is the Part 1
+// answer, since that value in register 0 would halt the program soonest. The
+// values start repeating eventually, and the last new one before they do is
+// the Part 2 answer, since it'd take the longest to halt on. The program stops
+// as soon as it's found the parts that were asked for, or when it halts, and
+// this gives back whatever it found along with the final registers.
+pub fn run_watching_special_reg(
+    ins_pointer: usize,
+    code: &[Instruction],
+    reg0: usize,
+    special_reg: usize,
+    p1: bool,
+    p2: bool,
+    debug: bool,
+) -> (Option<usize>, Option<usize>, Vec<usize>) {
     let mut regs = vec![reg0, 0, 0, 0, 0, 0];
+    let (mut part1, mut part2) = (None, None);
 
     let mut prev_special_regs = vec![];
 
@@ -56,14 +179,16 @@ pub fn main() -> Result<(), anyhow::Error> {
 
         if ins.name == "eqrr" {
             if p1 && prev_special_regs.is_empty() {
-                println!("Part 1: {:?}", regs[special_reg]);
+                part1 = Some(regs[special_reg]);
+
                 if !p2 {
                     break;
                 }
             }
 
             if p2 && prev_special_regs.contains(&regs[special_reg]) {
-                println!("Part 2: {:?}", prev_special_regs.last().unwrap());
+                part2 = prev_special_regs.last().copied();
+
                 break;
             }
 
@@ -75,171 +200,88 @@ pub fn main() -> Result<(), anyhow::Error> {
         regs[ins_pointer] += 1;
     }
 
-    println!("Final registers: {:?}", regs);
-
-    Ok(())
+    (part1, part2, regs)
 }
 
-fn parse_input(code_str: &str) -> Result<(usize, Vec<Instruction>), anyhow::Error> {
-    let mut code_lines = code_str.lines();
-
-    let ins_pointer = code_lines
-        .next()
-        .map(|s| s.trim_start_matches("#ip "))
-        .ok_or_else(|| anyhow!("Instruction pointer not found"))?
-        .parse()?;
-
-    let code = code_lines
-        .map(|c| -> Result<Instruction, anyhow::Error> {
-            let (op_str, inp1, inp2, output_reg) = c
-                .split_whitespace()
-                .collect_tuple()
-                .ok_or_else(|| anyhow!("Instruction not in correct format"))?;
-
-            let inp1: usize = inp1.parse()?;
-            let inp2: usize = inp2.parse()?;
-            let output_reg: usize = output_reg.parse()?;
-
-            Ok(match op_str {
-                "addr" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(ops::Add::add),
-                    input: [Value::Reg(inp1), Value::Reg(inp2)],
-                    output_reg,
-                },
-                "addi" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(ops::Add::add),
-                    input: [Value::Reg(inp1), Value::Imm(inp2)],
-                    output_reg,
-                },
-                "mulr" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(ops::Mul::mul),
-                    input: [Value::Reg(inp1), Value::Reg(inp2)],
-                    output_reg,
-                },
-                "muli" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(ops::Mul::mul),
-                    input: [Value::Reg(inp1), Value::Imm(inp2)],
-                    output_reg,
-                },
-                "banr" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(ops::BitAnd::bitand),
-                    input: [Value::Reg(inp1), Value::Reg(inp2)],
-                    output_reg,
-                },
-                "bani" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(ops::BitAnd::bitand),
-                    input: [Value::Reg(inp1), Value::Imm(inp2)],
-                    output_reg,
-                },
-                "borr" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(ops::BitOr::bitor),
-                    input: [Value::Reg(inp1), Value::Reg(inp2)],
-                    output_reg,
-                },
-                "bori" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(ops::BitOr::bitor),
-                    input: [Value::Reg(inp1), Value::Imm(inp2)],
-                    output_reg,
-                },
-                "setr" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(|a, _| a),
-                    input: [Value::Reg(inp1), Value::Imm(inp2)],
-                    output_reg,
-                },
-                "seti" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(|a, _| a),
-                    input: [Value::Imm(inp1), Value::Imm(inp2)],
-                    output_reg,
-                },
-                "gtir" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(|a, b| (a > b) as usize),
-                    input: [Value::Imm(inp1), Value::Reg(inp2)],
-                    output_reg,
-                },
-                "gtri" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(|a, b| (a > b) as usize),
-                    input: [Value::Reg(inp1), Value::Imm(inp2)],
-                    output_reg,
-                },
-                "gtrr" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(|a, b| (a > b) as usize),
-                    input: [Value::Reg(inp1), Value::Reg(inp2)],
-                    output_reg,
-                },
-                "eqir" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(|a, b| (a == b) as usize),
-                    input: [Value::Imm(inp1), Value::Reg(inp2)],
-                    output_reg,
-                },
-                "eqri" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(|a, b| (a == b) as usize),
-                    input: [Value::Reg(inp1), Value::Imm(inp2)],
-                    output_reg,
-                },
-                "eqrr" => Instruction {
-                    name: op_str.to_string(),
-                    operation: Box::new(|a, b| (a == b) as usize),
-                    input: [Value::Reg(inp1), Value::Reg(inp2)],
-                    output_reg,
-                },
-                _ => bail!("Invalid operation"),
-            })
+// The program halts once an eqrr finds that register 0 is equal to another
+// register, and that other register is the special one. There should only be
+// one of those, since otherwise there'd be no telling which one to look at.
+pub fn find_special_reg(code: &[Instruction]) -> Result<usize, anyhow::Error> {
+    let candidates = code
+        .iter()
+        .filter(|ins| ins.name == "eqrr")
+        .filter_map(|ins| match ins.input {
+            [Value::Reg(0), Value::Reg(reg)] | [Value::Reg(reg), Value::Reg(0)] if reg != 0 => {
+                Some(reg)
+            }
+            _ => None,
         })
-        .collect::<Result<Vec<_>, _>>()?;
+        .unique()
+        .collect_vec();
 
-    Ok((ins_pointer, code))
+    match candidates.as_slice() {
+        [special_reg] => Ok(*special_reg),
+        [] => bail!(
+            "Couldn't find an eqrr comparing register 0 to another register, use --special-reg"
+        ),
+        _ => bail!(
+            "Registers {} are all compared to register 0, use --special-reg to pick one",
+            candidates.iter().format(", ")
+        ),
+    }
 }
 
-struct Instruction {
-    name: String,
-    operation: Box<dyn Fn(usize, usize) -> usize>,
-    input: [Value; 2],
-    output_reg: usize,
-}
+// Runs the program with nothing but register 0 set, and returns how many
+// instructions it took to halt, or None if it's still going after max_steps.
+fn run_until_halt(
+    ins_pointer: usize,
+    code: &[Instruction],
+    reg0: usize,
+    max_steps: usize,
+) -> Option<usize> {
+    let mut regs = vec![reg0, 0, 0, 0, 0, 0];
+    let mut steps = 0;
 
-impl Instruction {
-    fn execute(&self, regs: &mut Vec<usize>) {
-        regs[self.output_reg] = ops::Fn::call(
-            &self.operation,
-            self.input
-                .iter()
-                .map(|v| match v {
-                    Value::Reg(r) => regs[*r],
-                    Value::Imm(i) => *i,
-                })
-                .collect_tuple()
-                .unwrap(),
-        );
-    }
-}
+    while let Some(ins) = code.get(regs[ins_pointer]) {
+        if steps == max_steps {
+            return None;
+        }
 
-impl fmt::Debug for Instruction {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{} = {} {:?} {:?}",
-            self.output_reg, self.name, self.input[0], self.input[1]
-        )
+        ins.execute(&mut regs);
+        regs[ins_pointer] += 1;
+        steps += 1;
     }
+
+    Some(steps)
 }
 
-#[derive(Debug)]
-enum Value {
-    Reg(usize),
-    Imm(usize),
+// Either min..max or min..=max, like Rust's own ranges.
+fn parse_range(range_str: &str) -> Result<Range<usize>, anyhow::Error> {
+    let (min_str, rest) = range_str
+        .splitn(2, "..")
+        .collect_tuple()
+        .ok_or_else(|| anyhow!("Range {:?} isn't in the form min..max", range_str))?;
+    let (max_str, inclusive) = match rest.strip_prefix('=') {
+        Some(max_str) => (max_str, true),
+        None => (rest, false),
+    };
+
+    let min = min_str
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("Start of the range {:?} is not a number", range_str))?;
+    let max: usize = max_str
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("End of the range {:?} is not a number", range_str))?;
+
+    // min..=max is the same thing as min..max + 1.
+    let end = if inclusive {
+        max.checked_add(1)
+            .ok_or_else(|| anyhow!("End of the range {:?} is too big", range_str))?
+    } else {
+        max
+    };
+
+    Ok(min..end)
 }
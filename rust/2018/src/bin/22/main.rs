@@ -1,66 +1,111 @@
-#![feature(default_free_fn)]
-
-use binary_heap_plus::*;
-use cached::proc_macro::cached;
+use aoc_2018_rust::{
+    geometry::Point,
+    input::read_input,
+    search::astar,
+    timing::{self, Timer},
+};
+use clap::{App, Arg};
+use colored::Colorize;
 use itertools::Itertools;
 use std::{
-    cmp::{max, min, Reverse},
-    collections::HashSet,
-    default::default,
-    env,
+    collections::HashMap,
     error::Error,
-    fmt, fs,
     hash::{Hash, Hasher},
     rc::Rc,
 };
 
+pub type Location = Point<usize, 2>;
+
 pub fn main() -> Result<(), Box<dyn Error>> {
-    let args = env::args().collect_vec();
+    let matches = App::new("2018-22")
+        .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
+        .arg(Arg::from_usage(
+            "[show_path] --show-path 'Print every step of the quickest path to the target'",
+        ))
+        .arg(Arg::from_usage(
+            "[render] --render 'Draw the cave with the quickest path to the target on it'",
+        ))
+        .arg(Arg::from_usage(
+            "[time] --time 'Print how long parsing and solving take'",
+        ))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
+        .get_matches();
 
-    let input_filename = if args.len() == 2 {
-        &args[1]
-    } else {
-        "input.txt"
-    };
+    let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| "Provided repeat count is not a positive number")?;
 
-    let cave_info_str = fs::read_to_string(input_filename)?;
+    let parse_timer = Timer::start("Parsing", time);
+    let cave_info_str = read_input(input_filename)?;
 
     let (depth, target) = parse_input(&cave_info_str)?;
+    drop(parse_timer);
+
+    let (risk, search_result) = timing::repeat("Solving", repeat, time, || {
+        (total_risk(depth, target), cave_search(depth, target))
+    });
+
+    println!("Total risk level: {}", risk);
+
+    let (min_time, goal) = search_result.expect("No path found");
 
-    let result = cave_search(depth, target).expect("No path found");
+    println!("Minimum time to target: {}", min_time);
 
-    println!("Minimum time to target: {}", result.path_cost);
+    let path = reconstruct_path(&goal);
+
+    if matches.is_present("show_path") {
+        for (location, tool) in &path {
+            match tool {
+                Some(tool) => println!("{:?} with {:?}", location, tool),
+                None => println!("{:?} with nothing", location),
+            }
+        }
+    }
+
+    if matches.is_present("render") {
+        print!("{}", render_cave(depth, target, &path));
+    }
 
     Ok(())
 }
 
-fn cave_search(depth: usize, target: Location) -> Option<CaveNode> {
+fn total_risk(depth: usize, target: Location) -> usize {
+    let mut erosion_levels = ErosionLevels::new(depth, target);
+
+    (0..=target[1])
+        .cartesian_product(0..=target[0])
+        .map(|(y, x)| get_region_type(erosion_levels.get(Point([x, y]))) as usize)
+        .sum()
+}
+
+// Gives back the goal node too, so that the path to it can be reconstructed.
+pub fn cave_search(depth: usize, target: Location) -> Option<(usize, CaveNode)> {
     const MOVE_COST: usize = 1;
     const SWITCH_COST: usize = 7;
 
     use Tool::*;
 
-    // path_cost and prev don't matter here
+    let start = CaveNode {
+        location: Point([0, 0]),
+        tool: Some(Torch),
+        prev: None,
+    };
+
     let goal = CaveNode {
         location: target,
         tool: Some(Torch),
-        ..default()
+        prev: None,
     };
 
-    let mut frontier = BinaryHeap::from_vec_cmp(
-        vec![CaveNode {
-            tool: Some(Torch),
-            ..default()
-        }],
-        KeyComparator(|n: &CaveNode| {
-            Reverse(
-                n.path_cost
-                    + n.location.manhattan_distance(&target) * MOVE_COST
-                    + if n.tool != goal.tool { SWITCH_COST } else { 0 },
-            )
-        }),
-    );
-
     fn possible_tools(region: Region) -> Vec<Option<Tool>> {
         match region {
             Region::Rocky => vec![Some(Torch), Some(ClimbingGear)],
@@ -69,68 +114,139 @@ fn cave_search(depth: usize, target: Location) -> Option<CaveNode> {
         }
     }
 
-    let expand = |node: &CaveNode| -> Vec<CaveNode> {
+    let mut erosion_levels = ErosionLevels::new(depth, target);
+
+    let expand = |node: &CaveNode| -> Vec<(CaveNode, usize)> {
         let mut expanded = vec![];
+        let prev = Some(Rc::new(node.clone()));
 
         // Add all possibilities for switching tools
-        let node_region = get_region_type(calculate_erosion_level(node.location, depth, target));
+        let node_region = get_region_type(erosion_levels.get(node.location));
 
         for other_tool in possible_tools(node_region) {
             if other_tool != node.tool {
-                expanded.push(CaveNode {
-                    location: node.location,
-                    tool: other_tool,
-                    path_cost: node.path_cost + SWITCH_COST,
-                    prev: Some(Rc::new(node.clone())),
-                });
+                expanded.push((
+                    CaveNode {
+                        location: node.location,
+                        tool: other_tool,
+                        prev: prev.clone(),
+                    },
+                    SWITCH_COST,
+                ));
             }
         }
 
         // Add all possibilities for moving to an adjacent region
-        for adj in node.location.adjacent() {
-            let adj_region = get_region_type(calculate_erosion_level(adj, depth, target));
+        for adj in node.location.orthogonal_neighbors() {
+            let adj_region = get_region_type(erosion_levels.get(adj));
 
             if possible_tools(adj_region).contains(&node.tool) {
-                expanded.push(CaveNode {
-                    location: adj,
-                    tool: node.tool,
-                    path_cost: node.path_cost + MOVE_COST,
-                    prev: Some(Rc::new(node.clone())),
-                });
+                expanded.push((
+                    CaveNode {
+                        location: adj,
+                        tool: node.tool,
+                        prev: prev.clone(),
+                    },
+                    MOVE_COST,
+                ));
             }
         }
 
         expanded
     };
 
-    let mut explored = HashSet::new();
+    // Every step towards the target takes at least a minute, and arriving
+    // without the torch means switching to it at some point, so this never
+    // overestimates. The goal only has to match on location and tool, so the
+    // last node on the path is the one with the real chain of previous nodes.
+    let heuristic = |n: &CaveNode| {
+        n.location.manhattan_distance::<usize>(&target) * MOVE_COST
+            + if n.tool != goal.tool { SWITCH_COST } else { 0 }
+    };
 
-    while let Some(current) = frontier.pop() {
-        if explored.contains(&current) {
-            continue;
-        }
+    let (min_time, mut path) = astar(start, goal.clone(), expand, heuristic)?;
 
-        if current == goal {
-            return Some(current);
-        }
+    Some((min_time, path.pop().unwrap()))
+}
+
+// Follows the chain of previous nodes back to the mouth of the cave, giving
+// every location and tool along the way in the order they were visited.
+fn reconstruct_path(node: &CaveNode) -> Vec<(Location, Option<Tool>)> {
+    let mut path = vec![(node.location, node.tool)];
+    let mut current = node;
 
-        for next in expand(&current) {
-            frontier.push(next);
+    while let Some(prev) = &current.prev {
+        path.push((prev.location, prev.tool));
+        current = prev;
+    }
+
+    path.reverse();
+
+    path
+}
+
+// Draws every region from the mouth out to a little past the target, or past
+// wherever the path went if that's further. The path is drawn over the top
+// in a different colour for each tool, which is the one being held when
+// leaving that region.
+fn render_cave(depth: usize, target: Location, path: &[(Location, Option<Tool>)]) -> String {
+    const RENDER_MARGIN: usize = 5;
+
+    let mut erosion_levels = ErosionLevels::new(depth, target);
+    // Later steps overwrite earlier ones, so switching tools in a region
+    // shows the tool it was switched to.
+    let path_tools = path.iter().copied().collect::<HashMap<_, _>>();
+
+    let (max_x, max_y) = path.iter().fold(
+        (target[0] + RENDER_MARGIN, target[1] + RENDER_MARGIN),
+        |(max_x, max_y), (location, _)| (max_x.max(location[0]), max_y.max(location[1])),
+    );
+
+    let mut output = String::new();
+
+    for y in 0..=max_y {
+        for x in 0..=max_x {
+            let location = Point([x, y]);
+
+            let symbol = match location {
+                Point([0, 0]) => "M",
+                _ if location == target => "T",
+                _ => match get_region_type(erosion_levels.get(location)) {
+                    Region::Rocky => ".",
+                    Region::Wet => "=",
+                    Region::Narrow => "|",
+                },
+            };
+
+            let symbol = match path_tools.get(&location) {
+                Some(Some(Tool::Torch)) => symbol.yellow().bold(),
+                Some(Some(Tool::ClimbingGear)) => symbol.green().bold(),
+                Some(None) => symbol.cyan().bold(),
+                None if symbol == "M" || symbol == "T" => symbol.bold(),
+                None => symbol.normal(),
+            };
+
+            output += &symbol.to_string();
         }
 
-        explored.insert(current);
+        output += "\n";
     }
 
-    None
+    output += &format!(
+        "{} torch, {} climbing gear, {} neither\n",
+        "#".yellow().bold(),
+        "#".green().bold(),
+        "#".cyan().bold()
+    );
+
+    output
 }
 
-#[derive(Clone, Default)]
-struct CaveNode {
+#[derive(Clone, Debug)]
+pub struct CaveNode {
     location: Location,
     tool: Option<Tool>,
-    path_cost: usize,
-    // We use Rc as opposed to Box here because it can be cloned really inexpensively,
-    // because its clone points to the same heap allocation
+    // How we got here, which isn't part of what makes two nodes the same.
     prev: Option<Rc<CaveNode>>,
 }
 
@@ -149,30 +265,69 @@ impl Hash for CaveNode {
     }
 }
 
-impl fmt::Debug for CaveNode {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_tuple("")
-            .field(&self.location)
-            .field(&self.tool)
-            .field(&self.path_cost)
-            .finish()
-    }
+// Erosion levels for every region from the mouth of the cave out to the
+// furthest point we've needed so far. Each level depends on the ones above and
+// to the left of it, so they're filled in row by row, and the table grows
+// whenever the search wanders outside of it.
+struct ErosionLevels {
+    depth: usize,
+    target: Location,
+    levels: Vec<Vec<usize>>,
 }
 
-#[cached]
-fn calculate_erosion_level(location: Location, depth: usize, target: Location) -> usize {
-    let geologic_index = match location {
-        Location { x: 0, y: 0 } => 0,
-        Location { x, y } if x == target.x && y == target.y => 0,
-        Location { x, y: 0 } => x * 16807,
-        Location { x: 0, y } => y * 48271,
-        Location { x, y } => {
-            calculate_erosion_level(Location { x: x - 1, y }, depth, target)
-                * calculate_erosion_level(Location { x, y: y - 1 }, depth, target)
+impl ErosionLevels {
+    // The search almost always ends up going a little past the target, so
+    // it's worth making room for that up front.
+    const MARGIN: usize = 50;
+
+    fn new(depth: usize, target: Location) -> Self {
+        let mut erosion_levels = Self {
+            depth,
+            target,
+            levels: vec![],
+        };
+
+        erosion_levels.grow_to(Point([target[0] + Self::MARGIN, target[1] + Self::MARGIN]));
+
+        erosion_levels
+    }
+
+    fn get(&mut self, location: Location) -> usize {
+        self.grow_to(location);
+
+        self.levels[location[1]][location[0]]
+    }
+
+    fn grow_to(&mut self, location: Location) {
+        // Every row is always the same length, so checking the first one's
+        // enough to know whether we're already covering this location.
+        if location[1] < self.levels.len() && location[0] < self.levels[0].len() {
+            return;
         }
-    };
 
-    (geologic_index + depth) % 20183
+        let width = self
+            .levels
+            .first()
+            .map_or(0, |row| row.len())
+            .max(location[0] + 1);
+        let height = self.levels.len().max(location[1] + 1);
+
+        self.levels.resize_with(height, Vec::new);
+
+        for y in 0..height {
+            for x in self.levels[y].len()..width {
+                let geologic_index = match Point([x, y]) {
+                    Point([0, 0]) => 0,
+                    point if point == self.target => 0,
+                    Point([x, 0]) => x * 16807,
+                    Point([0, y]) => y * 48271,
+                    Point([x, y]) => self.levels[y][x - 1] * self.levels[y - 1][x],
+                };
+
+                self.levels[y].push((geologic_index + self.depth) % 20183);
+            }
+        }
+    }
 }
 
 fn get_region_type(erosion_level: usize) -> Region {
@@ -187,9 +342,10 @@ fn get_region_type(erosion_level: usize) -> Region {
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 enum Region {
-    Rocky,
-    Wet,
-    Narrow,
+    // The discriminants are the risk levels.
+    Rocky = 0,
+    Wet = 1,
+    Narrow = 2,
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
@@ -198,7 +354,7 @@ enum Tool {
     ClimbingGear,
 }
 
-fn parse_input(cave_info_str: &str) -> Result<(usize, Location), &str> {
+pub fn parse_input(cave_info_str: &str) -> Result<(usize, Location), &str> {
     let cave_info_lines = cave_info_str.lines().collect_vec();
     let (depth_line, target_line) = (cave_info_lines[0], cave_info_lines[1]);
 
@@ -218,61 +374,33 @@ fn parse_input(cave_info_str: &str) -> Result<(usize, Location), &str> {
 
     Ok((
         depth_str.parse().map_err(|_| "Depth is not a number")?,
-        Location {
-            x: target_x_str
+        Point([
+            target_x_str
                 .parse()
                 .map_err(|_| "Target X is not a number")?,
-            y: target_y_str
+            target_y_str
                 .parse()
                 .map_err(|_| "Target Y is not a number")?,
-        },
+        ]),
     ))
 }
 
-#[derive(Eq, PartialEq, Default, Hash, Copy, Clone)]
-struct Location {
-    x: usize,
-    y: usize,
-}
-
-impl Location {
-    fn manhattan_distance(&self, other: &Self) -> usize {
-        (max(self.x, other.x) - min(self.x, other.x))
-            + (max(self.y, other.y) - min(self.y, other.y))
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn adjacent(&self) -> Vec<Self> {
-        let mut adjacent_locations = vec![
-            Location {
-                x: self.x,
-                y: self.y + 1,
-            },
-            Location {
-                x: self.x + 1,
-                y: self.y,
-            },
-        ];
-
-        if self.y > 0 {
-            adjacent_locations.push(Location {
-                x: self.x,
-                y: self.y - 1,
-            });
-        }
+    #[test]
+    fn sample_total_risk() {
+        let (depth, target) = parse_input(include_str!("input_small_2.txt")).unwrap();
 
-        if self.x > 0 {
-            adjacent_locations.push(Location {
-                x: self.x - 1,
-                y: self.y,
-            });
-        }
-
-        adjacent_locations
+        assert_eq!((depth, target), (510, Point([10, 10])));
+        assert_eq!(total_risk(depth, target), 114);
     }
-}
 
-impl fmt::Debug for Location {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_tuple("").field(&self.x).field(&self.y).finish()
+    #[test]
+    fn mouth_only_total_risk() {
+        // The mouth and the target are always rocky, and here they're the
+        // same region.
+        assert_eq!(total_risk(510, Point([0, 0])), 0);
     }
 }
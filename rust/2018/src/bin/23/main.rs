@@ -1,36 +1,125 @@
 use anyhow::{anyhow, Context};
+use aoc_2018_rust::{
+    geometry::Point,
+    input::read_input,
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
 use itertools::Itertools;
-use std::{fmt, fs, num::ParseIntError, str::FromStr};
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+pub type Location = Point<isize, 3>;
+
+const ORIGIN: Location = Point([0, 0, 0]);
 
 pub fn main() -> Result<(), anyhow::Error> {
-    let matches = App::new("2018-23")
+    let app = App::new("2018-23")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
-        .get_matches();
+        .arg(Arg::from_usage("[strongest] --strongest 'Count the bots in range of the bot with the strongest signal'"))
+        .arg(Arg::from_usage("[from_point] --from-point 'Count the bots that have a given x,y,z point in range'").takes_value(true))
+        .arg(Arg::from_usage("[time] --time 'Print how long parsing and solving take'"))
+        .arg(Arg::from_usage("[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'").default_value("1"));
+
+    #[cfg(feature = "z3")]
+    let app = app.arg(Arg::from_usage(
+        "[z3] --z3 'Use z3 to find the best point instead of searching for it directly'",
+    ));
+
+    let matches = app.get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
 
-    let bot_info_str = fs::read_to_string(input_filename)?;
+    let parse_timer = Timer::start("Parsing", time);
+    let bot_info_str = read_input(input_filename)?;
     let bots = parse_input(&bot_info_str)?;
+    drop(parse_timer);
+
+    if matches.is_present("strongest") || matches.is_present("from_point") {
+        let point: Option<Location> = matches
+            .value_of("from_point")
+            .map(|point_str| point_str.parse())
+            .transpose()?;
+
+        let (strongest, num_in_range_of_point) = timing::repeat("Solving", repeat, time, || {
+            (
+                matches
+                    .is_present("strongest")
+                    .then(|| in_range_of_strongest(&bots)),
+                point.map(|point| bots_in_range_of(&bots, &point)),
+            )
+        });
+
+        if let Some(strongest) = strongest {
+            let (strongest_bot, num_in_range) =
+                strongest.ok_or_else(|| anyhow!("There are no bots"))?;
+
+            println!(
+                "Bots in range of the strongest bot {:?}: {}",
+                strongest_bot, num_in_range
+            );
+        }
+
+        if let (Some(point), Some(num_in_range)) = (point, num_in_range_of_point) {
+            println!("Bots with {:?} in range: {}", point, num_in_range);
+        }
+
+        return Ok(());
+    }
 
-    let best_point = find_best_point_z3(bots).ok_or_else(|| anyhow!("No best point found"))?;
+    let best_point = timing::repeat("Solving", repeat, time, || {
+        #[cfg(feature = "z3")]
+        {
+            if matches.is_present("z3") {
+                return find_best_point_z3(&bots);
+            }
+        }
+
+        find_best_point_octree(&bots)
+    });
+
+    let best_point = best_point.ok_or_else(|| anyhow!("No best point found"))?;
 
     println!(
         "Best teleporation point: {:?}. Manhattan distance to origin: {}",
         best_point,
-        (best_point.x + best_point.y + best_point.z)
+        best_point.manhattan_distance::<usize>(&ORIGIN)
     );
 
     Ok(())
 }
 
+// Gives back the bot with the strongest signal, along with how many bots
+// (including itself) are in its range, or None if there aren't any bots.
+pub fn in_range_of_strongest(bots: &[Bot]) -> Option<(&Bot, usize)> {
+    let strongest_bot = bots.iter().max_by_key(|b| b.signal_radius)?;
+
+    Some((
+        strongest_bot,
+        bots.iter()
+            .filter(|b| strongest_bot.in_range_of(&b.location))
+            .count(),
+    ))
+}
+
+pub fn bots_in_range_of(bots: &[Bot], point: &Location) -> usize {
+    bots.iter().filter(|b| b.in_range_of(point)).count()
+}
+
 // This is basically cheating because it's stolen from /u/mserrano on the
 // /r/AdventOfCode solutions thread for this problem, and even if it wasn't
 // stolen it's a really unsatisfying solution because it basically just
 // assembles a problem description and asks another, far more advanced,
 // third-party dependency to just magically solve it. But I had no idea how to
-// solve it and this is really slow anyway.
-fn find_best_point_z3(bots: Vec<Bot>) -> Option<Location> {
+// solve it and this is really slow anyway. Since z3 is a pretty heavy
+// dependency, this is only available with the z3 feature enabled.
+#[cfg(feature = "z3")]
+fn find_best_point_z3(bots: &[Bot]) -> Option<Location> {
     use z3::{ast::*, *};
 
     let cfg = Config::new();
@@ -53,9 +142,9 @@ fn find_best_point_z3(bots: Vec<Bot>) -> Option<Location> {
 
     for (i, bot) in bots.iter().enumerate() {
         let (bot_x, bot_y, bot_z, bot_radius) = (
-            Int::from_i64(&ctx, bot.location.x as i64),
-            Int::from_i64(&ctx, bot.location.y as i64),
-            Int::from_i64(&ctx, bot.location.z as i64),
+            Int::from_i64(&ctx, bot.location[0] as i64),
+            Int::from_i64(&ctx, bot.location[1] as i64),
+            Int::from_i64(&ctx, bot.location[2] as i64),
             Int::from_u64(&ctx, bot.signal_radius as u64),
         );
 
@@ -101,14 +190,116 @@ fn find_best_point_z3(bots: Vec<Bot>) -> Option<Location> {
         model.eval(&z).unwrap().as_i64().unwrap() as isize,
     );
 
-    Some(Location {
-        x: res_x,
-        y: res_y,
-        z: res_z,
-    })
+    Some(Point([res_x, res_y, res_z]))
+}
+
+// The idea is to start with one huge cube that covers every bot and keep
+// splitting it into eighths, always zooming in on whichever cube we've seen so
+// far that's in range of the most bots. The number of bots in range of a cube
+// can only stay the same or go down as it gets smaller, and its distance to the
+// origin can only stay the same or go up, so the first single point that comes
+// out of the queue is guaranteed to be the best one.
+pub fn find_best_point_octree(bots: &[Bot]) -> Option<Location> {
+    let min_coord = bots.iter().flat_map(|b| b.location.iter().copied()).min()?;
+    let max_coord = bots.iter().flat_map(|b| b.location.iter().copied()).max()?;
+
+    // Halving a power of 2 always gives us whole cubes all the way down.
+    let mut size = 1;
+    while min_coord + size <= max_coord {
+        size *= 2;
+    }
+
+    let bounding_cube = Cube {
+        min: Point([min_coord; 3]),
+        size,
+    };
+
+    let mut queue = BinaryHeap::new();
+    queue.push(SearchNode::new(bounding_cube, bots));
+
+    while let Some(SearchNode { cube, .. }) = queue.pop() {
+        if cube.size == 1 {
+            return Some(cube.min);
+        }
+
+        for octant in cube.octants() {
+            queue.push(SearchNode::new(octant, bots));
+        }
+    }
+
+    None
+}
+
+// Private helper to make find_best_point_octree easier to keep track of. The
+// fields are ordered so that a max heap pops the cube in range of the most
+// bots first, then the one closest to the origin, then the smallest one.
+#[derive(Eq, PartialEq, Ord, PartialOrd)]
+struct SearchNode {
+    bots_in_range: usize,
+    distance_to_origin: Reverse<usize>,
+    size: Reverse<isize>,
+    cube: Cube,
+}
+
+impl SearchNode {
+    fn new(cube: Cube, bots: &[Bot]) -> Self {
+        Self {
+            bots_in_range: bots
+                .iter()
+                .filter(|b| cube.distance(&b.location) <= b.signal_radius)
+                .count(),
+            distance_to_origin: Reverse(cube.distance(&ORIGIN)),
+            size: Reverse(cube.size),
+            cube,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Ord, PartialOrd)]
+struct Cube {
+    min: Location,
+    size: isize,
+}
+
+impl Cube {
+    // The manhattan distance from a location to the closest point in the cube.
+    fn distance(&self, location: &Location) -> usize {
+        let axis_distance = |min: isize, coord: isize| {
+            let max = min + self.size - 1;
+
+            if coord < min {
+                (min - coord) as usize
+            } else if coord > max {
+                (coord - max) as usize
+            } else {
+                0
+            }
+        };
+
+        self.min
+            .iter()
+            .zip(location.iter())
+            .map(|(&min, &coord)| axis_distance(min, coord))
+            .sum()
+    }
+
+    fn octants(&self) -> Vec<Self> {
+        let half = self.size / 2;
+
+        (0..8)
+            .map(|i| Cube {
+                min: Point([
+                    self.min[0] + if i & 1 == 0 { 0 } else { half },
+                    self.min[1] + if i & 2 == 0 { 0 } else { half },
+                    self.min[2] + if i & 4 == 0 { 0 } else { half },
+                ]),
+                size: half,
+            })
+            .collect()
+    }
 }
 
-fn parse_input(bot_info_str: &str) -> Result<Vec<Bot>, anyhow::Error> {
+pub fn parse_input(bot_info_str: &str) -> Result<Vec<Bot>, anyhow::Error> {
     let mut bots = vec![];
 
     for bot_info_line in bot_info_str.lines() {
@@ -135,57 +326,13 @@ fn parse_input(bot_info_str: &str) -> Result<Vec<Bot>, anyhow::Error> {
 }
 
 #[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
-struct Bot {
+pub struct Bot {
     location: Location,
     signal_radius: usize,
 }
 
-#[derive(Eq, PartialEq, Hash, Copy, Clone)]
-struct Location {
-    x: isize,
-    y: isize,
-    z: isize,
-}
-
-impl FromStr for Location {
-    type Err = ParseLocationError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        use ParseLocationError::*;
-
-        let (x_str, y_str, z_str) = s.split(',').collect_tuple().ok_or(CommaFormatError)?;
-
-        Ok(Self {
-            x: x_str.trim().parse().map_err(|e| ParseCoordinateError {
-                coord: 'x',
-                source: e,
-            })?,
-            y: y_str.trim().parse().map_err(|e| ParseCoordinateError {
-                coord: 'y',
-                source: e,
-            })?,
-            z: z_str.trim().parse().map_err(|e| ParseCoordinateError {
-                coord: 'z',
-                source: e,
-            })?,
-        })
-    }
-}
-
-#[derive(thiserror::Error, Debug)]
-pub enum ParseLocationError {
-    #[error("String isn't formatted like 'x,y,z'")]
-    CommaFormatError,
-    #[error("The coordinate {} can't be parsed into an isize", coord)]
-    ParseCoordinateError { coord: char, source: ParseIntError },
-}
-
-impl fmt::Debug for Location {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_tuple("")
-            .field(&self.x)
-            .field(&self.y)
-            .field(&self.z)
-            .finish()
+impl Bot {
+    fn in_range_of(&self, location: &Location) -> bool {
+        self.location.manhattan_distance::<usize>(location) <= self.signal_radius
     }
 }
@@ -1,11 +1,15 @@
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
+use aoc_2018_rust::{
+    input::read_input,
+    timing::{self, Timer},
+};
 use clap::{App, Arg, ArgGroup};
 use itertools::Itertools;
 use regex::Regex;
-use std::cmp::Reverse;
+use std::cmp::{self, Reverse};
 use std::collections::HashSet;
 use std::fmt;
-use std::fs;
+use std::mem;
 
 pub fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2018-24")
@@ -19,42 +23,86 @@ pub fn main() -> Result<(), anyhow::Error> {
                 .multiple(true)
                 .default_value_if("p2", None, "Immune System"),
         )
+        .arg(
+            Arg::from_usage("[max_boost] --max-boost 'Largest boost to try in Part 2'")
+                .requires("p2")
+                .takes_value(true)
+                .default_value_if("p2", None, "1000000"),
+        )
         .group(
             ArgGroup::with_name("part")
                 .args(&["p1", "p2"])
                 .required(true),
         )
+        .arg(Arg::from_usage(
+            "[verbose] -v --verbose 'Print who attacks whom and how many units they kill in every round'",
+        ))
+        .arg(Arg::from_usage(
+            "[time] --time 'Print how long parsing and solving take'",
+        ))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
-
-    let battle_info_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
+
+    let parse_timer = Timer::start("Parsing", time);
+    let battle_info_str = read_input(input_filename)?;
     let groups = parse_input(&battle_info_str)?;
+    drop(parse_timer);
+
+    let verbose = matches.is_present("verbose");
+    // Logging every round of every repeated run would bury everything else.
+    let mut first_run = true;
+    let mut verbose_once = || verbose && mem::take(&mut first_run);
 
     if matches.is_present("p1") {
         println!("Battle without boosts:");
 
-        let no_boost_result = battle_to_end(groups, None, None)
-            .ok_or_else(|| anyhow!("Input results in a stalemate"))?;
+        let no_boost_result = timing::repeat("Solving", repeat, time, || {
+            battle_to_end(groups.clone(), None, None, verbose_once())
+        })
+        .ok_or_else(|| anyhow!("Input results in a stalemate"))?;
         battle_victor_info(&no_boost_result);
     } else if let Some(boosted_armies_iter) = matches.values_of("boosted") {
         let boosted_armies = boosted_armies_iter.collect_vec();
-
-        for boost in 1..=usize::MAX {
-            if let Some(boosted_result) =
-                battle_to_end(groups.clone(), Some(&boosted_armies), Some(boost))
-            {
-                if boosted_armies.contains(&&*boosted_result[0].army) {
-                    println!(
-                        "Battle with a boost of {} to {:?}:",
-                        boost,
-                        boosted_armies.join(", and ")
-                    );
-                    battle_victor_info(&boosted_result);
-                    break;
-                }
-            }
+        let max_boost = matches
+            .value_of("max_boost")
+            .unwrap()
+            .parse()
+            .map_err(|_| anyhow!("Provided max boost is not a number"))?;
+
+        if max_boost == 0 {
+            bail!("Max boost must be at least 1");
         }
+
+        let (boost, boosted_result) = timing::repeat("Solving", repeat, time, || {
+            find_min_boost(&groups, &boosted_armies, max_boost, verbose_once())
+        })
+        .ok_or_else(|| {
+            anyhow!(
+                "No boost up to {} lets {:?} win",
+                max_boost,
+                boosted_armies.join(", and ")
+            )
+        })?;
+
+        println!(
+            "Battle with a boost of {} to {:?}:",
+            boost,
+            boosted_armies.join(", and ")
+        );
+        battle_victor_info(&boosted_result);
     }
 
     Ok(())
@@ -64,14 +112,55 @@ fn battle_victor_info(groups: &[UnitGroup]) {
     println!(
         "{:?} wins with {:?} units left",
         groups[0].army,
-        groups.iter().map(|g| g.num_units).sum::<usize>()
+        total_units(groups)
     );
 }
 
-fn battle_to_end(
+// Finds the smallest boost, between 1 and max_boost, that lets the boosted
+// armies win, along with how the battle ends with it. This assumes that a
+// bigger boost never turns a win back into a loss, which holds for pretty
+// much every input, and lets us binary search instead of trying every boost.
+pub fn find_min_boost(
+    groups: &[UnitGroup],
+    boosted_armies: &[&str],
+    max_boost: usize,
+    verbose: bool,
+) -> Option<(usize, Vec<UnitGroup>)> {
+    // Stalemates count as not winning.
+    let boosted_win = |boost| {
+        if verbose {
+            println!("Trying a boost of {}:", boost);
+        }
+
+        battle_to_end(groups.to_vec(), Some(boosted_armies), Some(boost), verbose)
+            .filter(|result| boosted_armies.contains(&&*result[0].army))
+    };
+
+    // If even the biggest boost isn't enough, no smaller one will be either.
+    let mut best = (max_boost, boosted_win(max_boost)?);
+    // We never actually try a boost of 0, it's just below the lowest boost.
+    let (mut low, mut high) = (0, max_boost);
+
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+
+        match boosted_win(mid) {
+            Some(result) => {
+                high = mid;
+                best = (mid, result);
+            }
+            None => low = mid,
+        }
+    }
+
+    Some(best)
+}
+
+pub fn battle_to_end(
     mut groups: Vec<UnitGroup>,
     boost_armies: Option<&[&str]>,
     boost_amount: Option<usize>,
+    verbose: bool,
 ) -> Option<Vec<UnitGroup>> {
     if let Some(boost_amount) = boost_amount {
         let boost_armies = boost_armies.unwrap();
@@ -83,82 +172,143 @@ fn battle_to_end(
         }
     }
 
-    while groups.iter().any(|g| g.army != groups[0].army) {
-        let new_groups = battle_tick(groups.clone());
+    // Immunities and weaknesses never change over the course of a battle, so
+    // we only need to look them up once rather than on every single tick.
+    let dmg_multipliers = calculate_dmg_multipliers(&groups);
 
-        if new_groups == groups {
-            // Stalemate
-            return None;
+    for round in 1.. {
+        if groups.iter().all(|g| g.army == groups[0].army) {
+            break;
+        }
+
+        if verbose {
+            println!("Round {}:", round);
         }
 
-        groups = new_groups;
+        let units_before = total_units(&groups);
+
+        groups = battle_tick(groups, &dmg_multipliers, verbose);
+
+        // Units can only ever be lost, so if the total is the same then
+        // nobody lost any, and every tick from here on will go exactly the
+        // same way. Comparing the groups themselves wouldn't work, because
+        // they get reordered on every tick.
+        if total_units(&groups) == units_before {
+            return None;
+        }
     }
 
     Some(groups)
 }
 
-fn battle_tick(mut groups: Vec<UnitGroup>) -> Vec<UnitGroup> {
-    groups.sort_unstable_by_key(|g| Reverse((g.effective_power(), g.initiative)));
-
-    let mut attacks = Vec::new();
-
-    fn calculate_attack_dmg(attacker: &UnitGroup, defender: &UnitGroup) -> usize {
-        let mut dmg = attacker.effective_power();
+pub fn total_units(groups: &[UnitGroup]) -> usize {
+    groups.iter().map(|g| g.num_units).sum()
+}
 
-        if defender.immunities.contains(&attacker.attack_dmg_type) {
-            dmg = 0;
-        } else if defender.weaknesses.contains(&attacker.attack_dmg_type) {
-            dmg *= 2;
-        }
+fn battle_tick(
+    mut groups: Vec<UnitGroup>,
+    dmg_multipliers: &[Vec<usize>],
+    verbose: bool,
+) -> Vec<UnitGroup> {
+    // The puzzle breaks every tie with initiative, but made up inputs don't
+    // always have unique initiatives, so whenever that isn't enough, the
+    // group that came first in the input (the one with the lowest id) goes
+    // first, and gets picked first as a target. This means the same input
+    // always plays out the same way, no matter how the groups are ordered.
+    groups.sort_unstable_by_key(|g| (Reverse((g.effective_power(), g.initiative)), g.id));
+
+    // Nothing changes during target selection, so we can work out every
+    // group's effective power once up front instead of once per comparison.
+    let effective_powers = groups.iter().map(UnitGroup::effective_power).collect_vec();
 
-        dmg
-    }
+    let mut attacks = Vec::new();
+    let mut targeted = vec![false; groups.len()];
 
     for (pos, group) in groups.iter().enumerate() {
         let best_enemy = groups
             .iter()
             .enumerate()
-            .filter_map(|(other_pos, other)| {
-                if other.army != group.army && attacks.iter().all(|(_, e_p)| *e_p != other_pos) {
-                    Some((other, other_pos, calculate_attack_dmg(&group, &other)))
-                } else {
-                    None
-                }
+            .filter(|&(other_pos, other)| other.army != group.army && !targeted[other_pos])
+            .map(|(other_pos, other)| {
+                (
+                    other_pos,
+                    effective_powers[pos] * dmg_multipliers[group.id][other.id],
+                    other.initiative,
+                    other.id,
+                )
+            })
+            .max_by_key(|&(other_pos, dmg, initiative, id)| {
+                (dmg, effective_powers[other_pos], initiative, Reverse(id))
             })
-            .max_by_key(|&(e, _, dmg)| (dmg, e.effective_power(), e.initiative))
             // This group may already be damaged by the time it gets to attack,
             // so the damage calculated in this phase may not be correct. We can
             // ignore it now.
-            .map(|(_, enemy_pos, _)| enemy_pos);
+            .map(|(enemy_pos, _, _, _)| enemy_pos);
 
         if let Some(enemy_pos) = best_enemy {
+            if verbose {
+                println!("  {:?} targets {:?}", group, groups[enemy_pos]);
+            }
+
+            targeted[enemy_pos] = true;
             attacks.push((pos, enemy_pos));
         }
     }
 
-    attacks.sort_unstable_by_key(|(a_p, _)| Reverse(groups[*a_p].initiative));
+    attacks.sort_unstable_by_key(|(a_p, _)| (Reverse(groups[*a_p].initiative), groups[*a_p].id));
 
     for (attacker_pos, defender_pos) in attacks {
-        // We clone so we can get the &mut defender later
-        let attacker = groups.get(attacker_pos).unwrap().clone();
+        let attacker = &groups[attacker_pos];
 
         if attacker.num_units == 0 {
             // We can't remove it yet because we need to mantain the positions
             continue;
         }
 
-        let defender = groups.get_mut(defender_pos).unwrap();
-
-        let dmg = calculate_attack_dmg(&attacker, &defender);
+        // The attacker may have taken losses since target selection, so its
+        // effective power has to be recalculated here.
+        let dmg =
+            attacker.effective_power() * dmg_multipliers[attacker.id][groups[defender_pos].id];
+        let defender = &mut groups[defender_pos];
 
         // This is usize divison, meaning it'll round down on its own.
-        defender.num_units = defender.num_units.saturating_sub(dmg / defender.unit_hp);
+        let units_killed = cmp::min(dmg / defender.unit_hp, defender.num_units);
+        defender.num_units -= units_killed;
+
+        if verbose {
+            println!(
+                "  {:?} kills {} units, leaving {:?}",
+                groups[attacker_pos], units_killed, groups[defender_pos]
+            );
+        }
     }
 
     groups.into_iter().filter(|g| g.num_units > 0).collect_vec()
 }
 
-fn parse_input(battle_info_str: &str) -> Result<Vec<UnitGroup>, anyhow::Error> {
+// Indexed by the attacker's and then the defender's id. Since ids are
+// assigned at parse time, this stays valid no matter how the groups get
+// reordered or filtered during the battle.
+fn calculate_dmg_multipliers(groups: &[UnitGroup]) -> Vec<Vec<usize>> {
+    let mut dmg_multipliers = vec![vec![0; groups.len()]; groups.len()];
+
+    for attacker in groups {
+        for defender in groups {
+            dmg_multipliers[attacker.id][defender.id] =
+                if defender.immunities.contains(&attacker.attack_dmg_type) {
+                    0
+                } else if defender.weaknesses.contains(&attacker.attack_dmg_type) {
+                    2
+                } else {
+                    1
+                };
+        }
+    }
+
+    dmg_multipliers
+}
+
+pub fn parse_input(battle_info_str: &str) -> Result<Vec<UnitGroup>, anyhow::Error> {
     let army_lines_iter = battle_info_str
         .split("\n\n")
         .map(|army_str| army_str.lines());
@@ -174,7 +324,9 @@ fn parse_input(battle_info_str: &str) -> Result<Vec<UnitGroup>, anyhow::Error> {
     // (weak to radiation)
     // (weak to slashing; immune to cold)
     // This is what introduces most of the complexity of this regex. The rest of
-    // it is pretty straightforward mostly literal matching.
+    // it is pretty straightforward mostly literal matching, except that damage
+    // types are allowed to be hyphenated or more than one word, which some
+    // made up inputs use.
     // We're compiling it here inside of inside the loop for effeciency.
     let group_re: Regex = Regex::new(
         r"(?ix)
@@ -183,7 +335,7 @@ fn parse_input(battle_info_str: &str) -> Result<Vec<UnitGroup>, anyhow::Error> {
             # Matches the immunities and weaknesses
             \s*\(*(?:(?:immune \s+ to \s+ (?P<immunities>[^;\)]+));*\s*|(?:weak \s+ to \s+ (?P<weaknesses>[^;\)]+));*\s*)*\)*
             # Matches the damage attributes
-            \s* with \s+ an \s+ attack \s+ that \s+ does \s+ (?P<dmg>\d+) \s+ (?P<dmg_type>\S+) \s+ damage \s+
+            \s* with \s+ an \s+ attack \s+ that \s+ does \s+ (?P<dmg>\d+) \s+ (?P<dmg_type>[\w-]+(?:\s+[\w-]+)*?) \s+ damage \s+
             # Matches the initiative
             at \s+ initiative \s+ (?P<initiative>\d+)"
     ).unwrap(); // This would only panic if the regex itself is wrong
@@ -196,12 +348,17 @@ fn parse_input(battle_info_str: &str) -> Result<Vec<UnitGroup>, anyhow::Error> {
             .trim_matches(':')
             .to_string();
 
-        for group_str in army_lines {
-            let group_caps = group_re
-                .captures(group_str)
-                .ok_or(anyhow!("Group string not in expected format"))?;
+        for group_str in army_lines.filter(|line| !line.trim().is_empty()) {
+            let group_caps = group_re.captures(group_str).ok_or_else(|| {
+                anyhow!(
+                    "Group {:?} in {} isn't in the expected format",
+                    group_str.trim(),
+                    army_name
+                )
+            })?;
 
             groups.push(UnitGroup {
+                id: groups.len(),
                 army: army_name.clone(),
                 num_units: group_caps
                     .name("num_units")
@@ -213,26 +370,16 @@ fn parse_input(battle_info_str: &str) -> Result<Vec<UnitGroup>, anyhow::Error> {
                     .ok_or(anyhow!("Unit hp not found in the expected format"))?
                     .as_str()
                     .parse()?,
-                immunities: group_caps.name("immunities").map_or_else(
-                    || HashSet::new(),
-                    |imm_match| {
-                        imm_match
-                            .as_str()
-                            .split(", ")
-                            .map(|s| s.trim().to_string())
-                            .collect()
-                    },
-                ),
-                weaknesses: group_caps.name("weaknesses").map_or_else(
-                    || HashSet::new(),
-                    |weak_match| {
-                        weak_match
-                            .as_str()
-                            .split(", ")
-                            .map(|s| s.trim().to_string())
-                            .collect()
-                    },
-                ),
+                immunities: group_caps
+                    .name("immunities")
+                    .map_or_else(HashSet::new, |imm_match| {
+                        parse_dmg_types(imm_match.as_str())
+                    }),
+                weaknesses: group_caps
+                    .name("weaknesses")
+                    .map_or_else(HashSet::new, |weak_match| {
+                        parse_dmg_types(weak_match.as_str())
+                    }),
                 attack_dmg: group_caps
                     .name("dmg")
                     .ok_or(anyhow!("Attack damage not found in the expected format"))?
@@ -257,8 +404,20 @@ fn parse_input(battle_info_str: &str) -> Result<Vec<UnitGroup>, anyhow::Error> {
     Ok(groups)
 }
 
+// Splits up a list like "cold, radiation", without caring how much space
+// there is around the commas.
+fn parse_dmg_types(dmg_types_str: &str) -> HashSet<String> {
+    dmg_types_str
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
 #[derive(Eq, PartialEq, Clone)]
-struct UnitGroup {
+pub struct UnitGroup {
+    id: usize,
     army: String,
     num_units: usize,
     unit_hp: usize,
@@ -292,3 +451,126 @@ impl fmt::Debug for UnitGroup {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // How battle_tick worked out damage before the multipliers were cached,
+    // by looking up immunities and weaknesses for every single comparison.
+    fn uncached_battle_tick(mut groups: Vec<UnitGroup>) -> Vec<UnitGroup> {
+        fn calculate_attack_dmg(attacker: &UnitGroup, defender: &UnitGroup) -> usize {
+            if defender.immunities.contains(&attacker.attack_dmg_type) {
+                0
+            } else if defender.weaknesses.contains(&attacker.attack_dmg_type) {
+                attacker.effective_power() * 2
+            } else {
+                attacker.effective_power()
+            }
+        }
+
+        groups.sort_unstable_by_key(|g| (Reverse((g.effective_power(), g.initiative)), g.id));
+
+        let mut attacks: Vec<(usize, usize)> = Vec::new();
+
+        for (pos, group) in groups.iter().enumerate() {
+            let best_enemy = groups
+                .iter()
+                .enumerate()
+                .filter(|&(other_pos, other)| {
+                    other.army != group.army && attacks.iter().all(|&(_, e_p)| e_p != other_pos)
+                })
+                .max_by_key(|&(_, other)| {
+                    (
+                        calculate_attack_dmg(group, other),
+                        other.effective_power(),
+                        other.initiative,
+                        Reverse(other.id),
+                    )
+                })
+                .map(|(enemy_pos, _)| enemy_pos);
+
+            if let Some(enemy_pos) = best_enemy {
+                attacks.push((pos, enemy_pos));
+            }
+        }
+
+        attacks
+            .sort_unstable_by_key(|(a_p, _)| (Reverse(groups[*a_p].initiative), groups[*a_p].id));
+
+        for (attacker_pos, defender_pos) in attacks {
+            let attacker = groups[attacker_pos].clone();
+
+            if attacker.num_units == 0 {
+                continue;
+            }
+
+            let defender = &mut groups[defender_pos];
+            let dmg = calculate_attack_dmg(&attacker, defender);
+
+            defender.num_units = defender.num_units.saturating_sub(dmg / defender.unit_hp);
+        }
+
+        groups.into_iter().filter(|g| g.num_units > 0).collect_vec()
+    }
+
+    #[test]
+    fn cached_tick_matches_uncached_tick() {
+        let sample = parse_input(include_str!("input_small.txt")).unwrap();
+
+        for &boost in &[0, 1570] {
+            let mut groups = sample.clone();
+
+            for group in groups.iter_mut().filter(|g| g.army == "Immune System") {
+                group.attack_dmg += boost;
+            }
+
+            let dmg_multipliers = calculate_dmg_multipliers(&groups);
+
+            while groups.iter().any(|g| g.army != groups[0].army) {
+                let expected = uncached_battle_tick(groups.clone());
+                groups = battle_tick(groups, &dmg_multipliers, false);
+
+                assert_eq!(groups, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn stalemate() {
+        // Each army is immune to the other's attacks, apart from the second
+        // Immune System group, which is too weak to kill a single unit and
+        // has too many hit points to lose one.
+        let groups = parse_input(include_str!("input_stalemate.txt")).unwrap();
+
+        assert_eq!(battle_to_end(groups.clone(), None, None, false), None);
+        // Boosting cold damage doesn't help against an army that's immune to it.
+        assert_eq!(
+            find_min_boost(&groups, &["Immune System"], 1000, false),
+            None
+        );
+    }
+
+    #[test]
+    fn all_ties() {
+        // Every group has the same effective power and initiative, and does
+        // the same damage to every enemy, so only the ids break ties.
+        let groups = parse_input(include_str!("input_ties.txt")).unwrap();
+        let dmg_multipliers = calculate_dmg_multipliers(&groups);
+
+        // The Immune System groups come first, so they pick their targets
+        // and attack first too, and each of them wipes out the first enemy
+        // that's left, before either Infection group gets to do anything.
+        let expected = groups[..2].to_vec();
+
+        for reverse in &[false, true] {
+            let mut groups = groups.clone();
+
+            if *reverse {
+                groups.reverse();
+            }
+
+            assert_eq!(battle_tick(groups, &dmg_multipliers, false), expected);
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use parking_lot::{RwLock, RwLockUpgradableReadGuard};
+#[cfg(feature = "serde")]
+use serde::{de, ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
 use std::{
-    collections::HashSet,
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
     fmt,
     iter::{self, ExactSizeIterator, Extend, FromIterator, FusedIterator, TrustedLen},
     mem,
@@ -19,25 +22,46 @@ use std::{
 ///
 /// assert!(ds.contains(&1) && ds.contains(&2));
 /// assert_eq!(ds.same_set(a, b), Some(false));
-/// assert_eq!(ds.num_sets(), 2);
+/// assert_eq!(ds.num_subsets(), 2);
 ///
 /// assert_eq!(ds.union(a, b), Some(true));
 ///
 /// assert_eq!(ds.same_set(a, b), Some(true));
-/// assert_eq!(ds.num_sets(), 1);
+/// assert_eq!(ds.num_subsets(), 1);
 /// ```
 // Details about the algorithm used here can be found
 // at the Wikipedia page for "Disjoint-set data structure".
 pub struct DisjointSet<T> {
+    strategy: UnionStrategy,
     roots: HashSet<usize>,
     // Each elem idx corresponds to the same idx in nodes
     elems: Vec<T>,
     nodes: Vec<RwLock<Node>>,
 }
 
+/// Decides which of two subsets' roots becomes the parent of the
+/// other during a union. Either way keeps the trees shallow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UnionStrategy {
+    /// Attach the tree with the smaller upper bound on its height.
+    Rank,
+    /// Attach the tree with fewer elements.
+    Size,
+}
+
+impl Default for UnionStrategy {
+    fn default() -> Self {
+        Self::Rank
+    }
+}
+
 #[derive(Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 struct Node {
     rank: usize,
+    // Only kept up to date for roots.
+    size: usize,
     parent_idx: usize,
     // We use this to be able to iterate on each of our subsets.
     // This creates a circular linked list of nodes.
@@ -53,24 +77,38 @@ impl Clone for Node {
 
     fn clone_from(&mut self, source: &Self) {
         self.rank = source.rank;
+        self.size = source.size;
         self.parent_idx = source.parent_idx;
         self.next = source.next;
     }
 }
 
 impl<T> DisjointSet<T> {
-    /// Creates an empty `DisjointSet`.
+    /// Creates an empty `DisjointSet` that uses union by rank.
     pub fn new() -> Self {
+        Self::with_strategy(UnionStrategy::default())
+    }
+
+    /// Creates an empty `DisjointSet` that uses the given strategy for unions.
+    pub fn with_strategy(strategy: UnionStrategy) -> Self {
         Self {
+            strategy,
             roots: HashSet::new(),
             nodes: vec![],
             elems: vec![],
         }
     }
 
-    /// Creates a new `DisjointSet` with the given capacity.
+    /// Creates a new `DisjointSet` with the given capacity that uses union by rank.
     pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_strategy(capacity, UnionStrategy::default())
+    }
+
+    /// Creates a new `DisjointSet` with the given capacity that uses the
+    /// given strategy for unions.
+    pub fn with_capacity_and_strategy(capacity: usize, strategy: UnionStrategy) -> Self {
         Self {
+            strategy,
             roots: HashSet::new(),
             nodes: Vec::with_capacity(capacity),
             elems: Vec::with_capacity(capacity),
@@ -139,6 +177,7 @@ impl<T> DisjointSet<T> {
 
         self.nodes.push(RwLock::new(Node {
             rank: 0,
+            size: 1,
             parent_idx: insertion_idx,
             next: insertion_idx,
         }));
@@ -176,6 +215,58 @@ impl<T> DisjointSet<T> {
             .unwrap())
     }
 
+    /// Moves every element of `other` into this `DisjointSet`, keeping
+    /// `other`'s subsets together. Elements that are already present aren't
+    /// added again, but their subsets get joined with the ones they had in
+    /// `other`. Returns a `Vec` that maps each index in `other` to the index
+    /// the same element has now.
+    ///
+    /// # Example
+    /// ```
+    /// let mut ds: DisjointSet<_> = vec![vec![1, 2], vec![3]].into_iter().collect();
+    /// let other: DisjointSet<_> = vec![vec![3, 4], vec![5]].into_iter().collect();
+    ///
+    /// let idx_map = ds.merge_from(other);
+    ///
+    /// // Now we have {1, 2}, {3, 4} and {5}
+    ///
+    /// assert_eq!(idx_map.len(), 3);
+    /// assert_eq!(ds.num_elements(), 5);
+    /// assert_eq!(ds.num_subsets(), 3);
+    /// assert_eq!(ds.same_set(ds.position(&3).unwrap(), idx_map[1]), Some(true));
+    /// ```
+    pub fn merge_from(&mut self, other: DisjointSet<T>) -> Vec<usize>
+    where
+        T: PartialEq,
+    {
+        let other_sets_idxs = other
+            .roots
+            .iter()
+            .map(|&root| other.get_set_idxs(root).unwrap())
+            .collect::<Vec<_>>();
+
+        self.extend_reserve(other.num_elements());
+
+        let idx_map = other
+            .elems
+            .into_iter()
+            .map(|elem| match self.make_subset(elem) {
+                Ok(idx) => idx,
+                Err(DuplicateElementsError { existing_idx }) => existing_idx,
+            })
+            .collect::<Vec<_>>();
+
+        for set_idxs in other_sets_idxs {
+            let set_representative = idx_map[set_idxs[0]];
+
+            for other_idx in set_idxs {
+                self.union(set_representative, idx_map[other_idx]);
+            }
+        }
+
+        idx_map
+    }
+
     /// If present, returns an immutable reference to the element at `elem_idx`.
     pub fn get(&self, elem_idx: usize) -> Option<&T> {
         self.elems.get(elem_idx)
@@ -205,23 +296,45 @@ impl<T> DisjointSet<T> {
         Some(SubsetMut { ds: self, set_idxs })
     }
 
-    /// Returns an second-order iterator of `&T` of all the subsets.
+    /// Returns an second-order iterator of `&T` of all the subsets. The
+    /// subsets are ordered by the smallest element index in each one, and
+    /// the elements in each subset are in index order.
     pub fn get_all_subsets(&self) -> impl IntoIterator<Item = Subset<T>> {
-        self.roots.iter().map(move |&r| self.get_subset(r).unwrap())
+        self.sorted_set_idxs()
+            .into_iter()
+            .map(move |set_idxs| Subset { ds: self, set_idxs })
     }
 
-    /// Returns a second-order iterator of `&mut T` of all the subsets.
+    /// Returns the index of the root of each subset, in no particular order.
+    /// Unlike [`get_all_subsets`](DisjointSet::get_all_subsets), this doesn't
+    /// need to look at the elements of any subset.
+    pub fn roots(&self) -> impl Iterator<Item = usize> + '_ {
+        self.roots.iter().copied()
+    }
+
+    /// Returns the subset with the most elements, or None if the
+    /// `DisjointSet` is empty. If there's a tie, the subset with the
+    /// lowest root index wins.
+    pub fn largest_subset(&self) -> Option<Subset<T>> {
+        let largest_root = self
+            .roots()
+            .max_by_key(|&root| (self.nodes[root].read().size, Reverse(root)))?;
+
+        self.get_subset(largest_root)
+    }
+
+    /// Returns a second-order iterator of `&mut T` of all the subsets,
+    /// in the same order as [`get_all_subsets`](DisjointSet::get_all_subsets).
     pub fn get_mut_all_subsets(&mut self) -> impl IntoIterator<Item = SubsetMut<T>> {
-        // Clone to avoid violating aliasing rules
-        let roots = self.roots.clone();
+        // This is a new Vec, so it doesn't violate aliasing rules
+        let all_set_idxs = self.sorted_set_idxs();
 
-        roots.into_iter().map(move |root| {
+        all_set_idxs.into_iter().map(move |set_idxs| SubsetMut {
             // SAFETY: Here we reborrow self, which has the lifetime of this
             // closure (&'1 mut self) as an &'a mut self, which is valid here because
             // there are no overlapping indexes in each subset or among subsets.
-            unsafe { &mut *(self as *mut Self) }
-                .get_mut_subset(root)
-                .unwrap()
+            ds: unsafe { &mut *(self as *mut Self) },
+            set_idxs,
         })
     }
 
@@ -233,6 +346,12 @@ impl<T> DisjointSet<T> {
         Some(self.find_root_idx(elem1_idx)? == self.find_root_idx(elem2_idx)?)
     }
 
+    /// Same as [`same_set`](DisjointSet::same_set), but uses
+    /// [`root_of`](DisjointSet::root_of) so the structure isn't touched.
+    pub fn same_set_uncompressed(&self, elem1_idx: usize, elem2_idx: usize) -> Option<bool> {
+        Some(self.root_of(elem1_idx)? == self.root_of(elem2_idx)?)
+    }
+
     /// Performs a union for the two subsets containing the given elements.
     /// Returns Some(true) if the operation was performed, Some(false) if not,
     /// and None if either element doesn't exist.
@@ -251,7 +370,7 @@ impl<T> DisjointSet<T> {
     /// assert_eq!(ds.union(a, b), Some(true));
     ///
     /// assert_eq!(ds.same_set(a, b), Some(true));
-    /// assert_eq!(ds.num_sets(), 4);
+    /// assert_eq!(ds.num_subsets(), 4);
     ///
     /// assert_eq!(ds.union(a, b), Some(false));
     /// assert_eq!(ds.union(c, d), Some(true));
@@ -259,13 +378,13 @@ impl<T> DisjointSet<T> {
     ///
     /// // Now we have {a, b} and {c, d, e}
     ///
-    /// assert_eq!(ds.num_sets(), 2);
+    /// assert_eq!(ds.num_subsets(), 2);
     /// assert_eq!(ds.same_set(a, c), Some(false));
     /// assert_eq!(ds.same_set(d, e), Some(true));
     ///
     /// assert_eq!(ds.union(a, e), Some(true));
     ///
-    /// assert_eq!(ds.num_sets(), 1);
+    /// assert_eq!(ds.num_subsets(), 1);
     /// ```
     pub fn union(&mut self, elem_x_idx: usize, elem_y_idx: usize) -> Option<bool> {
         let (mut x_root_idx, mut y_root_idx) = (
@@ -289,20 +408,27 @@ impl<T> DisjointSet<T> {
         let (mut x_root, mut y_root) =
             unsafe { ((&mut *x_root).get_mut(), (&mut *y_root).get_mut()) };
 
-        if x_root.rank < y_root.rank {
+        let should_swap = match self.strategy {
+            UnionStrategy::Rank => x_root.rank < y_root.rank,
+            UnionStrategy::Size => x_root.size < y_root.size,
+        };
+
+        if should_swap {
             // Must use mem::swap here. If we shadowed,
             // it'd go out of scope when the if block ended.
             mem::swap(&mut x_root_idx, &mut y_root_idx);
             mem::swap(&mut x_root, &mut y_root);
         }
 
-        // Now x_root.rank >= y_root.rank no matter what.
-        // Therefore, make X the parent of Y.
+        // Now X is the bigger tree by whatever the strategy
+        // measures, so make X the parent of Y.
         y_root.parent_idx = x_root_idx;
         self.roots.remove(&y_root_idx);
-        if x_root.rank == y_root.rank {
-            x_root.rank += 1;
-        }
+        x_root.size += y_root.size;
+        // With union by rank, this only bumps the rank if the two were
+        // equal. With union by size, it's just kept around as a bound
+        // on the height of the tree.
+        x_root.rank = x_root.rank.max(y_root.rank + 1);
 
         // Merge the two set's circular linked lists.
         mem::swap(&mut x_root.next, &mut y_root.next);
@@ -326,23 +452,53 @@ impl<T> DisjointSet<T> {
         }
 
         let set_idxs = self.get_set_idxs(elem_idx)?;
+        let root_idx = self.find_root_idx(elem_idx)?;
+
+        // set_idxs starts at elem_idx, so everything after it is what's
+        // left of the subset once elem_idx is taken out.
+        let rest_idxs = &set_idxs[1..];
+        let (&next_idx, &prev_idx) = rest_idxs.first().zip(rest_idxs.last()).unwrap();
+
+        // Other nodes might have elem_idx as their parent, or as their root,
+        // so the simplest thing to do is point all of them straight at a
+        // root that's staying put.
+        let new_root_idx = if root_idx == elem_idx {
+            next_idx
+        } else {
+            root_idx
+        };
 
-        let (&next_idx, &prev_idx) = set_idxs.get(1).zip(set_idxs.last()).unwrap();
-
-        if prev_idx != elem_idx {
-            let mut prev = self.nodes[prev_idx].get_mut();
-            prev.next = next_idx;
+        for &idx in rest_idxs {
+            self.nodes[idx].get_mut().parent_idx = new_root_idx;
         }
 
-        let mut node = self.nodes[elem_idx].get_mut();
+        let new_root = self.nodes[new_root_idx].get_mut();
+        new_root.size = rest_idxs.len();
+        new_root.rank = (rest_idxs.len() > 1) as usize;
+
+        self.roots.remove(&root_idx);
+        self.roots.insert(new_root_idx);
+
+        self.nodes[prev_idx].get_mut().next = next_idx;
+
+        let node = self.nodes[elem_idx].get_mut();
 
         self.roots.insert(elem_idx);
         node.parent_idx = elem_idx;
         node.next = elem_idx;
+        node.size = 1;
+        node.rank = 0;
 
         Some(true)
     }
 
+    /// Returns the number of elements in the subset `elem_idx` belongs
+    /// to, if it exists. Unlike getting the subset itself, this doesn't
+    /// need to walk through every element.
+    pub fn subset_size(&self, elem_idx: usize) -> Option<usize> {
+        Some(self.nodes[self.find_root_idx(elem_idx)?].read().size)
+    }
+
     /// Returns the index of the root of the subset
     /// `elem_idx` belongs to, if it exists.
     pub fn find_root_idx(&self, elem_idx: usize) -> Option<usize> {
@@ -377,6 +533,51 @@ impl<T> DisjointSet<T> {
         Some(curr_idx)
     }
 
+    /// Returns the index of the root of the subset `elem_idx` belongs to,
+    /// if it exists, without doing any path splitting along the way.
+    ///
+    /// Unlike [`find_root_idx`](DisjointSet::find_root_idx), this only ever
+    /// takes read locks, so it's a better fit for read-heavy work like
+    /// collecting stats over every element. The catch is that the paths it
+    /// walks never get any shorter, so repeated calls on a deep tree stay
+    /// just as slow as the first one.
+    pub fn root_of(&self, elem_idx: usize) -> Option<usize> {
+        let mut curr_idx = elem_idx;
+        let mut parent_idx = self.nodes.get(curr_idx)?.read().parent_idx;
+
+        while parent_idx != curr_idx {
+            curr_idx = parent_idx;
+            parent_idx = self.nodes[curr_idx].read().parent_idx;
+        }
+
+        Some(curr_idx)
+    }
+
+    /// Returns the indexes of the items in every subset, with the subsets
+    /// ordered by their smallest index and the indexes in each one sorted.
+    /// Going through `roots` and the next-links directly would be quicker,
+    /// but the order of both of those changes from run to run.
+    fn sorted_set_idxs(&self) -> Vec<Vec<usize>> {
+        let mut root_positions = HashMap::with_capacity(self.num_subsets());
+        let mut all_set_idxs: Vec<Vec<usize>> = Vec::with_capacity(self.num_subsets());
+
+        // Since we go through the elements in order, each subset's Vec
+        // gets created when we reach the smallest index in it, and every
+        // index gets pushed after all the smaller ones.
+        for elem_idx in 0..self.num_elements() {
+            let root_idx = self.find_root_idx(elem_idx).unwrap();
+
+            let position = *root_positions.entry(root_idx).or_insert_with(|| {
+                all_set_idxs.push(vec![]);
+                all_set_idxs.len() - 1
+            });
+
+            all_set_idxs[position].push(elem_idx);
+        }
+
+        all_set_idxs
+    }
+
     /// Returns the indexes of all the items in the subset
     /// `elem_idx` belongs to in next-link order, if it exists.
     fn get_set_idxs(&self, elem_idx: usize) -> Option<Vec<usize>> {
@@ -416,6 +617,7 @@ impl<T: Eq + Clone> Clone for DisjointSet<T> {
             .collect();
 
         Self {
+            strategy: self.strategy,
             roots: self.roots.clone(),
             elems: self.elems.clone(),
             nodes: copied_nodes,
@@ -423,6 +625,7 @@ impl<T: Eq + Clone> Clone for DisjointSet<T> {
     }
 
     fn clone_from(&mut self, source: &Self) {
+        self.strategy = source.strategy;
         self.roots.clone_from(&source.roots);
         self.elems.clone_from(&source.elems);
 
@@ -432,6 +635,7 @@ impl<T: Eq + Clone> Clone for DisjointSet<T> {
             // only be mutated, not completely overwritten.
             RwLock::new(Node {
                 rank: 0,
+                size: 0,
                 parent_idx: 0,
                 next: 0,
             })
@@ -448,6 +652,71 @@ impl<T: Eq + Clone> Clone for DisjointSet<T> {
     }
 }
 
+// The nodes are behind RwLocks, which serde doesn't know how to deal
+// with, so we have to go through the plain Node values ourselves.
+#[cfg(feature = "serde")]
+impl<T: Serialize> Serialize for DisjointSet<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node_rwlock| *node_rwlock.read())
+            .collect::<Vec<_>>();
+
+        let mut state = serializer.serialize_struct("DisjointSet", 4)?;
+        state.serialize_field("strategy", &self.strategy)?;
+        state.serialize_field("roots", &self.roots)?;
+        state.serialize_field("elems", &self.elems)?;
+        state.serialize_field("nodes", &nodes)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for DisjointSet<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Private helper with the same shape as what Serialize writes out.
+        #[derive(Deserialize)]
+        #[serde(rename = "DisjointSet")]
+        struct DisjointSetData<T> {
+            strategy: UnionStrategy,
+            roots: HashSet<usize>,
+            elems: Vec<T>,
+            nodes: Vec<Node>,
+        }
+
+        let data = DisjointSetData::deserialize(deserializer)?;
+
+        if data.nodes.len() != data.elems.len() {
+            return Err(de::Error::custom(format!(
+                "{} nodes don't match up with {} elements",
+                data.nodes.len(),
+                data.elems.len()
+            )));
+        }
+
+        // Everything else indexes into nodes, and we'd rather
+        // catch a bad index here than panic on it later.
+        let num_elements = data.elems.len();
+
+        if data.roots.iter().any(|&root| root >= num_elements)
+            || data
+                .nodes
+                .iter()
+                .any(|node| node.parent_idx >= num_elements || node.next >= num_elements)
+        {
+            return Err(de::Error::custom("node index out of bounds"));
+        }
+
+        Ok(Self {
+            strategy: data.strategy,
+            roots: data.roots,
+            elems: data.elems,
+            nodes: data.nodes.into_iter().map(RwLock::new).collect(),
+        })
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 #[error("Attempted to add a duplicate element to a DisjointSet: already existed at {existing_idx}")]
 pub struct DuplicateElementsError {
@@ -690,6 +959,23 @@ impl<T> IndexMut<usize> for DisjointSet<T> {
     }
 }
 
+/// Two `DisjointSet`s are equal if they split up the same elements into
+/// the same subsets, no matter which order anything was added or joined in.
+///
+/// # Example
+/// ```
+/// let mut ds1: DisjointSet<_> = (1..=4).map(|n| vec![n]).collect();
+/// let mut ds2: DisjointSet<_> = (1..=4).rev().map(|n| vec![n]).collect();
+///
+/// ds1.union(0, 1);
+/// ds1.union(2, 3);
+///
+/// // Same as {1, 2} and {3, 4}, but with the indexes reversed
+/// ds2.union(0, 1);
+/// ds2.union(3, 2);
+///
+/// assert_eq!(ds1, ds2);
+/// ```
 impl<T: PartialEq> PartialEq for DisjointSet<T> {
     fn eq(&self, other: &Self) -> bool {
         if self.num_subsets() != other.num_subsets() || self.num_elements() != other.num_elements()
@@ -697,15 +983,28 @@ impl<T: PartialEq> PartialEq for DisjointSet<T> {
             return false;
         }
 
-        for (self_subset, other_subset) in self.into_iter().zip(other.into_iter()) {
-            let mut other_subset = other_subset.into_iter();
+        // Elements are never duplicated, so if every subset here has all of
+        // its elements in one subset of the same size over there, there's no
+        // room left for the two to be split up any differently.
+        for self_subset in self {
+            let mut self_subset = self_subset.into_iter();
+            let subset_len = self_subset.len();
+
+            let other_representative = match self_subset.next().and_then(|e| other.position(e)) {
+                Some(idx) => idx,
+                None => return false,
+            };
+
+            if other.subset_size(other_representative) != Some(subset_len) {
+                return false;
+            }
 
             for elem in self_subset {
-                if let Some(other_elem) = other_subset.next() {
-                    if elem != other_elem {
-                        return false;
-                    }
-                } else {
+                let in_same_subset = other
+                    .position(elem)
+                    .and_then(|idx| other.same_set_uncompressed(idx, other_representative));
+
+                if in_same_subset != Some(true) {
                     return false;
                 }
             }
@@ -757,11 +1056,7 @@ impl<T: PartialEq, I: IntoIterator<Item = T>> FromIterator<I> for DisjointSet<T>
 
 impl<T> From<DisjointSet<T>> for Vec<Vec<T>> {
     default fn from(ds: DisjointSet<T>) -> Self {
-        let all_sets_idxs = ds
-            .roots
-            .iter()
-            .map(|&root| ds.get_set_idxs(root).unwrap())
-            .collect::<Vec<_>>();
+        let all_sets_idxs = ds.sorted_set_idxs();
 
         let mut vec_2d: Vec<Vec<T>> = iter::repeat_with(Vec::new)
             .take(all_sets_idxs.len())
@@ -824,3 +1119,218 @@ impl<'a, T> IntoIterator for &'a mut DisjointSet<T> {
         self.get_mut_all_subsets().into_iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subsets<T: Clone>(ds: &DisjointSet<T>) -> Vec<Vec<T>> {
+        ds.get_all_subsets()
+            .into_iter()
+            .map(|subset| subset.into_iter().cloned().collect())
+            .collect()
+    }
+
+    // Checks subset_size against actually counting up each subset.
+    fn assert_subset_sizes<T>(ds: &DisjointSet<T>) {
+        for elem_idx in 0..ds.num_elements() {
+            assert_eq!(
+                ds.subset_size(elem_idx),
+                Some(ds.get_subset(elem_idx).unwrap().into_iter().len())
+            );
+        }
+    }
+
+    #[test]
+    fn with_capacity_and_strategy() {
+        let ds = DisjointSet::<usize>::with_capacity_and_strategy(10, UnionStrategy::Size);
+
+        assert!(ds.capacity() >= 10);
+        assert_eq!(ds.strategy, UnionStrategy::Size);
+        assert_eq!(
+            DisjointSet::<usize>::with_capacity(10).strategy,
+            UnionStrategy::Rank
+        );
+    }
+
+    #[test]
+    fn subset_sizes_across_unions() {
+        for &strategy in &[UnionStrategy::Rank, UnionStrategy::Size] {
+            let mut ds = DisjointSet::with_capacity_and_strategy(8, strategy);
+
+            for n in 0..8 {
+                ds.make_subset(n).unwrap();
+            }
+
+            // Uneven subsets, so that the two strategies disagree about which
+            // root to keep at least some of the time.
+            for &(x, y) in &[(0, 1), (2, 3), (2, 4), (5, 6), (0, 2), (7, 0), (6, 7)] {
+                ds.union(x, y);
+                assert_subset_sizes(&ds);
+            }
+
+            assert_eq!(ds.subset_size(3), Some(8));
+        }
+    }
+
+    #[test]
+    fn subset_sizes_after_make_singleton() {
+        for &strategy in &[UnionStrategy::Rank, UnionStrategy::Size] {
+            let mut ds = DisjointSet::with_strategy(strategy);
+            let root = ds.add_subset(0..6).unwrap();
+
+            // The root's the awkward one, since everything else hangs off it.
+            assert_eq!(ds.make_singleton(root), Some(true));
+            assert_eq!(ds.subset_size(root), Some(1));
+            assert_eq!(ds.num_subsets(), 2);
+            assert_subset_sizes(&ds);
+
+            let not_root = (0..6).find(|&idx| !ds.roots.contains(&idx)).unwrap();
+
+            assert_eq!(ds.make_singleton(not_root), Some(true));
+            assert_eq!(ds.make_singleton(not_root), Some(false));
+            assert_eq!(ds.num_subsets(), 3);
+            assert_subset_sizes(&ds);
+
+            let rest = (0..6)
+                .filter(|&idx| idx != root && idx != not_root)
+                .collect::<Vec<_>>();
+
+            for &idx in &rest {
+                assert_eq!(ds.subset_size(idx), Some(4));
+                assert_eq!(ds.same_set(idx, rest[0]), Some(true));
+            }
+
+            // Joining them back up should still count them all.
+            ds.union(root, not_root);
+            ds.union(rest[0], root);
+            assert_eq!(ds.subset_size(not_root), Some(6));
+            assert_subset_sizes(&ds);
+        }
+    }
+
+    #[test]
+    fn eq_ignores_union_order() {
+        let unions = [(0, 1), (1, 2), (3, 4), (5, 6), (6, 4)];
+
+        let mut in_order = DisjointSet::new();
+        let mut reversed = DisjointSet::with_strategy(UnionStrategy::Size);
+
+        for n in 0..8 {
+            in_order.make_subset(n).unwrap();
+            // Adding the elements backwards puts them at different indexes too.
+            reversed.make_subset(7 - n).unwrap();
+        }
+
+        for &(x, y) in unions.iter() {
+            in_order.union(x, y);
+        }
+
+        for &(x, y) in unions.iter().rev() {
+            reversed.union(7 - y, 7 - x);
+        }
+
+        // {0, 1, 2}, {3, 4, 5, 6} and {7} either way.
+        assert_eq!(in_order, reversed);
+        assert_eq!(reversed, in_order);
+        assert_eq!(
+            subsets(&in_order),
+            [vec![0, 1, 2], vec![3, 4, 5, 6], vec![7]]
+        );
+
+        // One more union on either side makes them different.
+        reversed.union(0, 7);
+        assert_ne!(in_order, reversed);
+    }
+
+    #[test]
+    fn eq_needs_same_elements() {
+        let ds1: DisjointSet<_> = vec![vec![1, 2], vec![3]].into_iter().collect();
+        let ds2: DisjointSet<_> = vec![vec![1, 2], vec![4]].into_iter().collect();
+        let ds3: DisjointSet<_> = vec![vec![1], vec![2, 3]].into_iter().collect();
+
+        assert_ne!(ds1, ds2);
+        assert_ne!(ds1, ds3);
+    }
+
+    #[test]
+    fn merge_from_disjoint() {
+        let mut ds: DisjointSet<_> = vec![vec![1, 2], vec![3]].into_iter().collect();
+        let other: DisjointSet<_> = vec![vec![4, 5], vec![6]].into_iter().collect();
+
+        let idx_map = ds.merge_from(other);
+
+        assert_eq!(idx_map, [3, 4, 5]);
+        assert_eq!(subsets(&ds), [vec![1, 2], vec![3], vec![4, 5], vec![6]]);
+    }
+
+    #[test]
+    fn merge_from_shared_elements() {
+        let mut ds: DisjointSet<_> = vec![vec![1, 2], vec![3], vec![4]].into_iter().collect();
+        // 2 and 3 are already here, so they don't get added again, but
+        // having them together over there joins up {1, 2} and {3}.
+        let other: DisjointSet<_> = vec![vec![2, 3, 5], vec![6]].into_iter().collect();
+
+        let idx_map = ds.merge_from(other);
+
+        assert_eq!(idx_map, [1, 2, 4, 5]);
+        assert_eq!(ds.num_elements(), 6);
+        assert_eq!(subsets(&ds), [vec![1, 2, 3, 5], vec![4], vec![6]]);
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn round_trip<T: Serialize + for<'de> Deserialize<'de>>(ds: &DisjointSet<T>) -> DisjointSet<T> {
+        serde_json::from_str(&serde_json::to_string(ds).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trip_subsets() {
+        for &strategy in &[UnionStrategy::Rank, UnionStrategy::Size] {
+            let mut ds = DisjointSet::with_strategy(strategy);
+
+            for subset in vec![vec![1, 2, 3], vec![4], vec![5, 6], vec![7, 8, 9, 10]] {
+                ds.add_subset(subset).unwrap();
+            }
+
+            // Now we have {1, 2, 3, 7, 8, 9, 10}, {4} and {5, 6}
+            ds.union(ds.position(&2).unwrap(), ds.position(&10).unwrap());
+
+            let deserialized = round_trip(&ds);
+
+            assert_eq!(deserialized, ds);
+            assert_eq!(deserialized.strategy, strategy);
+            assert_eq!(deserialized.num_subsets(), 3);
+            assert_eq!(deserialized.subset_size(0), Some(7));
+        }
+    }
+
+    #[test]
+    fn round_trip_empty() {
+        assert_eq!(round_trip(&DisjointSet::<u8>::new()), DisjointSet::new());
+    }
+
+    #[test]
+    fn rejects_bad_indexes() {
+        let ds: DisjointSet<_> = vec![vec![1, 2]].into_iter().collect();
+        let serialized = serde_json::to_value(&ds).unwrap();
+
+        let with_change = |pointer: &str, value: Value| {
+            let mut serialized = serialized.clone();
+            *serialized.pointer_mut(pointer).unwrap() = value;
+
+            serde_json::from_value::<DisjointSet<i32>>(serialized)
+        };
+
+        assert!(with_change("/nodes/1/parent_idx", json!(2)).is_err());
+        assert!(with_change("/nodes/0/next", json!(5)).is_err());
+        assert!(with_change("/roots", json!([0, 2])).is_err());
+        assert!(with_change("/elems", json!([1, 2, 3])).is_err());
+        // Changing something harmless should still be fine.
+        assert!(with_change("/elems/1", json!(3)).is_ok());
+    }
+}
@@ -11,33 +11,112 @@
 
 mod disjoint_set;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
+use aoc_2018_rust::{
+    geometry::Point,
+    input::read_input,
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
-use derive_more::From;
 use disjoint_set::DisjointSet;
 use itertools::Itertools;
 use num::{
     traits::{AsPrimitive, NumAssignOps},
     Num, Unsigned,
 };
-use std::{collections::HashMap, convert::TryInto, fmt, fs, iter, slice, str::FromStr};
+use std::{collections::HashMap, num::NonZeroUsize, str::FromStr};
 
 pub fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2018-25")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
+        .arg(
+            Arg::from_usage("[chain_distance] -d --chain-distance 'Largest distance at which two points are in the same constellation'")
+                .takes_value(true)
+                .default_value("3"),
+        )
+        .arg(Arg::from_usage(
+            "[sizes] -s --sizes 'Also print the size of each constellation, largest first'",
+        ))
+        .arg(Arg::from_usage("[time] --time 'Print how long parsing and solving take'"))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
 
-    let coords_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
-    let points = parse_input::<i8, 4>(&coords_str)?;
+    let chain_distance: usize = matches
+        .value_of("chain_distance")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided chain distance is not a number"))?;
+
+    let coords_str = read_input(input_filename)?;
+    let print_sizes = matches.is_present("sizes");
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
+
+    // D has to be known at compile time, so we look at how many coordinates
+    // the first point has and pick the matching version of solve.
+    let dimensions = coords_str
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .ok_or_else(|| anyhow!("Input doesn't contain any points"))?
+        .matches(',')
+        .count()
+        + 1;
+
+    match dimensions {
+        2 => solve::<2>(&coords_str, chain_distance, print_sizes, time, repeat),
+        3 => solve::<3>(&coords_str, chain_distance, print_sizes, time, repeat),
+        4 => solve::<4>(&coords_str, chain_distance, print_sizes, time, repeat),
+        5 => solve::<5>(&coords_str, chain_distance, print_sizes, time, repeat),
+        6 => solve::<6>(&coords_str, chain_distance, print_sizes, time, repeat),
+        _ => bail!(
+            "Points have {} coordinates, but only 2 to 6 are supported",
+            dimensions
+        ),
+    }
+}
 
-    let points_ds = find_chains(&points, 3u8);
+fn solve<const D: usize>(
+    coords_str: &str,
+    chain_distance: usize,
+    print_sizes: bool,
+    time: bool,
+    repeat: NonZeroUsize,
+) -> Result<(), anyhow::Error> {
+    let parse_timer = Timer::start("Parsing", time);
+    let points = parse_input::<i8, D>(coords_str)?;
+    drop(parse_timer);
+
+    let (num_constellations, sizes) = timing::repeat("Solving", repeat, time, || {
+        let points_ds = find_chains(&points, chain_distance);
+
+        let sizes = print_sizes.then(|| {
+            points_ds
+                .get_all_subsets()
+                .into_iter()
+                .map(|subset| subset.into_iter().len())
+                .sorted_by(|a, b| b.cmp(a))
+                .collect_vec()
+        });
 
-    println!(
-        "The number of constellations is {}",
-        points_ds.num_subsets()
-    );
+        (points_ds.num_subsets(), sizes)
+    });
+
+    println!("The number of constellations is {}", num_constellations);
+
+    if let Some(sizes) = sizes {
+        println!("Their sizes are {}", sizes.iter().format(", "));
+    }
 
     Ok(())
 }
@@ -87,86 +166,67 @@ where
         .map(|line| {
             line.trim()
                 .trim_matches(&['(', ')', '[', ']'] as &[_])
-                .split(',')
-                .map(|c| c.parse().map_err(|_| anyhow!("Could not parse coordinate")))
-                .try_collect()
-                .and_then(|coords: Vec<_>| {
-                    // Coerce this Vec into a fixed-size array
-                    // and error out if it doesn't work due to length
-                    Ok(Point(coords.try_into().map_err(|_| {
-                        anyhow!("Could not find {} coordinates in a line", D)
-                    })?))
-                })
+                .parse()
+                .map_err(anyhow::Error::from)
         })
         .try_collect()
 }
 
-#[derive(Clone, Copy, From, Eq, PartialEq, Hash)]
-struct Point<N: Num, const D: usize>([N; D]);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Finds the chains in each half separately, merges them, and then joins
+    // up whatever's close enough across the two halves, which should come out
+    // the same as finding the chains in one go.
+    fn merged_chains<const D: usize>(
+        first_half: &[Point<i8, D>],
+        second_half: &[Point<i8, D>],
+    ) -> DisjointSet<Point<i8, D>> {
+        let mut points_ds = find_chains(&first_half.to_vec(), 3_usize);
+        points_ds.merge_from(find_chains(&second_half.to_vec(), 3_usize));
+
+        for (first_point, second_point) in first_half.iter().cartesian_product(second_half) {
+            if first_point.manhattan_distance::<usize>(second_point) <= 3 {
+                points_ds.union(
+                    points_ds.position(first_point).unwrap(),
+                    points_ds.position(second_point).unwrap(),
+                );
+            }
+        }
 
-impl<N: Num + Default, const D: usize> Default for Point<N, D> {
-    fn default() -> Self {
-        Self(
-            match iter::repeat_with(N::default)
-                .take(D)
-                .collect_vec()
-                .try_into()
-            {
-                Ok(array) => array,
-                Err(_) => unsafe { std::hint::unreachable_unchecked() },
-            },
-        )
+        points_ds
     }
-}
-
-impl<N: Num + fmt::Debug, const D: usize> fmt::Debug for Point<N, D> {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let mut tuple_output = fmt.debug_tuple(&format!("P{}", D));
 
-        for c in self.iter() {
-            tuple_output.field(c);
-        }
+    #[test]
+    fn sample_constellations() {
+        let points = parse_input::<i8, 4>(include_str!("input_small.txt")).unwrap();
 
-        tuple_output.finish()
+        assert_eq!(find_chains(&points, 3_usize).num_subsets(), 8);
     }
-}
 
-impl<N: Num, const D: usize> Point<N, D> {
-    fn iter(&self) -> slice::Iter<N> {
-        self.0.iter()
+    #[test]
+    fn merged_halves() {
+        let points = parse_input::<i8, 4>(include_str!("input_small.txt")).unwrap();
+        let one_pass = find_chains(&points, 3_usize);
+
+        let (first_half, second_half) = points.split_at(points.len() / 2);
+        let merged = merged_chains(first_half, second_half);
+
+        assert_eq!(merged.num_subsets(), one_pass.num_subsets());
+        assert_eq!(merged, one_pass);
     }
-}
 
-// Did I make this unnecesssarily generic and therefore
-// complicated? Yes. But to be fair I thought it'd
-// be simpler than this and I wanted to mess around
-// with generics anyway.
-impl<N, const D: usize> Point<N, D>
-where
-    N: Num + PartialOrd,
-{
-    fn manhattan_distance<R>(&self, other: &Point<N, D>) -> R
-    where
-        R: 'static + Unsigned + Copy + NumAssignOps,
-        N: AsPrimitive<R>,
-    {
-        let mut total = R::zero();
-
-        for (&self_coord, &other_coord) in self.iter().zip(other.iter()) {
-            // We could use num::abs here, but for some absurd
-            // reason that requires N: num::Signed, which is unnecessarily
-            // restrictive for our purposes.
-            total += (if self_coord > other_coord {
-                self_coord - other_coord
-            } else {
-                other_coord - self_coord
-            })
-            .as_();
-            // We know for a fact that this has to be positive,
-            // so the "as" conversion to an R: Unsigned type
-            // will be fine.
-        }
+    #[test]
+    fn merged_overlapping_halves() {
+        let points = parse_input::<i8, 4>(include_str!("input_small.txt")).unwrap();
+        let one_pass = find_chains(&points, 3_usize);
+
+        // The middle four points are in both halves.
+        let merged = merged_chains(&points[..7], &points[3..]);
 
-        total
+        assert_eq!(merged.num_elements(), points.len());
+        assert_eq!(merged.num_subsets(), one_pass.num_subsets());
+        assert_eq!(merged, one_pass);
     }
 }
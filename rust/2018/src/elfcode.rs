@@ -0,0 +1,242 @@
+use anyhow::{anyhow, bail};
+use itertools::Itertools;
+use std::fmt;
+
+// The device from days 16, 19 and 21, which has six registers and these
+// sixteen operations. The two letters at the end say whether each input is
+// a register (r) or an immediate value (i), and the other one is ignored
+// for the set operations.
+pub const OPCODES: [&str; 16] = [
+    "addr", "addi", "mulr", "muli", "banr", "bani", "borr", "bori", "setr", "seti", "gtir", "gtri",
+    "gtrr", "eqir", "eqri", "eqrr",
+];
+
+pub const NUM_REGISTERS: usize = 6;
+
+// Runs the program until the instruction pointer leaves it, and gives back
+// the registers as they were at that point.
+pub fn run(code: &[Instruction], ip_register: usize, mut regs: Vec<usize>) -> Vec<usize> {
+    while let Some(ins) = code.get(regs[ip_register]) {
+        ins.execute(&mut regs);
+        regs[ip_register] += 1;
+    }
+
+    regs
+}
+
+// Which of the opcodes would have turned the registers from before into
+// after, when given these inputs and output. Working out which number is
+// which opcode in day 16 comes down to narrowing these down.
+pub fn matching_opcodes<'a>(
+    before: &'a [usize],
+    inputs: (usize, usize),
+    output_reg: usize,
+    after: &'a [usize],
+) -> impl Iterator<Item = &'static str> + 'a {
+    OPCODES.iter().copied().filter(move |name| {
+        let ins = Instruction::new(name, inputs.0, inputs.1, output_reg).unwrap();
+
+        // Anything that refers to a register that doesn't exist can't be it.
+        if ins.output_reg >= before.len()
+            || ins
+                .input
+                .iter()
+                .any(|v| matches!(v, Value::Reg(r) if *r >= before.len()))
+        {
+            return false;
+        }
+
+        let mut regs = before.to_vec();
+        ins.execute(&mut regs);
+
+        regs == after
+    })
+}
+
+// The first line says which register is bound to the instruction pointer,
+// and every other line is an instruction.
+pub fn parse_program(code_str: &str) -> Result<(usize, Vec<Instruction>), anyhow::Error> {
+    let mut code_lines = code_str.lines();
+
+    let ins_pointer = code_lines
+        .next()
+        .map(|s| s.trim_start_matches("#ip "))
+        .ok_or_else(|| anyhow!("Instruction pointer not found"))?
+        .parse()?;
+
+    let code = code_lines
+        .map(|c| -> Result<Instruction, anyhow::Error> {
+            let (op_str, inp1, inp2, output_reg) = c
+                .split_whitespace()
+                .collect_tuple()
+                .ok_or_else(|| anyhow!("Instruction not in correct format"))?;
+
+            Instruction::new(op_str, inp1.parse()?, inp2.parse()?, output_reg.parse()?)
+                .ok_or_else(|| anyhow!("Invalid operation {:?}", op_str))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if ins_pointer >= NUM_REGISTERS {
+        bail!("Instruction pointer is bound to a register that doesn't exist");
+    }
+
+    Ok((ins_pointer, code))
+}
+
+pub struct Instruction {
+    pub name: String,
+    pub opcode: Opcode,
+    pub input: [Value; 2],
+    pub output_reg: usize,
+}
+
+impl Instruction {
+    // None if there's no opcode with this name.
+    pub fn new(name: &str, inp1: usize, inp2: usize, output_reg: usize) -> Option<Self> {
+        use Value::*;
+
+        let (opcode, input) = match name {
+            "addr" => (Opcode::Add, [Reg(inp1), Reg(inp2)]),
+            "addi" => (Opcode::Add, [Reg(inp1), Imm(inp2)]),
+            "mulr" => (Opcode::Mul, [Reg(inp1), Reg(inp2)]),
+            "muli" => (Opcode::Mul, [Reg(inp1), Imm(inp2)]),
+            "banr" => (Opcode::BitAnd, [Reg(inp1), Reg(inp2)]),
+            "bani" => (Opcode::BitAnd, [Reg(inp1), Imm(inp2)]),
+            "borr" => (Opcode::BitOr, [Reg(inp1), Reg(inp2)]),
+            "bori" => (Opcode::BitOr, [Reg(inp1), Imm(inp2)]),
+            "setr" => (Opcode::Set, [Reg(inp1), Imm(inp2)]),
+            "seti" => (Opcode::Set, [Imm(inp1), Imm(inp2)]),
+            "gtir" => (Opcode::Gt, [Imm(inp1), Reg(inp2)]),
+            "gtri" => (Opcode::Gt, [Reg(inp1), Imm(inp2)]),
+            "gtrr" => (Opcode::Gt, [Reg(inp1), Reg(inp2)]),
+            "eqir" => (Opcode::Eq, [Imm(inp1), Reg(inp2)]),
+            "eqri" => (Opcode::Eq, [Reg(inp1), Imm(inp2)]),
+            "eqrr" => (Opcode::Eq, [Reg(inp1), Reg(inp2)]),
+            _ => return None,
+        };
+
+        Some(Self {
+            name: name.to_string(),
+            opcode,
+            input,
+            output_reg,
+        })
+    }
+
+    #[inline]
+    pub fn execute(&self, regs: &mut [usize]) {
+        let [a, b] = self.input;
+        let (a, b) = (a.get(regs), b.get(regs));
+
+        regs[self.output_reg] = match self.opcode {
+            Opcode::Add => a + b,
+            Opcode::Mul => a * b,
+            Opcode::BitAnd => a & b,
+            Opcode::BitOr => a | b,
+            Opcode::Set => a,
+            Opcode::Gt => (a > b) as usize,
+            Opcode::Eq => (a == b) as usize,
+        };
+    }
+}
+
+impl fmt::Debug for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} = {} {:?} {:?}",
+            self.output_reg, self.name, self.input[0], self.input[1]
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+    Reg(usize),
+    Imm(usize),
+}
+
+impl Value {
+    #[inline]
+    pub fn get(self, regs: &[usize]) -> usize {
+        match self {
+            Value::Reg(r) => regs[r],
+            Value::Imm(i) => i,
+        }
+    }
+}
+
+// What an instruction does with its two inputs. Whether those are registers
+// or immediate values is up to the Values, so e.g. addr and addi are both Add.
+// Matching on this in execute is a lot faster than calling a boxed closure,
+// which matters since day 21 runs billions of instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Add,
+    Mul,
+    BitAnd,
+    BitOr,
+    Set,
+    Gt,
+    Eq,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The sample program from day 19.
+    const SAMPLE_PROGRAM: &str = "#ip 0
+seti 5 0 1
+seti 6 0 2
+addi 0 1 0
+addr 1 2 3
+setr 1 0 0
+seti 8 0 4
+seti 9 0 5";
+
+    #[test]
+    fn run_sample_program() {
+        let (ip_register, code) = parse_program(SAMPLE_PROGRAM).unwrap();
+
+        assert_eq!(ip_register, 0);
+        assert_eq!(code.len(), 7);
+        // The instruction pointer is register 0, so it ends up just past the
+        // last instruction that ran.
+        assert_eq!(
+            run(&code, ip_register, vec![0; NUM_REGISTERS]),
+            [7, 5, 6, 0, 0, 9]
+        );
+    }
+
+    #[test]
+    fn sample_matching_opcodes() {
+        // The sample from day 16.
+        let matching = matching_opcodes(&[3, 2, 1, 1], (2, 1), 2, &[3, 2, 2, 1]).collect_vec();
+
+        assert_eq!(matching, ["addi", "mulr", "seti"]);
+    }
+
+    #[test]
+    fn registers_out_of_range() {
+        // Register 4 doesn't exist when there are only four registers, so
+        // only the opcodes that take 4 as an immediate value can match, and
+        // of those only eqir leaves register 0 as 0.
+        assert_eq!(
+            matching_opcodes(&[0, 0, 0, 0], (4, 0), 0, &[0, 0, 0, 0]).collect_vec(),
+            ["eqir"]
+        );
+        assert_eq!(
+            matching_opcodes(&[0, 0, 0, 0], (0, 0), 4, &[0, 0, 0, 0]).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn invalid_programs() {
+        assert!(parse_program("").is_err());
+        assert!(parse_program("#ip 6\nseti 0 0 0").is_err());
+        assert!(parse_program("#ip 0\nnope 0 0 0").is_err());
+        assert!(parse_program("#ip 0\nseti 0 0").is_err());
+    }
+}
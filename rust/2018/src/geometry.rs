@@ -0,0 +1,176 @@
+use derive_more::From;
+use itertools::Itertools;
+use num::{
+    traits::{AsPrimitive, NumAssignOps},
+    CheckedAdd, CheckedSub, Num, Unsigned,
+};
+use std::{convert::TryInto, fmt, iter, ops::Index, slice, str::FromStr};
+
+#[derive(Clone, Copy, From, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Point<N: Num, const D: usize>(pub [N; D]);
+
+impl<N: Num + Default, const D: usize> Default for Point<N, D> {
+    fn default() -> Self {
+        Self(
+            match iter::repeat_with(N::default)
+                .take(D)
+                .collect_vec()
+                .try_into()
+            {
+                Ok(array) => array,
+                Err(_) => unsafe { std::hint::unreachable_unchecked() },
+            },
+        )
+    }
+}
+
+impl<N: Num + fmt::Debug, const D: usize> fmt::Debug for Point<N, D> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut tuple_output = fmt.debug_tuple("");
+
+        for c in self.iter() {
+            tuple_output.field(c);
+        }
+
+        tuple_output.finish()
+    }
+}
+
+impl<N: Num, const D: usize> Index<usize> for Point<N, D> {
+    type Output = N;
+
+    fn index(&self, axis: usize) -> &N {
+        &self.0[axis]
+    }
+}
+
+impl<N: Num, const D: usize> Point<N, D> {
+    pub fn iter(&self) -> slice::Iter<'_, N> {
+        self.0.iter()
+    }
+}
+
+// Did I make this unnecesssarily generic and therefore
+// complicated? Yes. But to be fair I thought it'd
+// be simpler than this and I wanted to mess around
+// with generics anyway.
+impl<N, const D: usize> Point<N, D>
+where
+    N: Num + PartialOrd,
+{
+    pub fn manhattan_distance<R>(&self, other: &Point<N, D>) -> R
+    where
+        R: 'static + Unsigned + Copy + NumAssignOps,
+        N: AsPrimitive<R>,
+    {
+        let mut total = R::zero();
+
+        for (&self_coord, &other_coord) in self.iter().zip(other.iter()) {
+            // We could use num::abs here, but for some absurd
+            // reason that requires N: num::Signed, which is unnecessarily
+            // restrictive for our purposes.
+            total += (if self_coord > other_coord {
+                self_coord - other_coord
+            } else {
+                other_coord - self_coord
+            })
+            .as_();
+            // We know for a fact that this has to be positive,
+            // so the "as" conversion to an R: Unsigned type
+            // will be fine.
+        }
+
+        total
+    }
+}
+
+impl<N, const D: usize> Point<N, D>
+where
+    N: Num + Copy + CheckedAdd + CheckedSub,
+{
+    // One step away along a single axis in either direction. Anything that
+    // would over- or underflow is left out, so points with unsigned
+    // coordinates don't need to check for zero themselves.
+    pub fn orthogonal_neighbors(self) -> impl Iterator<Item = Self> {
+        (0..D).flat_map(move |axis| {
+            let coord = self.0[axis];
+
+            vec![coord.checked_sub(&N::one()), coord.checked_add(&N::one())]
+                .into_iter()
+                .flatten()
+                .map(move |new_coord| {
+                    let mut neighbor = self;
+                    neighbor.0[axis] = new_coord;
+                    neighbor
+                })
+        })
+    }
+}
+
+impl<N: Num + FromStr, const D: usize> FromStr for Point<N, D> {
+    type Err = ParsePointError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use ParsePointError::*;
+
+        let coords: Vec<N> = s
+            .split(',')
+            .enumerate()
+            .map(|(axis, coord_str)| {
+                coord_str.trim().parse().map_err(|_| ParseCoordinateError {
+                    axis,
+                    coord_str: coord_str.to_owned(),
+                })
+            })
+            .try_collect()?;
+
+        let found = coords.len();
+
+        Ok(Self(
+            coords
+                .try_into()
+                .map_err(|_| WrongDimensions { expected: D, found })?,
+        ))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParsePointError {
+    #[error("Expected {} comma-separated coordinates, found {}", expected, found)]
+    WrongDimensions { expected: usize, found: usize },
+    #[error("Coordinate {} ({:?}) can't be parsed", axis, coord_str)]
+    ParseCoordinateError { axis: usize, coord_str: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted_neighbors<N, const D: usize>(point: Point<N, D>) -> Vec<Point<N, D>>
+    where
+        N: Num + Copy + Ord + CheckedAdd + CheckedSub,
+    {
+        point.orthogonal_neighbors().sorted().collect()
+    }
+
+    #[test]
+    fn orthogonal_neighbors() {
+        assert_eq!(
+            sorted_neighbors(Point([2_usize, 3])),
+            [Point([1, 3]), Point([2, 2]), Point([2, 4]), Point([3, 3])]
+        );
+        assert_eq!(sorted_neighbors(Point([0_i32, 0, 0, 0])).len(), 8);
+    }
+
+    #[test]
+    fn orthogonal_neighbors_on_the_edge() {
+        assert_eq!(
+            sorted_neighbors(Point([0_usize, 0])),
+            [Point([0, 1]), Point([1, 0])]
+        );
+        assert_eq!(
+            sorted_neighbors(Point([u8::MAX, 0])),
+            [Point([u8::MAX - 1, 0]), Point([u8::MAX, 1])]
+        );
+    }
+}
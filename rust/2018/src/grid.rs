@@ -0,0 +1,94 @@
+use crate::geometry::Point;
+use anyhow::{bail, Context};
+
+// A rectangular grid, stored row by row, with [0, 0] in the top left corner.
+// Points are [x, y].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // The top left and bottom right points of the grid, both inclusive, or
+    // None if the grid is empty.
+    pub fn bounds(&self) -> Option<(Point<usize, 2>, Point<usize, 2>)> {
+        if self.cells.is_empty() {
+            return None;
+        }
+
+        Some((Point([0, 0]), Point([self.width - 1, self.height - 1])))
+    }
+
+    pub fn get(&self, point: Point<usize, 2>) -> Option<&T> {
+        let Point([x, y]) = point;
+
+        if x >= self.width {
+            return None;
+        }
+
+        self.cells.get(y * self.width + x)
+    }
+
+    // Goes through every cell in reading order.
+    pub fn iter_points(&self) -> impl Iterator<Item = (Point<usize, 2>, &T)> {
+        let width = self.width;
+
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(idx, cell)| (Point([idx % width, idx / width]), cell))
+    }
+}
+
+// Turns each character of a block of text into a cell of a grid, failing if
+// any of the lines are a different length from the first one.
+pub fn parse<T>(
+    grid_str: &str,
+    mut cell_fn: impl FnMut(char, Point<usize, 2>) -> Result<T, anyhow::Error>,
+) -> Result<Grid<T>, anyhow::Error> {
+    let mut cells = vec![];
+    let mut width = None;
+    let mut height = 0;
+
+    for (y, row) in grid_str.lines().enumerate() {
+        let row_width = row.chars().count();
+
+        match width {
+            None => width = Some(row_width),
+            Some(width) if width != row_width => bail!(
+                "Row {} is {} characters wide, but the rows above it are {}",
+                y,
+                row_width,
+                width
+            ),
+            _ => {}
+        }
+
+        for (x, cell_char) in row.chars().enumerate() {
+            let point = Point([x, y]);
+
+            cells.push(
+                cell_fn(cell_char, point)
+                    .with_context(|| format!("Invalid cell at {:?}", point))?,
+            );
+        }
+
+        height += 1;
+    }
+
+    Ok(Grid {
+        width: width.unwrap_or(0),
+        height,
+        cells,
+    })
+}
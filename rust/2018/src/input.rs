@@ -0,0 +1,33 @@
+use std::{
+    fs,
+    io::{self, Read},
+};
+
+// Reads the whole puzzle input, either from the file it names or from stdin
+// if it's "-", so input can be piped in without having to save it first.
+// Windows line endings are turned into plain \n ones, so that no parser ever
+// has to deal with a stray \r at the end of a line.
+pub fn read_input(arg: &str) -> io::Result<String> {
+    let input = if arg == "-" {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+
+        input
+    } else {
+        fs::read_to_string(arg)?
+    };
+
+    Ok(input.replace("\r\n", "\n"))
+}
+
+// The same as read_input, but without the newline at the end of the file,
+// which is handy for inputs that are just one line.
+pub fn read_input_trimmed(arg: &str) -> io::Result<String> {
+    let mut input = read_input(arg)?;
+
+    if input.ends_with('\n') {
+        input.pop();
+    }
+
+    Ok(input)
+}
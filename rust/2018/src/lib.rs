@@ -0,0 +1,10 @@
+#![allow(incomplete_features)]
+#![feature(min_const_generics)]
+
+pub mod elfcode;
+pub mod geometry;
+pub mod grid;
+pub mod input;
+pub mod search;
+pub mod summed_area_table;
+pub mod timing;
@@ -0,0 +1,128 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    hash::Hash,
+};
+
+// Finds the cheapest path from start to goal, returning its total cost and
+// every node along it, start and goal included. neighbors gives the nodes
+// reachable in one step along with what that step costs, and heuristic
+// estimates the cost from a node to the goal. As long as the heuristic never
+// overestimates, the path that comes out is guaranteed to be the cheapest one.
+// A heuristic that's always 0 turns this into plain old Dijkstra's.
+pub fn astar<N>(
+    start: N,
+    goal: N,
+    mut neighbors: impl FnMut(&N) -> Vec<(N, usize)>,
+    mut heuristic: impl FnMut(&N) -> usize,
+) -> Option<(usize, Vec<N>)>
+where
+    N: Hash + Eq + Clone,
+{
+    // The heap only holds indexes into this, so that N doesn't need to be Ord.
+    let mut nodes = vec![start.clone()];
+    let mut best_costs = HashMap::new();
+    let mut came_from = HashMap::new();
+    let mut frontier = BinaryHeap::new();
+
+    best_costs.insert(start.clone(), 0);
+    frontier.push(SearchNode {
+        estimated_cost: Reverse(heuristic(&start)),
+        cost: 0,
+        node_idx: 0,
+    });
+
+    while let Some(SearchNode { cost, node_idx, .. }) = frontier.pop() {
+        let current = nodes[node_idx].clone();
+
+        // We've already found a cheaper way here since this was queued.
+        if best_costs[&current] < cost {
+            continue;
+        }
+
+        if current == goal {
+            let mut path = vec![current];
+
+            while let Some(prev) = came_from.get(path.last().unwrap()) {
+                path.push(N::clone(prev));
+            }
+
+            path.reverse();
+
+            return Some((cost, path));
+        }
+
+        for (next, step_cost) in neighbors(&current) {
+            let next_cost = cost + step_cost;
+
+            if best_costs.get(&next).map_or(true, |&c| next_cost < c) {
+                best_costs.insert(next.clone(), next_cost);
+                came_from.insert(next.clone(), current.clone());
+
+                frontier.push(SearchNode {
+                    estimated_cost: Reverse(next_cost + heuristic(&next)),
+                    cost: next_cost,
+                    node_idx: nodes.len(),
+                });
+                nodes.push(next);
+            }
+        }
+    }
+
+    None
+}
+
+// Private helper to make astar easier to keep track of. The fields are
+// ordered so that a max heap pops the node with the lowest estimated total
+// cost first, and out of those, the one that's furthest along.
+#[derive(Eq, PartialEq, Ord, PartialOrd)]
+struct SearchNode {
+    estimated_cost: Reverse<usize>,
+    cost: usize,
+    node_idx: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A -1-> B -1-> C -1-> D
+    // |                    ^
+    // +---------5----------+
+    //
+    // Plus E, which has no way in, so it's never reachable.
+    fn neighbors(node: &char) -> Vec<(char, usize)> {
+        match node {
+            'A' => vec![('B', 1), ('D', 5)],
+            'B' => vec![('C', 1)],
+            'C' => vec![('D', 1)],
+            'E' => vec![('A', 1)],
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn long_way_round() {
+        // The exact number of steps left to D, which never overestimates.
+        let heuristic = |node: &char| match node {
+            'A' => 3,
+            'B' => 2,
+            'C' => 1,
+            _ => 0,
+        };
+
+        // Going straight to D costs 5, so the three steps through B and C
+        // are cheaper.
+        assert_eq!(
+            astar('A', 'D', neighbors, heuristic),
+            Some((3, vec!['A', 'B', 'C', 'D']))
+        );
+        assert_eq!(
+            astar('A', 'D', neighbors, |_| 0),
+            astar('A', 'D', neighbors, heuristic)
+        );
+        assert_eq!(astar('A', 'A', neighbors, heuristic), Some((0, vec!['A'])));
+        assert_eq!(astar('B', 'A', neighbors, |_| 0), None);
+        assert_eq!(astar('A', 'E', neighbors, |_| 0), None);
+    }
+}
@@ -0,0 +1,231 @@
+use std::{error::Error, fmt};
+
+// Lets you get the sum of any rectangle of a grid in constant time, after
+// one pass over the grid to build it.
+// See https://en.wikipedia.org/wiki/Summed-area_table
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SummedAreaTable {
+    // This has an extra row and column of zeroes at the start, so that
+    // I(x, y) is the sum of everything in the grid above and to the left of
+    // (x, y), not including row y and column x themselves. This means nothing
+    // here needs any special cases for the edges.
+    table: Vec<Vec<isize>>,
+}
+
+impl SummedAreaTable {
+    pub fn from_grid(grid: &[Vec<isize>]) -> Result<Self, NonRectError> {
+        let width = grid.first().map_or(0, |row| row.len());
+        let mut table = vec![vec![0; width + 1]; grid.len() + 1];
+
+        for (yi, row) in grid.iter().enumerate() {
+            if row.len() != width {
+                return Err(NonRectError {
+                    yi,
+                    row_len: row.len(),
+                    expected_len: width,
+                });
+            }
+
+            for (xi, &value) in row.iter().enumerate() {
+                // The value of the summed-area table at (x + 1, y + 1) is simply
+                // (where I provides previous values in the table, and i provides
+                // values in the original grid):
+                //
+                // I(x + 1, y + 1) = i(x, y) + I(x + 1, y) + I(x, y + 1) - I(x, y)
+                let north = table[yi][xi + 1];
+                let west = table[yi + 1][xi];
+                let northwest = table[yi][xi];
+
+                table[yi + 1][xi + 1] = value + north + west - northwest;
+            }
+        }
+
+        Ok(Self { table })
+    }
+
+    // Not counting the padding row and column.
+    pub fn width(&self) -> usize {
+        self.table[0].len() - 1
+    }
+
+    pub fn height(&self) -> usize {
+        self.table.len() - 1
+    }
+
+    // The top left and bottom right corners of the original grid, both
+    // inclusive, or None if it was empty.
+    pub fn bounds(&self) -> Option<((usize, usize), (usize, usize))> {
+        if self.width() == 0 || self.height() == 0 {
+            return None;
+        }
+
+        Some(((0, 0), (self.width() - 1, self.height() - 1)))
+    }
+
+    // The sum of everything from (x0, y0) to (x1, y1) in the original grid,
+    // both corners included. Panics if the rectangle is backwards or doesn't
+    // fit in the grid, the same way indexing out of bounds would.
+    pub fn rect_sum(&self, x0: usize, y0: usize, x1: usize, y1: usize) -> isize {
+        assert!(
+            x0 <= x1 && y0 <= y1 && x1 < self.width() && y1 < self.height(),
+            "({}, {}) to ({}, {}) isn't a rectangle inside a {}x{} grid",
+            x0,
+            y0,
+            x1,
+            y1,
+            self.width(),
+            self.height()
+        );
+
+        // Thanks to the padding, the rectangle's corners in the table are
+        // exactly (x0, y0) and (x1 + 1, y1 + 1).
+        self.table[y1 + 1][x1 + 1] - self.table[y0][x1 + 1] - self.table[y1 + 1][x0]
+            + self.table[y0][x0]
+    }
+
+    // Goes through every place a rect_width x rect_height rectangle fits in
+    // the grid, in reading order, giving the top left corner and the sum.
+    pub fn iter_rects(
+        &self,
+        rect_width: usize,
+        rect_height: usize,
+    ) -> impl Iterator<Item = ((usize, usize), isize)> + '_ {
+        let num_ys = if rect_height == 0 {
+            0
+        } else {
+            (self.height() + 1).saturating_sub(rect_height)
+        };
+        let num_xs = if rect_width == 0 {
+            0
+        } else {
+            (self.width() + 1).saturating_sub(rect_width)
+        };
+
+        (0..num_ys)
+            .flat_map(move |y0| (0..num_xs).map(move |x0| (x0, y0)))
+            .map(move |(x0, y0)| {
+                (
+                    (x0, y0),
+                    self.rect_sum(x0, y0, x0 + rect_width - 1, y0 + rect_height - 1),
+                )
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NonRectError {
+    yi: usize,
+    row_len: usize,
+    expected_len: usize,
+}
+
+impl fmt::Display for NonRectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "grid is not a rectangular 2d Vec: row {} has {} columns, but row 0 has {}",
+            self.yi, self.row_len, self.expected_len
+        )
+    }
+}
+
+impl Error for NonRectError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1 2 3 4
+    // 5 6 7 8
+    // 9 0 1 2
+    fn grid() -> Vec<Vec<isize>> {
+        vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8], vec![9, 0, 1, 2]]
+    }
+
+    // Adds the rectangle up the slow way, to check against.
+    fn brute_force_sum(grid: &[Vec<isize>], x0: usize, y0: usize, x1: usize, y1: usize) -> isize {
+        grid[y0..=y1]
+            .iter()
+            .map(|row| row[x0..=x1].iter().sum::<isize>())
+            .sum()
+    }
+
+    #[test]
+    fn single_cells() {
+        let grid = grid();
+        let sat = SummedAreaTable::from_grid(&grid).unwrap();
+
+        for (yi, row) in grid.iter().enumerate() {
+            for (xi, &value) in row.iter().enumerate() {
+                assert_eq!(sat.rect_sum(xi, yi, xi, yi), value);
+            }
+        }
+    }
+
+    #[test]
+    fn rects_touching_the_edges() {
+        let sat = SummedAreaTable::from_grid(&grid()).unwrap();
+
+        // Top row, left column, bottom right corner.
+        assert_eq!(sat.rect_sum(0, 0, 3, 0), 10);
+        assert_eq!(sat.rect_sum(0, 0, 0, 2), 15);
+        assert_eq!(sat.rect_sum(2, 1, 3, 2), 18);
+    }
+
+    #[test]
+    fn full_grid() {
+        let sat = SummedAreaTable::from_grid(&grid()).unwrap();
+
+        assert_eq!(sat.bounds(), Some(((0, 0), (3, 2))));
+        assert_eq!(sat.rect_sum(0, 0, 3, 2), 48);
+        assert_eq!(sat.iter_rects(4, 3).collect::<Vec<_>>(), [((0, 0), 48)]);
+    }
+
+    #[test]
+    fn every_rect() {
+        let grid = grid();
+        let sat = SummedAreaTable::from_grid(&grid).unwrap();
+
+        for rect_height in 1..=3 {
+            for rect_width in 1..=4 {
+                let rects = sat.iter_rects(rect_width, rect_height).collect::<Vec<_>>();
+
+                assert_eq!(rects.len(), (5 - rect_width) * (4 - rect_height));
+
+                for ((x0, y0), sum) in rects {
+                    let (x1, y1) = (x0 + rect_width - 1, y0 + rect_height - 1);
+                    assert_eq!(sum, brute_force_sum(&grid, x0, y0, x1, y1));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn iter_rects_sizes_that_dont_fit() {
+        let sat = SummedAreaTable::from_grid(&grid()).unwrap();
+
+        assert_eq!(sat.iter_rects(0, 0).count(), 0);
+        assert_eq!(sat.iter_rects(0, 2).count(), 0);
+        assert_eq!(sat.iter_rects(5, 1).count(), 0);
+        assert_eq!(sat.iter_rects(1, 4).count(), 0);
+        assert_eq!(sat.iter_rects(100, 100).count(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rect_outside_grid() {
+        SummedAreaTable::from_grid(&grid())
+            .unwrap()
+            .rect_sum(0, 0, 4, 0);
+    }
+
+    #[test]
+    fn empty_and_ragged_grids() {
+        let sat = SummedAreaTable::from_grid(&[]).unwrap();
+
+        assert_eq!(sat.bounds(), None);
+        assert_eq!(sat.iter_rects(1, 1).count(), 0);
+
+        assert!(SummedAreaTable::from_grid(&[vec![1, 2], vec![3]]).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+// Runs each day's solver on the sample inputs from its puzzle description,
+// and checks the answers against the ones the puzzle gives. Each day is
+// pulled in from its binary as a module, so it's solved exactly the same way
+// main solves it, just without going through the command line. Answers that
+// a day's own tests already check aren't checked again here.
+
+use aoc_2018_rust::{elfcode::parse_program, geometry::Point};
+
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/15/main.rs"]
+mod day15;
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/21/main.rs"]
+mod day21;
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/22/main.rs"]
+mod day22;
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/23/main.rs"]
+mod day23;
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/24/main.rs"]
+mod day24;
+
+#[test]
+fn day_15() {
+    let combat_grid = day15::parse_input(include_str!("../src/bin/15/input_small_2.txt")).unwrap();
+
+    assert_eq!(day15::minimum_elf_power(&combat_grid), (15, 4988));
+}
+
+#[test]
+fn day_21() {
+    // There's no sample program, so this runs the real one. Part 2 takes
+    // far too long to be worth checking here.
+    let (ins_pointer, code) = parse_program(include_str!("../src/bin/21/input.txt")).unwrap();
+    let special_reg = day21::find_special_reg(&code).unwrap();
+
+    let (part1, _, _) =
+        day21::run_watching_special_reg(ins_pointer, &code, 0, special_reg, true, false, false);
+
+    assert_eq!(part1, Some(16457176));
+}
+
+#[test]
+fn day_22() {
+    let (depth, target) =
+        day22::parse_input(include_str!("../src/bin/22/input_small_2.txt")).unwrap();
+
+    assert_eq!(
+        day22::cave_search(depth, target).map(|(min_time, _)| min_time),
+        Some(45)
+    );
+}
+
+#[test]
+fn day_23() {
+    let bots = day23::parse_input(include_str!("../src/bin/23/input_small_2.txt")).unwrap();
+    let (_, num_in_range) = day23::in_range_of_strongest(&bots).unwrap();
+
+    assert_eq!(num_in_range, 7);
+
+    let bots = day23::parse_input(include_str!("../src/bin/23/input_small.txt")).unwrap();
+    let best_point = day23::find_best_point_octree(&bots).unwrap();
+
+    assert_eq!(best_point, Point([12, 12, 12]));
+    assert_eq!(
+        best_point.manhattan_distance::<usize>(&Point([0, 0, 0])),
+        36
+    );
+}
+
+#[test]
+fn day_24() {
+    let groups = day24::parse_input(include_str!("../src/bin/24/input_small.txt")).unwrap();
+
+    let no_boost_result = day24::battle_to_end(groups.clone(), None, None, false).unwrap();
+    assert_eq!(day24::total_units(&no_boost_result), 5216);
+
+    let (boost, boosted_result) =
+        day24::find_min_boost(&groups, &["Immune System"], 1_000_000, false).unwrap();
+    assert_eq!(boost, 1570);
+    assert_eq!(day24::total_units(&boosted_result), 51);
+}
@@ -1,49 +1,85 @@
 use anyhow::anyhow;
+use aoc_2019_rust::{
+    input::read_input,
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
 use itertools::Itertools;
-use std::fs;
+use std::iter;
 
 pub fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2019-1")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
+        .arg(Arg::from_usage(
+            "[time] --time 'Print how long parsing and solving take'",
+        ))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
 
-    let module_masses_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
+    let parse_timer = Timer::start("Parsing", time);
+    let module_masses_str = read_input(input_filename)?;
     let module_masses = parse_input(&module_masses_str)?;
+    drop(parse_timer);
+
+    let (fuel_without_fuel, fuel_with_fuel) = timing::repeat("Solving", repeat, time, || {
+        (part1(&module_masses), part2(&module_masses))
+    });
 
     println!(
         "Total fuel requirements based purely on module mass: {}",
-        module_masses
-            .iter()
-            .map(|&m| calculate_fuel(m))
-            .sum::<usize>()
+        fuel_without_fuel.ok_or_else(|| anyhow!("Total fuel is too big to count"))?
     );
 
     println!(
         "Total fuel requirements with fuel mass: {}",
-        module_masses
-            .iter()
-            .map(|&m| calculate_all_fuel(m))
-            .sum::<usize>()
+        fuel_with_fuel.ok_or_else(|| anyhow!("Total fuel is too big to count"))?
     );
 
     Ok(())
 }
 
+// These return None if the total would overflow, which a handful of
+// huge modules is enough for.
+pub fn part1(module_masses: &[usize]) -> Option<usize> {
+    module_masses
+        .iter()
+        .try_fold(0usize, |total, &m| total.checked_add(calculate_fuel(m)))
+}
+
+// The fuel needs fuel of its own, and so does that fuel, and so on.
+pub fn part2(module_masses: &[usize]) -> Option<usize> {
+    module_masses
+        .iter()
+        .try_fold(0usize, |total, &m| total.checked_add(calculate_all_fuel(m)))
+}
+
+// Each step is at most a third of the one before, so for a single module
+// this can't overflow: it adds up to less than half of the mass.
 fn calculate_all_fuel(mass: usize) -> usize {
-    match calculate_fuel(mass) {
-        0 => 0,
-        x => x + calculate_all_fuel(x),
-    }
+    iter::successors(Some(mass), |&m| Some(calculate_fuel(m)))
+        .skip(1)
+        .take_while(|&fuel| fuel > 0)
+        .sum()
 }
 
 fn calculate_fuel(mass: usize) -> usize {
     (mass / 3).saturating_sub(2)
 }
 
-fn parse_input(module_masses_str: &str) -> Result<Vec<usize>, anyhow::Error> {
+pub fn parse_input(module_masses_str: &str) -> Result<Vec<usize>, anyhow::Error> {
     module_masses_str
         .lines()
         .map(|mass_str| {
@@ -53,3 +89,46 @@ fn parse_input(module_masses_str: &str) -> Result<Vec<usize>, anyhow::Error> {
         })
         .try_collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documented_examples() {
+        for &(mass, fuel, all_fuel) in &[
+            (12, 2, 2),
+            (14, 2, 2),
+            (1969, 654, 966),
+            (100756, 33583, 50346),
+        ] {
+            assert_eq!(calculate_fuel(mass), fuel, "{}", mass);
+            assert_eq!(calculate_all_fuel(mass), all_fuel, "{}", mass);
+        }
+
+        assert_eq!(part1(&[100756]), Some(33583));
+        assert_eq!(part2(&[100756]), Some(50346));
+    }
+
+    #[test]
+    fn huge_module() {
+        let module_masses = parse_input(include_str!("input_huge.txt")).unwrap();
+
+        assert_eq!(part1(&module_masses), Some(usize::MAX / 3 - 2));
+
+        // Every step is a third of the one before, so the fuel's fuel adds
+        // up to a bit more than a third of the mass, but less than half.
+        let all_fuel = part2(&module_masses).unwrap();
+        assert!(all_fuel > usize::MAX / 3 && all_fuel < usize::MAX / 2);
+    }
+
+    #[test]
+    fn total_overflow() {
+        let module_masses = parse_input(include_str!("input_overflow.txt")).unwrap();
+
+        // Three modules' worth of a third of the mass only just fits, but
+        // three lots of the fuel's fuel on top of that doesn't.
+        assert_eq!(part1(&module_masses), Some(3 * (usize::MAX / 3 - 2)));
+        assert_eq!(part2(&module_masses), None);
+    }
+}
@@ -1,233 +1,261 @@
 #![feature(iter_partition_in_place, box_syntax)]
 
 use anyhow::{anyhow, bail};
+use aoc_2019_rust::{geometry::Point2, grid, input::read_input, timing::{self, Timer}};
 use clap::{App, Arg};
-use derive_more::From;
 use itertools::Itertools;
 use multimap::MultiMap;
 use ordered_float::OrderedFloat;
-use std::{cmp::Reverse, collections::HashSet, fmt, fs, iter};
+use std::{collections::HashSet, f64::consts::PI, iter};
 
 fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2019-10")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
+        .arg(Arg::from_usage(
+            "[dump_order] --dump-order 'Print every asteroid in the order it's vaporized'",
+        ))
+        .arg(Arg::from_usage(
+            "[time] --time 'Print how long parsing and solving take'",
+        ))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
-
-    let asteroid_map_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
+
+    let parse_timer = Timer::start("Parsing", time);
+    let asteroid_map_str = read_input(input_filename)?;
     let asteroid_positions = parse_input(&asteroid_map_str)?;
+    drop(parse_timer);
 
-    let (best_asteroid, best_asteroid_visibility) = asteroid_positions
-        .iter()
-        .map(|&potential_station| {
-            (
-                potential_station,
-                iter_visible_from(potential_station, asteroid_positions.clone()).count(),
-            )
-        })
-        .max_by_key(|&(_, visible)| visible)
-        .ok_or_else(|| anyhow!("Couldn't find best asteroid - input empty"))?;
+    let (best_asteroid, best_asteroid_visibility, vaporized_200th) =
+        timing::repeat("Solving", repeat, time, || -> Result<_, anyhow::Error> {
+            let (best_asteroid, best_asteroid_visibility) = best_station(&asteroid_positions)
+                .ok_or_else(|| anyhow!("Couldn't find best asteroid - input empty"))?;
+
+            let vaporized_200th =
+                iter_vaporize_from(best_asteroid, asteroid_positions.clone()).nth(199);
+
+            Ok((best_asteroid, best_asteroid_visibility, vaporized_200th))
+        })?;
 
     println!(
         "Best place to position a new station is: {:?}, where {} asteroids are visibile",
-        best_asteroid, best_asteroid_visibility,
+        to_map_position(best_asteroid),
+        best_asteroid_visibility,
+    );
+
+    let vaporized_200th = to_map_position(
+        vaporized_200th.ok_or_else(|| anyhow!("Less than 200 asteroids are vaporized"))?,
     );
 
     println!(
-        "200th asteroid to be vaporized is {:?}",
-        iter_vaporize_from(best_asteroid, asteroid_positions)
-            .nth(199)
-            .ok_or_else(|| anyhow!("Less than 200 asteroids are vaporized"))?
+        "200th asteroid to be vaporized is {:?}, so the answer is {}",
+        vaporized_200th,
+        vaporized_200th.x * 100 + vaporized_200th.y
     );
 
+    if matches.is_present("dump_order") {
+        for (idx, asteroid) in iter_vaporize_from(best_asteroid, asteroid_positions).enumerate() {
+            println!("{}: {:?}", idx + 1, to_map_position(asteroid));
+        }
+    }
+
     Ok(())
 }
 
+// Gives the asteroid that can see the most other asteroids, along with how
+// many it can see.
+fn best_station(asteroid_positions: &HashSet<Point2>) -> Option<(Point2, usize)> {
+    asteroid_positions
+        .iter()
+        .map(|&potential_station| {
+            (
+                potential_station,
+                iter_visible_from(potential_station, asteroid_positions.clone()).count(),
+            )
+        })
+        .max_by_key(|&(_, visible)| visible)
+}
+
 fn iter_vaporize_from(
-    station: Point,
-    mut asteroid_positions: HashSet<Point>,
-) -> impl Iterator<Item = Point> {
-    let mut current_visible_iter: Option<Box<dyn Iterator<Item = Point>>> = None;
+    station: Point2,
+    mut asteroid_positions: HashSet<Point2>,
+) -> impl Iterator<Item = Point2> {
+    let mut current_visible_iter: Option<Box<dyn Iterator<Item = Point2>>> = None;
 
     iter::from_fn(move || {
-        if let Some(next_vaporized) = current_visible_iter.as_mut().and_then(|i| i.next()) {
-            asteroid_positions.remove(&next_vaporized);
+        let next_vaporized = match current_visible_iter.as_mut().and_then(|i| i.next()) {
+            Some(next_vaporized) => next_vaporized,
+            None => {
+                // The laser's finished a rotation, so we look again at what's
+                // visible now that the closest asteroids have been cleared.
+                current_visible_iter = Some(box iter_visible_from(station, asteroid_positions.clone()));
+
+                current_visible_iter.as_mut().and_then(|i| i.next())?
+            }
+        };
 
-            Some(next_vaporized)
-        } else {
-            current_visible_iter = Some(box iter_visible_from(station, asteroid_positions.clone()));
+        // This has to happen for the first asteroid of a rotation too, or
+        // it'd come back in the next one and we'd never run out.
+        asteroid_positions.remove(&next_vaporized);
 
-            current_visible_iter.as_mut().and_then(|i| i.next())
-        }
+        Some(next_vaporized)
     })
 }
 
+// Yields the closest asteroid in each direction from the station, going
+// clockwise from straight up, which is exactly the order the laser hits them
+// in during a single rotation.
 fn iter_visible_from(
-    station: Point,
-    asteroid_positions: HashSet<Point>,
-) -> impl Iterator<Item = Point> {
-    let mut relative_slopes = all_slopes_relative(station, asteroid_positions)
+    station: Point2,
+    asteroid_positions: HashSet<Point2>,
+) -> impl Iterator<Item = Point2> {
+    let mut directions = directions_relative(station, &asteroid_positions)
         .into_iter()
         .collect_vec();
 
-    relative_slopes.sort_unstable_by_key(|&(slope, _)| Reverse(slope));
+    // Every direction here is different, and since they're all reduced, no
+    // two of them can point the same way. That means the angles only matter
+    // for ordering, so it's fine for them to be floats.
+    directions.sort_by_cached_key(|&((dx, dy), _)| OrderedFloat(clockwise_angle(dx, dy)));
 
-    IterVisible {
-        center: station,
-        pos: 0,
-        on_right_side: true,
-        ordered_relative_slopes: relative_slopes
+    directions.into_iter().filter_map(move |(_, points)| {
+        points
             .into_iter()
-            .map(|(slope, points)| (slope.into_inner(), points))
-            .collect(),
-    }
-}
-
-// We're rotating an imaginary line around the center of a Cartesian plane.
-// The line rotates clockwise, so it goes from quadrant 1 to Q4 to Q3 to Q2.
-// When we access the points on a given line from ordered_relative_slopes,
-// we access the ones on both sides of the center (so in two different quadrants),
-// so we need to keep track of which direction we're looking at.
-struct IterVisible {
-    center: Point,
-    pos: usize,
-    on_right_side: bool,
-    ordered_relative_slopes: Vec<(f64, Vec<Point>)>,
-}
-
-impl Iterator for IterVisible {
-    type Item = Point;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        // The slope we're on might not have any points on its line,
-        // at least not in the direction we're currently looking in, but that
-        // doesn't mean we can terminate iteration. We have to keep checking
-        // until we find the next visible point.
-        loop {
-            if self.pos >= self.ordered_relative_slopes.len() {
-                if !self.on_right_side {
-                    return None;
-                }
-
-                self.pos = 0;
-                self.on_right_side = false;
-            }
-
-            let (slope, visibility_line) = &self.ordered_relative_slopes[self.pos];
-
-            let (before_points, after_points) = visibility_line.into_iter().partition(|p| {
-                if *slope != 0. {
-                    p.y < self.center.y
-                } else {
-                    // The line is straight and horizontal,
-                    // in which case all y's are the same.
-                    p.x < self.center.x
-                }
-            });
-
-            // For us to use the after points, we must either be in positive
-            // slopes on the right side or negative slopes on the left side.
-            let front_points: Vec<_> = if (*slope >= 0.) == self.on_right_side {
-                after_points
-            } else {
-                before_points
-            };
-
-            let min_front_point = front_points
-                .into_iter()
-                .min_by_key(|&p| OrderedFloat(Point::distance(&self.center, p)))
-                .copied();
-
-            self.pos += 1;
-
-            if min_front_point.is_some() {
-                return min_front_point;
-            }
-        }
-    }
-
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.ordered_relative_slopes.len(), None)
-    }
+            .min_by_key(|p| station.manhattan_distance(p))
+    })
 }
 
-fn all_slopes_relative(
-    station: Point,
-    asteroid_positions: HashSet<Point>,
-) -> MultiMap<OrderedFloat<f64>, Point> {
+// Groups asteroids by the direction they're in from the station. The
+// direction is (dx, dy) divided by their GCD, so asteroids on the same line
+// of sight get exactly the same key, without any floating point involved.
+fn directions_relative(
+    station: Point2,
+    asteroid_positions: &HashSet<Point2>,
+) -> MultiMap<(isize, isize), Point2> {
     asteroid_positions
         .iter()
-        .filter(|&a| a != &station)
+        .filter(|&&a| a != station)
         .map(|&other_asteroid| {
-            (
-                OrderedFloat(Point::slope(&station, &other_asteroid)),
-                other_asteroid,
-            )
+            let (dx, dy) = (other_asteroid.x - station.x, other_asteroid.y - station.y);
+            // This can't be 0, since we've filtered out the station itself.
+            let divisor = gcd(dx.abs(), dy.abs());
+
+            ((dx / divisor, dy / divisor), other_asteroid)
         })
         .collect()
 }
 
-fn parse_input(asteroid_map_str: &str) -> Result<HashSet<Point>, anyhow::Error> {
-    let mut asteroid_positions = HashSet::new();
-
-    for (row_idx, row) in asteroid_map_str.lines().enumerate() {
-        for (column_idx, pos_char) in row.chars().enumerate() {
-            match pos_char {
-                '.' => continue,
-                '#' => {
-                    // The points are all represented as being in Q4 (positive X, negative Y),
-                    // so that all the slope and distance calculations work out properly.
-                    // If we used positive numbers for both of them, we'd end up with
-                    // opposite-signed slopes for some points.
-                    asteroid_positions.insert(Point::new(column_idx as isize, -(row_idx as isize)));
-                }
-                _ => bail!("Unknown character: {}", pos_char),
-            }
-        }
+// The angle of (dx, dy) going clockwise from straight up, between 0 and 2pi.
+// Straight up is positive y here, because parse_input flips the map.
+fn clockwise_angle(dx: isize, dy: isize) -> f64 {
+    let angle = (dx as f64).atan2(dy as f64);
+
+    if angle < 0. {
+        angle + 2. * PI
+    } else {
+        angle
     }
+}
 
-    Ok(asteroid_positions)
+// See https://en.wikipedia.org/wiki/Greatest_common_divisor#Euclid%27s_algorithm
+fn gcd(a: isize, b: isize) -> isize {
+    if a == 0 {
+        b
+    } else if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, From)]
-struct Point {
-    x: isize,
-    y: isize,
+fn parse_input(asteroid_map_str: &str) -> Result<HashSet<Point2>, anyhow::Error> {
+    let asteroid_map = grid::parse(asteroid_map_str, |pos_char, _| match pos_char {
+        '.' => Ok(false),
+        '#' => Ok(true),
+        _ => bail!("Unknown character: {}", pos_char),
+    })?;
+
+    Ok(asteroid_map
+        .iter_points()
+        .filter(|&(_, &is_asteroid)| is_asteroid)
+        // y is flipped so that it goes up the map rather than down, which
+        // makes positive y straight up, the way clockwise_angle measures
+        // from. Otherwise the laser would start pointing down and go round
+        // anticlockwise.
+        .map(|(pos, _)| Point2::new(pos.x, -pos.y))
+        .collect())
 }
 
-impl fmt::Debug for Point {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_tuple("").field(&self.x).field(&self.y).finish()
-    }
+// Undoes the flip that parse_input does, so that positions are printed the
+// same way the puzzle describes them, with y going down the map.
+fn to_map_position(pos: Point2) -> Point2 {
+    Point2::new(pos.x, -pos.y)
 }
 
-impl Point {
-    fn new(x: isize, y: isize) -> Self {
-        Self::from((x, y))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn best_map_station(asteroid_map_str: &str) -> (Point2, usize) {
+        let (station, visible) = best_station(&parse_input(asteroid_map_str).unwrap()).unwrap();
+
+        (to_map_position(station), visible)
     }
 
-    fn slope(p1: &Self, p2: &Self) -> f64 {
-        // Cast to isize to avoid overflows
-        let slope = (p2.y - p1.y) as f64 / (p2.x - p1.x) as f64;
-
-        if slope.is_infinite() {
-            // We've done (y2 - y) / 0., which means the two points
-            // are on a vertical line, in which case the sign
-            // of the infinity doesn't matter.
-            slope.abs()
-        } else if slope.is_nan() {
-            // We've done 0. / 0., which means the two points
-            // are exactly the same.
-            0.
-        } else {
-            slope
+    #[test]
+    fn best_stations() {
+        for &(asteroid_map_str, x, y, visible) in &[
+            (include_str!("input_small.txt"), 3, 4, 8),
+            (include_str!("input_medium_1.txt"), 5, 8, 33),
+            (include_str!("input_medium_2.txt"), 1, 2, 35),
+            (include_str!("input_medium_3.txt"), 6, 3, 41),
+            (include_str!("input_large.txt"), 11, 13, 210),
+        ] {
+            assert_eq!(
+                best_map_station(asteroid_map_str),
+                (Point2::new(x, y), visible)
+            );
         }
     }
 
-    fn distance(p1: &Self, p2: &Self) -> f64 {
-        // sqrt returns NaN only if the original number is
-        // negative, which isn't possible in this case.
-        (((p2.x - p1.x).pow(2) + (p2.y - p1.y).pow(2)) as f64).sqrt()
+    #[test]
+    fn vaporize_order() {
+        let asteroid_positions = parse_input(include_str!("input_large.txt")).unwrap();
+        let station = to_map_position(Point2::new(11, 13));
+
+        let vaporized = iter_vaporize_from(station, asteroid_positions.clone())
+            .map(to_map_position)
+            .collect_vec();
+
+        // Every asteroid apart from the station gets vaporized eventually.
+        assert_eq!(vaporized.len(), asteroid_positions.len() - 1);
+
+        for &(nth, x, y) in &[
+            (1, 11, 12),
+            (2, 12, 1),
+            (3, 12, 2),
+            (10, 12, 8),
+            (20, 16, 0),
+            (50, 16, 9),
+            (100, 10, 16),
+            (199, 9, 6),
+            (200, 8, 2),
+            (201, 10, 9),
+            (299, 11, 1),
+        ] {
+            assert_eq!(vaporized[nth - 1], Point2::new(x, y), "vaporized #{}", nth);
+        }
     }
 }
@@ -1,84 +1,106 @@
-#![feature(entry_insert, destructuring_assignment)]
-
-use anyhow::{anyhow, bail, ensure};
+#![feature(destructuring_assignment)]
+
+use anyhow::anyhow;
+use aoc_2019_rust::{
+    geometry::{Direction, Point2},
+    grid::Grid,
+    input::read_input_trimmed,
+    intcode::{parse_program, run_program},
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
-use derive_more::From;
-use digits_iterator::*;
-use itertools::Itertools;
-use std::{collections::HashMap, convert::TryFrom, fmt, fs, iter, sync::Mutex};
-use tokio::pin;
-use tokio_stream::{Stream, StreamExt};
+use colored::Colorize;
+use std::{iter, sync::Mutex};
+use tokio_stream::StreamExt;
 
 fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2019-11")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
+        .arg(Arg::from_usage(
+            "[invert] -i --invert 'Draw the white panels of the hull instead of the black ones'",
+        ))
+        .arg(Arg::from_usage(
+            "[time] --time 'Print how long parsing and solving take'",
+        ))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
+
+    let parse_timer = Timer::start("Parsing", time);
+    let program_str = read_input_trimmed(input_filename)?;
+    let robot_program = parse_program(&program_str)?;
+    drop(parse_timer);
+
+    let (painted_hull, registration_id_hull) = timing::repeat("Solving", repeat, time, || {
+        let painted_hull = paint_hull(robot_program.clone(), Grid::sparse(), Color::Black);
+
+        let mut starting_hull = Grid::sparse();
+        starting_hull.set(Point2::origin(), Color::White);
 
-    let program_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
-    let robot_program = parse_input(&program_str)?;
+        let registration_id_hull = paint_hull(robot_program.clone(), starting_hull, Color::Black);
 
-    let painted_hull = paint_hull(robot_program.clone(), HashMap::new(), Color::Black)?;
+        (painted_hull, registration_id_hull)
+    });
+
+    let painted_hull = painted_hull?;
 
     println!(
         "Number of panels painted at least once: {}",
         painted_hull.len()
     );
 
-    let registration_id_hull = paint_hull(
-        robot_program,
-        iter::once((Point::origin(), Color::White)).collect(),
+    print_hull(
+        &registration_id_hull?,
         Color::Black,
-    )?;
-
-    print_hull(&registration_id_hull, Color::Black);
+        matches.is_present("invert"),
+    );
 
     Ok(())
 }
 
-fn print_hull(hull: &HashMap<Point, Color>, default_color: Color) {
-    let ((min_x, max_x), (min_y, max_y)) = (
-        hull.keys()
-            .map(|p| p.x)
-            .minmax()
-            .into_option()
-            .unwrap_or_default(),
-        hull.keys()
-            .map(|p| p.y)
-            .minmax()
-            .into_option()
-            .unwrap_or_default(),
-    );
+fn print_hull(hull: &Grid<Color>, default_color: Color, invert: bool) {
+    // The registration ID is painted in white, so by default it shows up as
+    // gaps in a block of black, which some fonts make hard to read.
+    let drawn_color = if invert { Color::White } else { Color::Black };
 
-    for y in (min_y..=max_y).rev() {
-        for x in min_x..=max_x {
-            if hull.get(&Point::new(x, y)).unwrap_or(&default_color) == &Color::Black {
-                print!("█");
+    print!(
+        "{}",
+        hull.render(|color| {
+            if color.unwrap_or(&default_color) == &drawn_color {
+                "█".normal()
             } else {
-                print!(" ");
+                " ".normal()
             }
-        }
-
-        println!()
-    }
+        })
+    );
 }
 
 fn paint_hull(
     robot_program: Vec<isize>,
-    starting_hull: HashMap<Point, Color>,
+    starting_hull: Grid<Color>,
     default_color: Color,
-) -> Result<HashMap<Point, Color>, anyhow::Error> {
+) -> Result<Grid<Color>, anyhow::Error> {
     use Color::*;
-    use Direction::*;
 
     // Basically, we're using Mutex as a way of telling Rust that we know
     // for sure we aren't gonna be accessing these values concurrently.
     // The borrow checker is then satisfied.
     let hull = Mutex::new(starting_hull);
-    let current_location = Mutex::new(Point::origin());
+    let current_location = Mutex::new(Point2::origin());
     let mut is_paint_output = true;
-    let mut facing_direction = Up;
+    let mut facing_direction = Direction::Up;
 
     futures_executor::block_on(run_program(
         robot_program,
@@ -88,7 +110,7 @@ fn paint_hull(
             Some(
                 hull.lock()
                     .unwrap()
-                    .get(&current_location)
+                    .get(current_location)
                     .copied()
                     .unwrap_or(default_color),
             )
@@ -100,27 +122,15 @@ fn paint_hull(
             if is_paint_output {
                 hull.lock()
                     .unwrap()
-                    .entry(*current_location)
-                    .insert(if output == 0 { Black } else { White });
+                    .set(*current_location, if output == 0 { Black } else { White });
             } else {
-                let turn_direction = if output == 0 { Left } else { Right };
-
-                (*current_location, facing_direction) = match (turn_direction, facing_direction) {
-                    (Left, Right) | (Right, Left) => {
-                        (Point::new(current_location.x, current_location.y + 1), Up)
-                    }
-                    (Left, Left) | (Right, Right) => {
-                        (Point::new(current_location.x, current_location.y - 1), Down)
-                    }
-                    (Left, Up) | (Right, Down) => {
-                        (Point::new(current_location.x - 1, current_location.y), Left)
-                    }
-                    (Left, Down) | (Right, Up) => (
-                        Point::new(current_location.x + 1, current_location.y),
-                        Right,
-                    ),
-                    _ => unsafe { std::hint::unreachable_unchecked() },
-                }
+                facing_direction = if output == 0 {
+                    facing_direction.turn_left()
+                } else {
+                    facing_direction.turn_right()
+                };
+
+                *current_location = facing_direction.step(*current_location);
             }
 
             is_paint_output = !is_paint_output;
@@ -130,211 +140,61 @@ fn paint_hull(
     Ok(hull.into_inner().unwrap())
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Color {
     White,
     Black,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-#[derive(Clone, Copy, PartialEq, Eq, Hash, From)]
-struct Point {
-    x: isize,
-    y: isize,
-}
-
-impl fmt::Debug for Point {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_tuple("").field(&self.x).field(&self.y).finish()
-    }
-}
-
-impl Point {
-    fn origin() -> Self {
-        Self::new(0, 0)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The hand-written robot in input_small.txt keeps its move counter in
+    // relative memory and reads the camera with a relative mode write. It
+    // paints every panel it's on the opposite colour, then turns right and
+    // moves, four times over, so it goes around a 2x2 square and ends up
+    // back where it started.
+    fn square() -> [Point2; 4] {
+        let origin = Point2::origin();
+        let right = Direction::Right.step(origin);
+
+        [
+            origin,
+            right,
+            Direction::Down.step(right),
+            Direction::Down.step(origin),
+        ]
     }
 
-    fn new(x: isize, y: isize) -> Self {
-        Self::from((x, y))
+    fn robot() -> Vec<isize> {
+        parse_program(include_str!("input_small.txt").trim()).unwrap()
     }
-}
 
-async fn run_program(
-    mut program: Vec<isize>,
-    input: impl Stream<Item = isize>,
-    mut output_fn: impl FnMut(isize),
-) -> Result<Vec<isize>, anyhow::Error> {
-    pin!(input);
-
-    let mut instruction_pointer = 0;
-    let mut relative_base = 0;
-
-    loop {
-        let opcode = usize::try_from(program[instruction_pointer])
-            .map_err(|_| anyhow!("Found a negative integer where an opcode was expected"))?;
-
-        let parameter_modes = get_parameter_modes(opcode)?;
-
-        let parameter_mode_of = |param: usize| {
-            parameter_modes
-                .get(param)
-                .unwrap_or(&ParameterModes::Position)
-        };
-
-        let mut get_param = |param: usize, need_write: bool| {
-            let param_value = program
-                .get(instruction_pointer + param + 1)
-                .copied()
-                .ok_or(anyhow!("Parameter not found"))?;
-
-            let param_mode = parameter_mode_of(param);
-
-            if need_write {
-                ensure!(
-                    [ParameterModes::Position, ParameterModes::Relative].contains(param_mode),
-                    "Invalid argument for opcode {}: {}",
-                    opcode,
-                    param_value
-                );
-            }
+    #[test]
+    fn robot_on_black_hull() {
+        let hull = paint_hull(robot(), Grid::sparse(), Color::Black).unwrap();
 
-            Ok(match param_mode {
-                ParameterModes::Position | ParameterModes::Relative => {
-                    let raw_idx = if param_mode == &ParameterModes::Relative {
-                        relative_base + param_value
-                    } else {
-                        param_value
-                    };
-
-                    let idx = usize::try_from(raw_idx).map_err(|_| {
-                        anyhow!(
-                            "The program is attempting to access a negative index: {}",
-                            raw_idx
-                        )
-                    })?;
-
-                    if idx >= program.len() {
-                        program.resize_with(idx + 1, || 0);
-                    }
-
-                    if !need_write {
-                        program[idx]
-                    } else {
-                        raw_idx
-                    }
-                }
-                ParameterModes::Immediate => param_value,
-            })
-        };
-
-        // x % 100 gets the last 2 digits of a number,
-        // no matter how long it is.
-        match opcode % 100 {
-            1 | 2 | 7 | 8 => {
-                let (x, y, result_idx) = (
-                    get_param(0, false)?,
-                    get_param(1, false)?,
-                    get_param(2, true)? as usize,
-                );
-
-                match opcode % 100 {
-                    1 => program[result_idx] = x + y,
-                    2 => program[result_idx] = x * y,
-                    7 => program[result_idx] = (x < y) as isize,
-                    8 => program[result_idx] = (x == y) as isize,
-                    _ => unsafe { std::hint::unreachable_unchecked() },
-                }
-
-                instruction_pointer += 4;
-            }
-            5 | 6 => {
-                let (checked_value, jump_point) = (
-                    get_param(0, false)?,
-                    usize::try_from(get_param(1, false)?).map_err(|_| {
-                        anyhow!("Found a negative integer where a jump point was expected")
-                    })?,
-                );
-
-                let should_jump = match opcode % 100 {
-                    5 => checked_value != 0,
-                    6 => checked_value == 0,
-                    _ => unsafe { std::hint::unreachable_unchecked() },
-                };
+        assert_eq!(hull.len(), 4);
 
-                if should_jump {
-                    instruction_pointer = jump_point;
-                } else {
-                    instruction_pointer += 3;
-                }
-            }
-            3 | 4 | 9 => {
-                match opcode % 100 {
-                    3 => {
-                        let input = input
-                            .next()
-                            .await
-                            .ok_or(anyhow!("Found an input opcode but no input was provided"))?;
-                        let input_storage = get_param(0, true)? as usize;
-
-                        program[input_storage] = input;
-                    }
-                    4 => output_fn(get_param(0, false)?),
-                    9 => relative_base += get_param(0, false)?,
-                    _ => unsafe { std::hint::unreachable_unchecked() },
-                }
-
-                instruction_pointer += 2;
-            }
-            99 => return Ok(program),
-            op => bail!("Encountered an unknown opcode: {}", op),
+        for &point in &square() {
+            assert_eq!(hull.get(point), Some(&Color::White), "{:?}", point);
         }
     }
-}
 
-fn get_parameter_modes(opcode: usize) -> Result<Vec<ParameterModes>, anyhow::Error> {
-    opcode
-        .digits()
-        .rev()
-        .skip(2)
-        .map(ParameterModes::try_from)
-        .try_collect()
-}
+    #[test]
+    fn robot_starting_on_white() {
+        let mut starting_hull = Grid::sparse();
+        starting_hull.set(Point2::origin(), Color::White);
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum ParameterModes {
-    Position,
-    Immediate,
-    Relative,
-}
+        let hull = paint_hull(robot(), starting_hull, Color::Black).unwrap();
+        let [origin, rest @ ..] = square();
 
-impl TryFrom<u8> for ParameterModes {
-    type Error = anyhow::Error;
+        assert_eq!(hull.len(), 4);
+        assert_eq!(hull.get(origin), Some(&Color::Black));
 
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        Ok(match value {
-            0 => Self::Position,
-            1 => Self::Immediate,
-            2 => Self::Relative,
-            _ => bail!("Unknown parameter mode: {}", value),
-        })
+        for &point in rest.iter() {
+            assert_eq!(hull.get(point), Some(&Color::White), "{:?}", point);
+        }
     }
 }
-
-fn parse_input(program_str: &str) -> Result<Vec<isize>, anyhow::Error> {
-    program_str
-        .split(",")
-        .map(|num_str| {
-            num_str
-                .trim()
-                .parse()
-                .map_err(|_| anyhow!("Could not parse number in program as isize: '{}'", num_str))
-        })
-        .try_collect()
-}
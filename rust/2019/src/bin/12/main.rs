@@ -1,10 +1,14 @@
 #![feature(default_free_fn)]
 
-use anyhow::{bail, Context};
+use anyhow::{anyhow, bail, Context};
+use aoc_2019_rust::{
+    input::read_input,
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
 use derive_more::{Add, AddAssign, From, SubAssign};
 use itertools::Itertools;
-use std::{cmp::Ordering, default::default, fmt, fs};
+use std::{cmp::Ordering, default::default, fmt};
 
 fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2019-12")
@@ -13,86 +17,94 @@ fn main() -> Result<(), anyhow::Error> {
             Arg::from_usage("[required_steps] -n --num-steps 'Number of steps to simulate for'")
                 .default_value("1000"),
         )
+        .arg(
+            Arg::from_usage(
+                "[energy_only] -e --energy-only 'Stop once the energy has been found instead of looking for when the universe loops around'",
+            )
+            .visible_alias("no-loop"),
+        )
+        .arg(
+            Arg::from_usage("[part] --part 'Which part of the problem to solve'")
+                .takes_value(true)
+                .possible_values(&["1", "2", "both"])
+                .default_value("both"),
+        )
+        .arg(Arg::from_usage("[time] --time 'Print how long parsing and solving take'"))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
 
-    let positions_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
+    let parse_timer = Timer::start("Parsing", time);
+    let positions_str = read_input(input_filename)?;
     let positions = parse_input(&positions_str)?;
-
-    let input_planets = positions
-        .into_iter()
-        .map(|pos| (pos, default()))
-        .collect_vec();
+    drop(parse_timer);
 
     let required_steps = matches
         .value_of("required_steps")
         .and_then(|n_str| n_str.parse::<usize>().ok())
         .context("Number of steps provided couldn't be parsed as a positive number")?;
 
-    let mut planets = input_planets.clone();
-    let mut num_steps = 0_usize;
-    let (mut x_loop, mut y_loop, mut z_loop) = (None, None, None);
-
-    loop {
-        num_steps += 1;
-        planets = simulate_step(planets);
-
-        if num_steps == required_steps {
-            let total_energy = planets
-                .iter()
-                .map(|(pos, vel)| {
-                    ((pos.x.abs() + pos.y.abs() + pos.z.abs())
-                        * (vel.x.abs() + vel.y.abs() + vel.z.abs())) as usize
-                })
-                .sum::<usize>();
-
-            println!(
-                "Total energy after {} steps: {}",
-                required_steps, total_energy
-            );
-        }
+    // --energy-only predates --part, and means the same thing as --part 1.
+    let part = if matches.is_present("energy_only") {
+        "1"
+    } else {
+        matches.value_of("part").unwrap()
+    };
 
-        let mut zipped_iter = input_planets.iter().zip(planets.iter());
+    let (energy, loop_steps) = timing::repeat("Solving", repeat, time, || {
+        (
+            (part != "2").then(|| energy_after_steps(&positions, required_steps)),
+            (part != "1").then(|| steps_until_loop(&positions)),
+        )
+    });
 
-        // The three coordinates don't affect each other, so we find the points
-        // at which each of them individually loops around and then find their LCM.
+    if let Some(energy) = energy {
+        println!("Total energy after {} steps: {}", required_steps, energy);
+    }
 
-        if x_loop.is_none()
-            && zipped_iter
-                .clone()
-                .all(|((ipos, ivel), (pos, vel))| ipos.x == pos.x && ivel.x == vel.x)
-        {
-            x_loop = Some(num_steps);
-        }
+    if let Some(loop_steps) = loop_steps {
+        println!(
+            "Number of steps until the universe loops around: {}",
+            loop_steps?
+        );
+    }
 
-        if y_loop.is_none()
-            && zipped_iter
-                .clone()
-                .all(|((ipos, ivel), (pos, vel))| ipos.y == pos.y && ivel.y == vel.y)
-        {
-            y_loop = Some(num_steps);
-        }
+    Ok(())
+}
 
-        if z_loop.is_none()
-            && zipped_iter.all(|((ipos, ivel), (pos, vel))| ipos.z == pos.z && ivel.z == vel.z)
-        {
-            z_loop = Some(num_steps);
-        }
+pub fn energy_after_steps(positions: &[Coords3D], num_steps: usize) -> usize {
+    let mut planets = positions.iter().map(|&pos| (pos, default())).collect_vec();
 
-        if x_loop.is_some() && y_loop.is_some() && z_loop.is_some() {
-            break;
-        }
+    for _ in 0..num_steps {
+        planets = simulate_step(planets);
     }
 
-    let (x_loop, y_loop, z_loop) = (x_loop.unwrap(), y_loop.unwrap(), z_loop.unwrap());
-
-    let lcm =
-        x_loop * y_loop * z_loop / gcd(y_loop * z_loop, gcd(z_loop * x_loop, x_loop * y_loop));
+    total_energy(&planets)
+}
 
-    println!("Number of steps until the universe loops around: {}", lcm);
+fn steps_until_loop(positions: &[Coords3D]) -> Result<usize, anyhow::Error> {
+    // The three coordinates don't affect each other, so we find the points
+    // at which each of them individually loops around and then find their LCM.
+    let axis_cycles = [
+        axis_cycle(&positions.iter().map(|pos| pos.x).collect_vec()),
+        axis_cycle(&positions.iter().map(|pos| pos.y).collect_vec()),
+        axis_cycle(&positions.iter().map(|pos| pos.z).collect_vec()),
+    ];
 
-    Ok(())
+    lcm_all(&axis_cycles)
+        .context("The number of steps until the universe loops around is too big to count")
 }
 
 // See https://en.wikipedia.org/wiki/Greatest_common_divisor#Euclid%27s_algorithm
@@ -106,8 +118,64 @@ fn gcd(a: usize, b: usize) -> usize {
     }
 }
 
+// Returns None if the result would overflow. Dividing by the GCD before
+// multiplying means this only happens if the LCM itself is too big.
+fn lcm(a: usize, b: usize) -> Option<usize> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+fn lcm_all(nums: &[usize]) -> Option<usize> {
+    nums.iter().try_fold(1, |acc, &n| lcm(acc, n))
+}
+
+// Simulates the planets along just one axis, starting from a standstill, and
+// returns how many steps it takes for them all to get back to where they started.
+fn axis_cycle(initial_positions: &[isize]) -> usize {
+    let mut positions = initial_positions.to_vec();
+    let mut velocities = vec![0; positions.len()];
+    let mut num_steps = 0;
+
+    loop {
+        for (a_idx, b_idx) in (0..positions.len()).tuple_combinations() {
+            // Same as in simulate_step, lower coordinates get pulled upwards.
+            let vel_delta = match positions[a_idx].cmp(&positions[b_idx]) {
+                Ordering::Less => 1,
+                Ordering::Greater => -1,
+                Ordering::Equal => 0,
+            };
+
+            velocities[a_idx] += vel_delta;
+            velocities[b_idx] -= vel_delta;
+        }
+
+        for (pos, vel) in positions.iter_mut().zip(&velocities) {
+            *pos += vel;
+        }
+
+        num_steps += 1;
+
+        if positions == initial_positions && velocities.iter().all(|&vel| vel == 0) {
+            return num_steps;
+        }
+    }
+}
+
 type Planet = (Coords3D, Coords3D);
 
+fn total_energy(planets: &[Planet]) -> usize {
+    planets
+        .iter()
+        .map(|(pos, vel)| {
+            ((pos.x.abs() + pos.y.abs() + pos.z.abs()) * (vel.x.abs() + vel.y.abs() + vel.z.abs()))
+                as usize
+        })
+        .sum()
+}
+
 fn simulate_step(mut planets: Vec<Planet>) -> Vec<Planet> {
     let mut velocity_deltas = vec![default(); planets.len()];
 
@@ -142,7 +210,7 @@ fn simulate_step(mut planets: Vec<Planet>) -> Vec<Planet> {
     planets
 }
 
-fn parse_input(positions_str: &str) -> Result<Vec<Coords3D>, anyhow::Error> {
+pub fn parse_input(positions_str: &str) -> Result<Vec<Coords3D>, anyhow::Error> {
     positions_str
         .lines()
         .map(|coords_str| {
@@ -162,7 +230,7 @@ fn parse_input(positions_str: &str) -> Result<Vec<Coords3D>, anyhow::Error> {
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Default, From, Add, AddAssign, SubAssign)]
-struct Coords3D {
+pub struct Coords3D {
     x: isize,
     y: isize,
     z: isize,
@@ -177,3 +245,42 @@ impl fmt::Debug for Coords3D {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lcm_of_coprime_cycles() {
+        // All three are prime, so the LCM is their product, which only just
+        // fits in a 64-bit usize.
+        assert_eq!(
+            lcm_all(&[1_000_003, 2_000_003, 3_000_017]),
+            Some(6_000_061_000_180_000_153)
+        );
+        assert_eq!(lcm_all(&[9_999_991, 9_999_973, 9_999_971]), None);
+    }
+
+    #[test]
+    fn lcm_with_shared_factors() {
+        // Multiplying these together would overflow, but their LCM doesn't.
+        let p = 4_000_000_007;
+
+        assert_eq!(lcm_all(&[2 * p, 3 * p, 5 * p]), Some(30 * p));
+        assert_eq!(lcm(12, 18), Some(36));
+        assert_eq!(lcm(0, 5), Some(0));
+        assert_eq!(lcm_all(&[]), Some(1));
+    }
+
+    #[test]
+    fn sample_steps_until_loop() {
+        for &(positions_str, steps) in &[
+            (include_str!("input_small_1.txt"), 2772),
+            (include_str!("input_small_2.txt"), 4_686_774_924),
+        ] {
+            let positions = parse_input(positions_str).unwrap();
+
+            assert_eq!(steps_until_loop(&positions).unwrap(), steps);
+        }
+    }
+}
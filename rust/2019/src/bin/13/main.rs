@@ -1,23 +1,30 @@
 #![feature(default_free_fn, duration_zero)]
 
-use anyhow::{anyhow, bail, ensure, Context};
+use anyhow::{anyhow, bail};
+use aoc_2019_rust::{
+    geometry::Point2,
+    grid::{self, StyledChar},
+    input::read_input_trimmed,
+    intcode::{parse_program, run_program, Vm},
+    timing::{self, Timer},
+};
 use atomic::Atomic;
 use clap::{App, Arg};
 use colored::*;
 use crossterm::{
-    cursor, execute, style,
-    terminal::{Clear, ClearType},
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, style,
+    terminal::{self, Clear, ClearType},
 };
-use derive_more::From;
-use digits_iterator::*;
 use itertools::Itertools;
 use parking_lot::Mutex;
+use serde::{Serialize, Serializer};
 use std::{
     cmp::Ordering,
     collections::HashMap,
     convert::TryFrom,
     default::default,
-    fmt, fs,
     io::{stdin, stdout, Write},
     iter, panic, process,
     sync::{
@@ -27,8 +34,6 @@ use std::{
     thread,
     time::Duration,
 };
-use tokio::pin;
-use tokio_stream::{Stream, StreamExt};
 
 fn main() -> Result<(), anyhow::Error> {
     // Because we're doing fancy terminal stuff here, we should
@@ -57,21 +62,72 @@ fn main() -> Result<(), anyhow::Error> {
 
     let matches = App::new("2019-13")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
-        .arg(Arg::from_usage("[draw_intermediate] -d --draw-intermediate 'Draw the screen while the game is running'").takes_value(false))
+        .arg(Arg::from_usage("[draw_intermediate] -d --draw-intermediate 'Draw the screen while the game is running. Space pauses, +/- change the speed'").takes_value(false))
         .arg(Arg::from_usage("[draw_fast] -f --draw-fast 'Speed the game up while drawing it'").takes_value(false))
+        .arg(Arg::from_usage("[interactive] -i --interactive 'Play the game yourself with the arrow keys, using down to stay still. Implies -d'").takes_value(false))
+        .arg(Arg::from_usage("[auto] -a --auto 'Put the quarters in without asking, and play the game without drawing it'").takes_value(false).conflicts_with_all(&["draw_intermediate", "interactive"]))
+        .arg(Arg::from_usage("[plain] -p --plain 'Draw the screen with plain ASCII and no colors'").takes_value(false))
+        .arg(Arg::from_usage("[format] --format 'Print the final board and score of a game with quarters as JSON instead'").takes_value(true).possible_values(&["text", "json"]).conflicts_with_all(&["draw_intermediate", "interactive"]))
+        .arg(Arg::from_usage("[time] --time 'Print how long parsing and solving take'"))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
+
+    let parse_timer = Timer::start("Parsing", time);
+    let program_str = read_input_trimmed(input_filename)?;
+    let mut game = Vm::new(parse_program(&program_str)?);
+    drop(parse_timer);
+
+    let theme = if matches.is_present("plain") {
+        // This takes care of everything else we color, like the score.
+        colored::control::set_override(false);
+
+        Theme::plain()
+    } else {
+        Theme::default()
+    };
 
-    let program_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
-    let mut game_program = parse_input(&program_str)?;
+    if matches.value_of("format") == Some("json") {
+        game.write(0, 2);
 
-    let (screen, _) = run_game(game_program.clone(), |_, _| JoystickInput::Neutral, None)?;
+        let (screen, score) = timing::repeat("Solving", repeat, time, || {
+            run_game(game.clone(), FollowBall, None, &theme)
+        })?;
+
+        println!(
+            "{}",
+            serde_json::to_string(&GameReport::new(&screen, score))?
+        );
+
+        return Ok(());
+    }
 
-    println!(
-        "Number of block tiles with no quarters: {}",
-        screen.values().filter(|&tile| tile == &Tile::Block).count(),
-    );
+    let num_blocks = timing::repeat("Solving", repeat, time, || blocks_without_quarters(&game))?;
+
+    println!("Number of block tiles with no quarters: {}", num_blocks);
+
+    if matches.is_present("auto") {
+        // Nothing's drawn or waited on here, so this can be repeated.
+        let (score, blocks_remaining) =
+            timing::repeat("Playing", repeat, time, || auto_play(&game))?;
+
+        println!("Final score: {}", score);
+        println!("Blocks remaining: {}", blocks_remaining);
+
+        return Ok(());
+    }
 
     let mut input = String::new();
     print!("Insert 2 quarters? (Y/n) ");
@@ -85,22 +141,29 @@ fn main() -> Result<(), anyhow::Error> {
     }
 
     // HACKERMAN
-    game_program[0] = 2;
+    game.write(0, 2);
 
     game_running.store(true, Release);
 
-    let (_, score) = run_game(
-        game_program,
-        |paddle_pos, ball_pos| {
-            use JoystickInput::*;
+    let interactive = matches.is_present("interactive");
 
-            match ball_pos.x.cmp(&paddle_pos.x) {
-                Ordering::Less => Left,
-                Ordering::Greater => Right,
-                Ordering::Equal => Neutral,
-            }
-        },
-        if matches.is_present("draw_intermediate") {
+    let strategy: Box<dyn PaddleStrategy> = if interactive {
+        Box::new(Keyboard)
+    } else {
+        Box::new(FollowBall)
+    };
+
+    // This can be interactive, or drawn with delays, so there's no point
+    // repeating it, and it's timed separately from everything else.
+    let _play_timer = Timer::start("Playing", time);
+
+    let (_, score) = run_game(
+        game,
+        strategy,
+        if interactive {
+            // The game already waits on the player for every move.
+            Some(Duration::ZERO)
+        } else if matches.is_present("draw_intermediate") {
             Some(if matches.is_present("draw_fast") {
                 Duration::ZERO
             } else {
@@ -109,6 +172,7 @@ fn main() -> Result<(), anyhow::Error> {
         } else {
             None
         },
+        &theme,
     )?;
 
     println!("Final score: {}", score);
@@ -116,18 +180,145 @@ fn main() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum JoystickInput {
     Neutral,
     Left,
     Right,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum PlaybackAction {
+    TogglePause,
+    SpeedUp,
+    SlowDown,
+    Quit,
+}
+
+const FRAME_DELAY_STEP: Duration = Duration::from_millis(10);
+
+fn playback_action(key: KeyEvent) -> Option<PlaybackAction> {
+    use PlaybackAction::*;
+
+    match key.code {
+        KeyCode::Char(' ') => Some(TogglePause),
+        // = is on the same key as + on most keyboards, so accept it too
+        // so that nobody has to hold shift.
+        KeyCode::Char('+') | KeyCode::Char('=') => Some(SpeedUp),
+        KeyCode::Char('-') => Some(SlowDown),
+        // Raw mode swallows Ctrl-C, so the ctrlc handler never sees it.
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Quit),
+        KeyCode::Char('q') | KeyCode::Esc => Some(Quit),
+        _ => None,
+    }
+}
+
+// Whatever moves the joystick, which gets asked every time the game wants
+// input, given where the paddle and the ball are right now.
+trait PaddleStrategy {
+    fn decide(&mut self, paddle_pos: Point2, ball_pos: Point2) -> JoystickInput;
+
+    // Whether decide reads key events itself, in which case nothing else
+    // should read them first, or the keypresses meant for it get lost.
+    fn reads_keyboard(&self) -> bool {
+        false
+    }
+}
+
+impl<S: PaddleStrategy + ?Sized> PaddleStrategy for Box<S> {
+    fn decide(&mut self, paddle_pos: Point2, ball_pos: Point2) -> JoystickInput {
+        (**self).decide(paddle_pos, ball_pos)
+    }
+
+    fn reads_keyboard(&self) -> bool {
+        (**self).reads_keyboard()
+    }
+}
+
+// Never misses, since the paddle moves just as fast as the ball does.
+struct FollowBall;
+
+impl PaddleStrategy for FollowBall {
+    fn decide(&mut self, paddle_pos: Point2, ball_pos: Point2) -> JoystickInput {
+        use JoystickInput::*;
+
+        match ball_pos.x.cmp(&paddle_pos.x) {
+            Ordering::Less => Left,
+            Ordering::Greater => Right,
+            Ordering::Equal => Neutral,
+        }
+    }
+}
+
+struct StayStill;
+
+impl PaddleStrategy for StayStill {
+    fn decide(&mut self, _: Point2, _: Point2) -> JoystickInput {
+        JoystickInput::Neutral
+    }
+}
+
+// Lets the player decide. Blocks until they press a key that means
+// something, so the game only moves forward when they do.
+struct Keyboard;
+
+impl PaddleStrategy for Keyboard {
+    fn decide(&mut self, _: Point2, _: Point2) -> JoystickInput {
+        use JoystickInput::*;
+
+        loop {
+            let key = match event::read().unwrap() {
+                Event::Key(key) => key,
+                _ => continue,
+            };
+
+            match key.code {
+                KeyCode::Left => return Left,
+                KeyCode::Right => return Right,
+                KeyCode::Down => return Neutral,
+                _ if playback_action(key) == Some(PlaybackAction::Quit) => {
+                    let _ = game_exit_handler();
+                    process::exit(1);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn reads_keyboard(&self) -> bool {
+        true
+    }
+}
+
+// Lets the game draw its board without any quarters in, and counts the blocks
+// on it. Nothing's drawn, so the theme doesn't matter.
+pub fn blocks_without_quarters(game: &Vm) -> Result<usize, anyhow::Error> {
+    let (screen, _) = run_game(game.clone(), StayStill, None, &Theme::plain())?;
+
+    Ok(count_blocks(&screen))
+}
+
+// Puts the quarters in and plays until the game ends without drawing
+// anything, giving back the final score and how many blocks are left.
+pub fn auto_play(game: &Vm) -> Result<(isize, usize), anyhow::Error> {
+    let mut game = game.clone();
+    game.write(0, 2);
+
+    let (screen, score) = run_game(game, FollowBall, None, &Theme::plain())?;
+
+    Ok((score, count_blocks(&screen)))
+}
+
+fn count_blocks(screen: &HashMap<Point2, Tile>) -> usize {
+    screen.values().filter(|&tile| tile == &Tile::Block).count()
+}
+
 fn run_game(
-    game_program: Vec<isize>,
-    mut input_fn: impl FnMut(Point, Point) -> JoystickInput,
+    game: Vm,
+    mut strategy: impl PaddleStrategy,
     should_draw: Option<Duration>,
-) -> Result<(HashMap<Point, Tile>, isize), anyhow::Error> {
+    theme: &Theme,
+) -> Result<(HashMap<Point2, Tile>, isize), anyhow::Error> {
     let screen = Mutex::new(HashMap::new());
     let current_score = Arc::new(AtomicIsize::new(0));
     let current_ball_pos = Arc::new(Atomic::new(default()));
@@ -143,28 +334,36 @@ fn run_game(
 
     // These are only accessed from the output closure, and
     // therefore don't need any synchronization.
-    let mut current_tile_pos = Point::default();
+    let mut current_tile_pos = Point2::default();
     let mut current_screen_instruction = 0_u8;
 
     let mut stdout = stdout();
+    let mut frame_delay = should_draw;
+    // The game already waits on a strategy like that for every move, so
+    // there's nothing for the playback controls to do anyway.
+    let playback_controls = !strategy.reads_keyboard();
 
     if should_draw.is_some() {
+        // We need raw mode to get at individual keypresses for the
+        // playback controls as soon as they happen.
+        terminal::enable_raw_mode().unwrap();
         execute!(stdout, cursor::Hide).unwrap();
     }
 
     futures_executor::block_on(run_program(
-        game_program,
+        game,
         tokio_stream::iter(iter::from_fn(|| {
-            if let Some(pause_duration) = should_draw {
-                let screen_str = screen_to_string(&screen.lock());
+            if let Some(pause_duration) = frame_delay.as_mut() {
+                let screen_str = screen_to_string(&screen.lock(), theme);
                 let current_score = current_score_input.as_ref().unwrap().load(Acquire);
 
                 execute!(
                     stdout,
                     cursor::SavePosition,
-                    style::Print(screen_str),
+                    // Raw mode doesn't turn newlines into carriage returns.
+                    style::Print(screen_str.replace('\n', "\r\n")),
                     style::Print(format!(
-                        "Score: {}\n",
+                        "Score: {}\r\n",
                         current_score.to_string().underline()
                     )),
                     cursor::RestorePosition,
@@ -177,12 +376,39 @@ fn run_game(
                 // this will allow the OS to update the terminal before we
                 // start printing it again. This is different from flushing
                 // for reasons that I really can't understand.
-                thread::sleep(pause_duration);
+                thread::sleep(*pause_duration);
+
+                let mut paused = false;
+
+                // If we're paused, we block until something unpauses us,
+                // otherwise we just handle whatever was pressed since the
+                // last frame and get on with the game.
+                while playback_controls && (paused || event::poll(Duration::ZERO).unwrap()) {
+                    let key = match event::read().unwrap() {
+                        Event::Key(key) => key,
+                        _ => continue,
+                    };
+
+                    use PlaybackAction::*;
+
+                    match playback_action(key) {
+                        Some(TogglePause) => paused = !paused,
+                        Some(SpeedUp) => {
+                            *pause_duration = pause_duration.saturating_sub(FRAME_DELAY_STEP)
+                        }
+                        Some(SlowDown) => *pause_duration += FRAME_DELAY_STEP,
+                        Some(Quit) => {
+                            let _ = game_exit_handler();
+                            process::exit(1);
+                        }
+                        None => {}
+                    }
+                }
             }
 
             use JoystickInput::*;
 
-            let joystick_input = input_fn(
+            let joystick_input = strategy.decide(
                 current_paddle_pos_input.load(Acquire),
                 current_ball_pos_input.load(Acquire),
             );
@@ -203,7 +429,7 @@ fn run_game(
 
                 current_screen_instruction = 2;
             } else if current_screen_instruction == 2 {
-                if current_tile_pos == Point::new(-1, 0) {
+                if current_tile_pos == Point2::new(-1, 0) {
                     current_score.store(output, Release);
                 } else {
                     let tile = Tile::try_from(output as u8).unwrap();
@@ -226,7 +452,7 @@ fn run_game(
     let score = current_score.load(Acquire);
 
     if should_draw.is_some() {
-        let screen_str = screen_to_string(&screen);
+        let screen_str = screen_to_string(&screen, theme);
         execute!(
             stdout,
             style::Print(screen_str),
@@ -235,79 +461,99 @@ fn run_game(
         .unwrap();
 
         execute!(stdout, cursor::Show).unwrap();
+        terminal::disable_raw_mode().unwrap();
     }
 
     Ok((screen, score))
 }
 
 fn game_exit_handler() -> Result<(), anyhow::Error> {
+    terminal::disable_raw_mode()?;
     execute!(stdout(), Clear(ClearType::FromCursorDown), cursor::Show)?;
 
     Ok(())
 }
 
-fn screen_to_string(screen: &HashMap<Point, Tile>) -> String {
-    let ((min_x, max_x), (min_y, max_y)) = (
-        screen
-            .keys()
-            .map(|p| p.x)
-            .minmax()
-            .into_option()
-            .unwrap_or_default(),
-        screen
-            .keys()
-            .map(|p| p.y)
-            .minmax()
-            .into_option()
-            .unwrap_or_default(),
-    );
-
-    let mut screen_str = String::new();
-
-    use Tile::*;
+struct Theme {
+    empty: StyledChar,
+    wall: StyledChar,
+    block: StyledChar,
+    paddle: StyledChar,
+    ball: StyledChar,
+}
 
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            screen_str.push_str(&*match screen.get(&Point::new(x, y)).unwrap_or(&Empty) {
-                Empty => " ".to_string(),
-                Wall => "█".black().bold().to_string(),
-                Block => "░".red().to_string(),
-                Paddle => "_".bright_yellow().to_string(),
-                Ball => "o".bright_green().bold().to_string(),
-            });
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            empty: " ".normal(),
+            wall: "█".black().bold(),
+            block: "░".red(),
+            paddle: "_".bright_yellow(),
+            ball: "o".bright_green().bold(),
         }
-
-        screen_str.push('\n');
     }
+}
 
-    screen_str
+impl Theme {
+    // For terminals that can't handle ANSI colors or unicode very well.
+    fn plain() -> Self {
+        Self {
+            empty: " ".normal(),
+            wall: "#".normal(),
+            block: "=".normal(),
+            paddle: "_".normal(),
+            ball: "o".normal(),
+        }
+    }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, From, Default)]
-struct Point {
-    x: isize,
-    y: isize,
+fn screen_to_string(screen: &HashMap<Point2, Tile>, theme: &Theme) -> String {
+    use Tile::*;
+
+    grid::render(screen, |tile| match tile.unwrap_or(&Empty) {
+        Empty => theme.empty.clone(),
+        Wall => theme.wall.clone(),
+        Block => theme.block.clone(),
+        Paddle => theme.paddle.clone(),
+        Ball => theme.ball.clone(),
+    })
 }
 
-impl fmt::Debug for Point {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_tuple("").field(&self.x).field(&self.y).finish()
-    }
+#[derive(Serialize)]
+struct GameReport {
+    blocks_remaining: usize,
+    score: isize,
+    tiles: Vec<(isize, isize, Tile)>,
 }
 
-impl Point {
-    fn new(x: isize, y: isize) -> Self {
-        Self::from((x, y))
+impl GameReport {
+    fn new(screen: &HashMap<Point2, Tile>, score: isize) -> Self {
+        Self {
+            blocks_remaining: count_blocks(screen),
+            score,
+            tiles: screen
+                .iter()
+                .map(|(point, &tile)| (point.x, point.y, tile))
+                .sorted_by_key(|&(x, y, _)| (y, x))
+                .collect(),
+        }
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Tile {
-    Empty,
-    Wall,
-    Block,
-    Paddle,
-    Ball,
+    Empty = 0,
+    Wall = 1,
+    Block = 2,
+    Paddle = 3,
+    Ball = 4,
+}
+
+// Tiles are serialized as the same numbers the game uses for them.
+impl Serialize for Tile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
 }
 
 impl TryFrom<u8> for Tile {
@@ -325,173 +571,70 @@ impl TryFrom<u8> for Tile {
     }
 }
 
-async fn run_program(
-    mut program: Vec<isize>,
-    input: impl Stream<Item = isize>,
-    mut output_fn: impl FnMut(isize),
-) -> Result<Vec<isize>, anyhow::Error> {
-    pin!(input);
-
-    let mut instruction_pointer = 0;
-    let mut relative_base = 0;
-
-    loop {
-        let opcode = usize::try_from(program[instruction_pointer])
-            .context("Found a negative integer where an opcode was expected")?;
-
-        let parameter_modes = get_parameter_modes(opcode)?;
-
-        let parameter_mode_of = |param: usize| {
-            parameter_modes
-                .get(param)
-                .unwrap_or(&ParameterModes::Position)
-        };
-
-        let mut get_param = |param: usize, need_write: bool| {
-            let param_value = program
-                .get(instruction_pointer + param + 1)
-                .copied()
-                .ok_or(anyhow!("Parameter not found"))?;
-
-            let param_mode = parameter_mode_of(param);
-
-            if need_write {
-                ensure!(
-                    [ParameterModes::Position, ParameterModes::Relative].contains(param_mode),
-                    "Invalid argument for opcode {}: {}",
-                    opcode,
-                    param_value
-                );
-            }
-
-            Ok(match param_mode {
-                ParameterModes::Position | ParameterModes::Relative => {
-                    let raw_idx = if param_mode == &ParameterModes::Relative {
-                        relative_base + param_value
-                    } else {
-                        param_value
-                    };
-
-                    let idx = usize::try_from(raw_idx).with_context(|| {
-                        format!(
-                            "The program is attempting to access a negative index: {}",
-                            raw_idx
-                        )
-                    })?;
-
-                    if idx >= program.len() {
-                        program.resize_with(idx + 1, || 0);
-                    }
-
-                    if !need_write {
-                        program[idx]
-                    } else {
-                        raw_idx
-                    }
-                }
-                ParameterModes::Immediate => param_value,
-            })
-        };
-
-        // x % 100 gets the last 2 digits of a number,
-        // no matter how long it is.
-        match opcode % 100 {
-            1 | 2 | 7 | 8 => {
-                let (x, y, result_idx) = (
-                    get_param(0, false)?,
-                    get_param(1, false)?,
-                    get_param(2, true)? as usize,
-                );
-
-                match opcode % 100 {
-                    1 => program[result_idx] = x + y,
-                    2 => program[result_idx] = x * y,
-                    7 => program[result_idx] = (x < y) as isize,
-                    8 => program[result_idx] = (x == y) as isize,
-                    _ => unsafe { std::hint::unreachable_unchecked() },
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                instruction_pointer += 4;
-            }
-            5 | 6 => {
-                let (checked_value, jump_point) = (
-                    get_param(0, false)?,
-                    usize::try_from(get_param(1, false)?)
-                        .context("Found a negative integer where a jump point was expected")?,
-                );
-
-                let should_jump = match opcode % 100 {
-                    5 => checked_value != 0,
-                    6 => checked_value == 0,
-                    _ => unsafe { std::hint::unreachable_unchecked() },
-                };
-
-                if should_jump {
-                    instruction_pointer = jump_point;
-                } else {
-                    instruction_pointer += 3;
-                }
-            }
-            3 | 4 | 9 => {
-                match opcode % 100 {
-                    3 => {
-                        let input = input
-                            .next()
-                            .await
-                            .ok_or(anyhow!("Found an input opcode but no input was provided"))?;
-                        let input_storage = get_param(0, true)? as usize;
-
-                        program[input_storage] = input;
-                    }
-                    4 => output_fn(get_param(0, false)?),
-                    9 => relative_base += get_param(0, false)?,
-                    _ => unsafe { std::hint::unreachable_unchecked() },
-                }
-
-                instruction_pointer += 2;
-            }
-            99 => return Ok(program),
-            op => bail!("Encountered an unknown opcode: {}", op),
-        }
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
     }
-}
 
-fn get_parameter_modes(opcode: usize) -> Result<Vec<ParameterModes>, anyhow::Error> {
-    opcode
-        .digits()
-        .rev()
-        .skip(2)
-        .map(ParameterModes::try_from)
-        .try_collect()
-}
+    #[test]
+    fn playback_bindings() {
+        use PlaybackAction::*;
+
+        assert_eq!(playback_action(key(KeyCode::Char(' '))), Some(TogglePause));
+        assert_eq!(playback_action(key(KeyCode::Char('+'))), Some(SpeedUp));
+        assert_eq!(playback_action(key(KeyCode::Char('='))), Some(SpeedUp));
+        assert_eq!(playback_action(key(KeyCode::Char('-'))), Some(SlowDown));
+        assert_eq!(playback_action(key(KeyCode::Char('q'))), Some(Quit));
+        assert_eq!(playback_action(key(KeyCode::Esc)), Some(Quit));
+        assert_eq!(
+            playback_action(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(Quit)
+        );
+    }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum ParameterModes {
-    Position,
-    Immediate,
-    Relative,
-}
+    #[test]
+    fn unmapped_keys() {
+        assert_eq!(playback_action(key(KeyCode::Char('x'))), None);
+        // Only Ctrl-C quits, not a plain c.
+        assert_eq!(playback_action(key(KeyCode::Char('c'))), None);
+        // The arrow keys are for the joystick.
+        assert_eq!(playback_action(key(KeyCode::Left)), None);
+    }
 
-impl TryFrom<u8> for ParameterModes {
-    type Error = anyhow::Error;
+    #[test]
+    fn only_keyboard_reads_keys() {
+        // Otherwise the playback controls would eat the arrow keys.
+        let keyboard: Box<dyn PaddleStrategy> = Box::new(Keyboard);
+        assert!(keyboard.reads_keyboard());
 
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        Ok(match value {
-            0 => Self::Position,
-            1 => Self::Immediate,
-            2 => Self::Relative,
-            _ => bail!("Unknown parameter mode: {}", value),
-        })
+        assert!(!FollowBall.reads_keyboard());
+        assert!(!StayStill.reads_keyboard());
     }
-}
 
-fn parse_input(program_str: &str) -> Result<Vec<isize>, anyhow::Error> {
-    program_str
-        .split(",")
-        .map(|num_str| {
-            num_str.trim().parse().with_context(|| {
-                format!("Could not parse number in program as isize: '{}'", num_str)
-            })
-        })
-        .try_collect()
+    #[test]
+    fn follow_ball() {
+        use JoystickInput::*;
+
+        let paddle_pos = Point2::new(5, 20);
+
+        // How high up the ball is doesn't matter, only which side it's on.
+        for &(ball_pos, input) in &[
+            (Point2::new(2, 10), Left),
+            (Point2::new(4, 19), Left),
+            (Point2::new(8, 10), Right),
+            (Point2::new(6, 19), Right),
+            (Point2::new(5, 10), Neutral),
+            (Point2::new(5, 19), Neutral),
+        ] {
+            assert_eq!(
+                FollowBall.decide(paddle_pos, ball_pos),
+                input,
+                "{:?}",
+                ball_pos
+            );
+        }
+    }
 }
@@ -1,198 +1,343 @@
 use anyhow::anyhow;
+use aoc_2019_rust::{
+    input::read_input,
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
 use itertools::Itertools;
-use maplit::{hashmap, hashset};
+use maplit::hashmap;
 use std::{
     collections::{HashMap, HashSet},
-    fmt, fs,
+    fmt::{self, Write},
 };
 
 fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2019-14")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
-        .arg(Arg::from_usage("[raw_resource] -r --raw-resource 'Name of the initial raw resource to find the amount of'").takes_value(true).default_value("ORE"))
+        .arg(Arg::from_usage("[raw_resource] -r --raw-resource 'Name of an initial raw resource to find the amount of, which can be given more than once'").takes_value(true).multiple(true).number_of_values(1).default_value("ORE"))
         .arg(Arg::from_usage("[goal] -g --goal 'Name of the goal chemical to reach'").takes_value(true).default_value("FUEL"))
+        .arg(Arg::from_usage("[available_ore] -a --available-ore 'Amount of each raw resource available for Part 2'").takes_value(true).default_value("1000000000000"))
+        .arg(Arg::from_usage("[dot] --dot 'Print the reactions as a Graphviz DOT graph instead of solving'"))
+        .arg(Arg::from_usage("[time] --time 'Print how long parsing and solving take'"))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
 
-    let reactions_str = fs::read_to_string(&input_filename)?.replace("\r\n", "\n");
+    let parse_timer = Timer::start("Parsing", time);
+    let reactions_str = read_input(input_filename)?;
 
     let possible_reactions = parse_input(&reactions_str)?;
-    let raw_resource = matches
-        .value_of("raw_resource")
+    drop(parse_timer);
+
+    let raw_resources = matches
+        .values_of("raw_resource")
+        .unwrap()
         .map(|s| s.to_owned())
-        .unwrap();
+        .unique()
+        .collect_vec();
     let goal = matches.value_of("goal").map(|s| s.to_owned()).unwrap();
+    let available_ore = matches
+        .value_of("available_ore")
+        .unwrap()
+        .parse::<usize>()?;
+
+    let bases: HashSet<_> = raw_resources.iter().cloned().collect();
+
+    if matches.is_present("dot") {
+        let _solve_timer = Timer::start("Solving", time);
+
+        print!("{}", reactions_to_dot(&possible_reactions, &bases, &goal));
+
+        return Ok(());
+    }
+
+    let (requirements_and_leftovers, fuel) = timing::repeat("Solving", repeat, time, || {
+        (
+            find_requirements_and_leftovers(&possible_reactions, &bases, goal.clone(), 1),
+            max_fuel(
+                &possible_reactions,
+                &bases,
+                &goal,
+                &raw_resources,
+                available_ore,
+            ),
+        )
+    });
+
+    let (requirements, leftovers) = requirements_and_leftovers
+        .ok_or_else(|| anyhow!("Couldn't find a way to obtain the target chemical."))?;
+
+    println!(
+        "You need {} to produce 1 {}.",
+        raw_resources
+            .iter()
+            .map(|raw_resource| format!(
+                "{} {}",
+                requirements.get(raw_resource).copied().unwrap_or(0),
+                raw_resource
+            ))
+            .join(", "),
+        goal
+    );
+
+    if !leftovers.is_empty() {
+        println!(
+            "Leftover chemicals: {}",
+            leftovers
+                .iter()
+                .sorted()
+                .map(|(chemical, amount)| format!("{} {}", amount, chemical))
+                .join(", ")
+        );
+    }
 
-    let requirements = find_requirements_alt(
-        &possible_reactions,
-        &hashset! {raw_resource.clone()},
-        goal.clone(),
-        1,
-    )
-    .ok_or_else(|| anyhow!("Couldn't find a way to obtain the target chemical."))?;
+    let fuel = fuel
+        .ok_or_else(|| anyhow!("Couldn't find how much of the target chemical can be produced."))?;
 
     println!(
-        "You need {} {} to produce 1 {}.",
-        requirements[&raw_resource], raw_resource, goal
+        "You can produce {} {} with {}.",
+        fuel,
+        goal,
+        raw_resources
+            .iter()
+            .map(|raw_resource| format!("{} {}", available_ore, raw_resource))
+            .join(", ")
     );
 
     Ok(())
 }
 
-fn find_requirements_alt(
+// Producing more of the goal chemical can never take less of any raw
+// resource, so we can binary search for the most we can produce. There's
+// `available` of each raw resource, and whichever one runs out first is
+// what limits us.
+pub fn max_fuel(
     possible_reactions: &HashMap<Chemical, Reaction>,
     bases: &HashSet<Chemical>,
-    goal_chemical: Chemical,
-    goal_amount: usize,
-) -> Option<HashMap<Chemical, usize>> {
-    let mut bucket = hashmap! {
-        goal_chemical => goal_amount
+    goal_chemical: &Chemical,
+    raw_resources: &[Chemical],
+    available: usize,
+) -> Option<usize> {
+    // The order doesn't depend on the amounts at all, so there's no need to
+    // work it out again for every amount we try.
+    let order = topological_order(possible_reactions, bases, goal_chemical)?;
+
+    let raw_required = |goal_amount| {
+        let (requirements, _) =
+            resolve_requirements(possible_reactions, bases, &order, goal_amount);
+
+        raw_resources
+            .iter()
+            .map(|raw_resource| requirements.get(raw_resource).copied().unwrap_or(0))
+            .max()
+            .unwrap_or(0)
     };
 
-    while !bucket.iter().all(|(chemical, _)| bases.contains(chemical)) {
-        let mut to_add = HashMap::with_capacity(bucket.len());
-        let mut to_remove = Vec::with_capacity(bucket.len());
-
-        for (chemical, &amount) in &bucket {
-            // Check if we need this chemical to produce anything else in the bucket
-            let mut chemical_needed_later = false;
-
-            for other_chemical in bucket.keys() {
-                if other_chemical == chemical {
-                    continue;
-                }
-
-                if let Some(other_chemical_reaction) = possible_reactions.get(other_chemical) {
-                    if other_chemical_reaction
-                        .inputs
-                        .iter()
-                        .any(|(input, _)| input == chemical)
-                    {
-                        chemical_needed_later = true;
-                        break;
-                    }
-                } else if bases.contains(other_chemical) {
-                    continue;
-                } else {
-                    // There's a chemical here that we have no way of producing.
-                    return None;
-                }
-            }
+    // If the goal doesn't need any of the raw resources at all, there's no
+    // limit to how much of it we could produce.
+    if raw_required(1) == 0 {
+        return None;
+    }
 
-            if chemical_needed_later {
-                continue;
-            }
+    // First we keep doubling to find an amount we definitely can't produce,
+    // and then narrow things down between that and the last amount we can.
+    let (mut low, mut high) = (0, 1);
 
-            let chemical_reaction = possible_reactions.get(chemical)?;
+    while raw_required(high) <= available {
+        low = high;
+        high *= 2;
+    }
 
-            for (input_chemical, &input_amount) in chemical_reaction.inputs.iter() {
-                *to_add.entry(input_chemical.clone()).or_insert(0) += input_amount
-                    * (amount as f64 / chemical_reaction.output_amount as f64).ceil() as usize;
-            }
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
 
-            to_remove.push(chemical.clone());
+        if raw_required(mid) <= available {
+            low = mid;
+        } else {
+            high = mid;
         }
+    }
 
-        if to_remove.is_empty() {
-            // We're stuck in a loop, there's nothing we can remove from the bucket.
-            return None;
+    Some(low)
+}
+
+// Finds how much of each base chemical is needed to produce the goal, and
+// also keeps track of how much of each intermediate chemical was produced in
+// excess of what was actually consumed, since reactions only happen in whole
+// batches.
+pub fn find_requirements_and_leftovers(
+    possible_reactions: &HashMap<Chemical, Reaction>,
+    bases: &HashSet<Chemical>,
+    goal_chemical: Chemical,
+    goal_amount: usize,
+) -> Option<(HashMap<Chemical, usize>, HashMap<Chemical, usize>)> {
+    let order = topological_order(possible_reactions, bases, &goal_chemical)?;
+
+    Some(resolve_requirements(
+        possible_reactions,
+        bases,
+        &order,
+        goal_amount,
+    ))
+}
+
+// Orders the goal and everything it depends on so that every chemical comes
+// before all of the chemicals that are used to produce it. This means that by
+// the time we get to a chemical, everything that needs it has already been
+// accounted for, so we know exactly how much of it to produce in one go. Fails
+// if there's a chemical we have no way of producing, or if some chemical ends
+// up being needed to produce itself.
+fn topological_order(
+    possible_reactions: &HashMap<Chemical, Reaction>,
+    bases: &HashSet<Chemical>,
+    goal_chemical: &Chemical,
+) -> Option<Vec<Chemical>> {
+    fn visit(
+        chemical: &Chemical,
+        possible_reactions: &HashMap<Chemical, Reaction>,
+        bases: &HashSet<Chemical>,
+        in_progress: &mut HashSet<Chemical>,
+        order: &mut Vec<Chemical>,
+    ) -> Option<()> {
+        if order.contains(chemical) {
+            return Some(());
         }
 
-        for (chemical, amount) in to_add {
-            *bucket.entry(chemical).or_insert(0) += amount;
+        if !in_progress.insert(chemical.clone()) {
+            return None;
         }
 
-        for chemical in to_remove {
-            bucket.remove(&chemical);
+        if !bases.contains(chemical) {
+            for input_chemical in possible_reactions.get(chemical)?.inputs.keys() {
+                visit(
+                    input_chemical,
+                    possible_reactions,
+                    bases,
+                    in_progress,
+                    order,
+                )?;
+            }
         }
+
+        in_progress.remove(chemical);
+        order.push(chemical.clone());
+
+        Some(())
     }
 
-    Some(bucket)
+    let mut order = vec![];
+
+    visit(
+        goal_chemical,
+        possible_reactions,
+        bases,
+        &mut HashSet::new(),
+        &mut order,
+    )?;
+
+    // Every chemical got pushed after all of its inputs, so this is backwards.
+    order.reverse();
+
+    Some(order)
 }
 
-// TODO: This does not work accurately because the bucket is created
-// while the input chemicals are being iterated through. So depending
-// on the order in which that happens (which is arbitrary, because
-// goal_reaction.inputs is a HashSet), we may perform the reactions in
-// an ineffecient order. This can sometimes be "solved" by re-running
-// the program in hopes to get a different iteration order, but
-// that obviously isn't brilliant either.
-fn find_requirements(
+fn resolve_requirements(
     possible_reactions: &HashMap<Chemical, Reaction>,
     bases: &HashSet<Chemical>,
-    goal_chemical: Chemical,
+    order: &[Chemical],
     goal_amount: usize,
-    mut bucket: HashMap<Chemical, usize>,
-) -> Option<(usize, HashMap<Chemical, usize>, HashMap<Chemical, usize>)> {
+) -> (HashMap<Chemical, usize>, HashMap<Chemical, usize>) {
+    let mut required = hashmap! {
+        order[0].clone() => goal_amount
+    };
     let mut requirements = HashMap::with_capacity(bases.len());
+    let mut leftovers = HashMap::new();
 
-    let goal_reaction = possible_reactions.get(&goal_chemical)?;
-
-    for (input_chemical, &input_amount) in goal_reaction.inputs.iter() {
-        let amount_in_bucket = bucket.get(input_chemical).copied().unwrap_or(0);
-
-        if amount_in_bucket > input_amount {
-            bucket.get_mut(input_chemical).map(|amount_in_bucket_mut| {
-                *amount_in_bucket_mut -= input_amount;
-            });
+    for chemical in order {
+        let amount = required.get(chemical).copied().unwrap_or(0);
 
+        if bases.contains(chemical) {
+            requirements.insert(chemical.clone(), amount);
             continue;
-        } else {
-            bucket.remove(input_chemical);
         }
 
-        // This can't overflow because we checked earlier if the RHS >= LHS.
-        let input_required_amount = input_amount - amount_in_bucket;
+        let chemical_reaction = &possible_reactions[chemical];
+        let num_reactions =
+            (amount + chemical_reaction.output_amount - 1) / chemical_reaction.output_amount;
 
-        if bases.contains(input_chemical) {
-            *requirements.entry(input_chemical.clone()).or_insert(0) += input_required_amount;
-        } else {
-            let (input_produced_amount, input_requirements, input_leftovers) = find_requirements(
-                possible_reactions,
-                bases,
-                input_chemical.to_owned(),
-                input_required_amount,
-                bucket,
-            )?;
-
-            for (base, base_amount) in input_requirements {
-                *requirements.entry(base).or_insert(0) += base_amount;
-            }
+        for (input_chemical, &input_amount) in chemical_reaction.inputs.iter() {
+            *required.entry(input_chemical.clone()).or_insert(0) += input_amount * num_reactions;
+        }
 
-            bucket = input_leftovers;
+        let leftover_amount = num_reactions * chemical_reaction.output_amount - amount;
 
-            if input_produced_amount > input_required_amount {
-                *bucket.entry(input_chemical.clone()).or_insert(0) +=
-                    input_produced_amount - input_required_amount;
-            }
+        if leftover_amount > 0 {
+            leftovers.insert(chemical.clone(), leftover_amount);
         }
     }
 
-    let mut produced_amount = goal_reaction.output_amount;
+    (requirements, leftovers)
+}
 
-    if goal_reaction.output_amount < goal_amount {
-        let (rest_produced_amount, rest_requirements, rest_leftovers) = find_requirements(
-            possible_reactions,
-            bases,
-            goal_chemical,
-            goal_amount - goal_reaction.output_amount,
-            bucket,
-        )?;
+// Each chemical is a node, labeled with how much of it one reaction produces,
+// and each edge goes from an input to what it's used to produce, labeled with
+// how much of the input that takes. Sorted so that the same input always
+// gives the same graph.
+fn reactions_to_dot(
+    possible_reactions: &HashMap<Chemical, Reaction>,
+    bases: &HashSet<Chemical>,
+    goal_chemical: &Chemical,
+) -> String {
+    let mut dot = String::new();
 
-        for (base, base_amount) in rest_requirements {
-            *requirements.entry(base).or_insert(0) += base_amount;
-        }
+    writeln!(dot, "digraph reactions {{").unwrap();
 
-        produced_amount += rest_produced_amount;
-        bucket = rest_leftovers
+    for base in bases.iter().sorted() {
+        writeln!(dot, "    {:?} [style=filled, fillcolor=lightblue];", base).unwrap();
     }
 
-    Some((produced_amount, requirements, bucket))
+    for (chemical, reaction) in possible_reactions.iter().sorted_by_key(|(c, _)| *c) {
+        let style = if chemical == goal_chemical {
+            ", style=filled, fillcolor=gold"
+        } else {
+            ""
+        };
+
+        writeln!(
+            dot,
+            "    {:?} [label=\"{} {}\"{}];",
+            chemical, reaction.output_amount, chemical, style
+        )
+        .unwrap();
+
+        for (input_chemical, input_amount) in reaction.inputs.iter().sorted() {
+            writeln!(
+                dot,
+                "    {:?} -> {:?} [label=\"{}\"];",
+                input_chemical, chemical, input_amount
+            )
+            .unwrap();
+        }
+    }
+
+    writeln!(dot, "}}").unwrap();
+
+    dot
 }
 
-fn parse_input(reactions_str: &str) -> Result<HashMap<Chemical, Reaction>, anyhow::Error> {
+pub fn parse_input(reactions_str: &str) -> Result<HashMap<Chemical, Reaction>, anyhow::Error> {
     reactions_str
         .lines()
         .map(|reaction_str| {
@@ -228,10 +373,10 @@ fn parse_chemical_amount(chemical_amount_str: &str) -> Result<(Chemical, usize),
     Ok((chemical.to_owned(), amount_str.parse()?))
 }
 
-type Chemical = String;
+pub type Chemical = String;
 
 #[derive(Clone)]
-struct Reaction {
+pub struct Reaction {
     inputs: HashMap<Chemical, usize>,
     output_amount: usize,
 }
@@ -249,3 +394,59 @@ impl fmt::Debug for Reaction {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requirements_and_leftovers(
+        reactions_str: &str,
+        bases: &[&str],
+    ) -> (HashMap<Chemical, usize>, HashMap<Chemical, usize>) {
+        let possible_reactions = parse_input(reactions_str).unwrap();
+        let bases = bases.iter().map(|s| s.to_string()).collect();
+
+        find_requirements_and_leftovers(&possible_reactions, &bases, "FUEL".to_owned(), 1).unwrap()
+    }
+
+    #[test]
+    fn leftovers_in_165_ore_sample() {
+        let reactions_str = include_str!("input_small_2.txt");
+        let (requirements, leftovers) = requirements_and_leftovers(reactions_str, &["ORE"]);
+
+        assert_eq!(requirements, hashmap! { "ORE".to_owned() => 165 });
+        // FUEL needs 2 AB, 3 BC and 4 CA, which need 10 A, 23 B and 37 C
+        // between them. A comes in 2s, so there's none left over, but B
+        // comes in 3s and C in 5s, so 24 B and 40 C have to be produced.
+        assert_eq!(
+            leftovers,
+            hashmap! { "B".to_owned() => 1, "C".to_owned() => 3 }
+        );
+
+        // More generally, no reaction should ever have been run once more
+        // than it needed to be.
+        let possible_reactions = parse_input(reactions_str).unwrap();
+
+        for (chemical, &amount) in &leftovers {
+            assert!(amount < possible_reactions[chemical].output_amount);
+        }
+    }
+
+    #[test]
+    fn two_bases() {
+        let (requirements, leftovers) =
+            requirements_and_leftovers(include_str!("input_two_bases.txt"), &["ORE", "CLAY"]);
+
+        // The same reactions as the 165 ORE sample, except that C is made
+        // from CLAY. That takes 5 batches of A at 9 ORE each and 8 batches of
+        // B at 8 ORE each, and 8 batches of C at 7 CLAY each.
+        assert_eq!(
+            requirements,
+            hashmap! { "ORE".to_owned() => 45 + 64, "CLAY".to_owned() => 56 }
+        );
+        assert_eq!(
+            leftovers,
+            hashmap! { "B".to_owned() => 1, "C".to_owned() => 3 }
+        );
+    }
+}
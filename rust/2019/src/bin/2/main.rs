@@ -1,7 +1,12 @@
 use anyhow::{anyhow, bail};
+use aoc_2019_rust::{
+    input::read_input_trimmed,
+    intcode::{parse_program, Vm},
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
 use itertools::Itertools;
-use std::fs;
+use std::iter;
 
 fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2019-2")
@@ -10,17 +15,37 @@ fn main() -> Result<(), anyhow::Error> {
             Arg::from_usage("[required_value] -v, --required-value=<VALUE> 'Required value to produce for Part 2'")
                 .default_value("19690720"),
         )
+        .arg(
+            Arg::from_usage("[max_input] -m --max-input 'Largest value to try for each input in Part 2'")
+                .takes_value(true)
+                .default_value("99"),
+        )
+        .arg(
+            Arg::from_usage("[input_addrs] -a --input-addrs 'Comma-separated addresses of the inputs to search through in Part 2'")
+                .takes_value(true)
+                .default_value("1,2"),
+        )
+        .arg(Arg::from_usage("[time] --time 'Print how long parsing and solving take'"))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
 
-    let program_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
-    let program = parse_input(&program_str)?;
-
-    println!(
-        "Program with input (12, 2): {}",
-        run_program_with_inputs(&program, 12, 2)?[0],
-    );
+    let parse_timer = Timer::start("Parsing", time);
+    let program_str = read_input_trimmed(input_filename)?;
+    let program = parse_program(&program_str)?;
+    drop(parse_timer);
 
     let required_value = matches
         .value_of("required_value")
@@ -28,74 +53,82 @@ fn main() -> Result<(), anyhow::Error> {
         .parse()
         .map_err(|_| anyhow!("Provided required value is not a number"))?;
 
-    for (noun, verb) in (0usize..=99).permutations(2).map(|i| (i[0], i[1])) {
-        if run_program_with_inputs(&program, noun, verb)?[0] == required_value {
-            println!(
-                "Program with input ({}, {}): {} (required value)",
-                noun, verb, required_value
-            );
+    let max_input = matches
+        .value_of("max_input")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided max input is not a number"))?;
 
-            return Ok(());
-        }
+    let input_addrs: Vec<usize> = matches
+        .value_of("input_addrs")
+        .unwrap()
+        .split(',')
+        .map(|addr_str| addr_str.trim().parse())
+        .try_collect()
+        .map_err(|_| anyhow!("Provided input addresses aren't all numbers"))?;
+
+    let (part1_memory, found_inputs) = timing::repeat("Solving", repeat, time, || {
+        (
+            run_program_with_inputs(&program, &[1, 2], &[12, 2]),
+            find_inputs(&program, &input_addrs, max_input, required_value),
+        )
+    });
+
+    println!("Program with input (12, 2): {}", part1_memory?[0]);
+
+    match found_inputs? {
+        Some((inputs, value)) => println!(
+            "Program with input ({}): {} (required value)",
+            inputs.iter().join(", "),
+            value
+        ),
+        None => bail!(
+            "Couldn't find a set of inputs that produces {}",
+            required_value
+        ),
     }
 
-    bail!(
-        "Couldn't find a pair of inputs that produces {}",
-        required_value
-    );
+    Ok(())
 }
 
-fn run_program_with_inputs(
-    program: &Vec<usize>,
-    noun: usize,
-    verb: usize,
-) -> Result<Vec<usize>, anyhow::Error> {
-    let mut modified_program = program.clone();
-
-    modified_program[1] = noun;
-    modified_program[2] = verb;
+// Tries every combination of values up to max_input in the given addresses,
+// and returns the first one that leaves required_value in address 0.
+pub fn find_inputs(
+    program: &[isize],
+    input_addrs: &[usize],
+    max_input: isize,
+    required_value: isize,
+) -> Result<Option<(Vec<isize>, isize)>, anyhow::Error> {
+    for inputs in iter::repeat(0..=max_input)
+        .take(input_addrs.len())
+        .multi_cartesian_product()
+    {
+        let value = run_program_with_inputs(program, input_addrs, &inputs)?[0];
+
+        if value == required_value {
+            return Ok(Some((inputs, value)));
+        }
+    }
 
-    run_program(modified_program)
+    Ok(None)
 }
 
-fn run_program(mut program: Vec<usize>) -> Result<Vec<usize>, anyhow::Error> {
-    let mut instruction_pointer = 0;
-
-    loop {
-        let instruction = program[instruction_pointer];
-
-        match instruction {
-            1 | 2 => {
-                let ((x, y), result_idx) = (
-                    (
-                        program[program[instruction_pointer + 1]],
-                        program[program[instruction_pointer + 2]],
-                    ),
-                    program[instruction_pointer + 3],
-                );
-
-                match instruction {
-                    1 => program[result_idx] = x + y,
-                    2 => program[result_idx] = x * y,
-                    _ => unsafe { std::hint::unreachable_unchecked() },
-                }
-
-                instruction_pointer += 4;
-            }
-            99 => return Ok(program),
-            op => bail!("Encountered an unknown opcode: {}", op),
+pub fn run_program_with_inputs(
+    program: &[isize],
+    input_addrs: &[usize],
+    inputs: &[isize],
+) -> Result<Vec<isize>, anyhow::Error> {
+    let mut vm = Vm::new(program.to_vec());
+
+    for (&addr, &input) in input_addrs.iter().zip(inputs) {
+        if addr >= program.len() {
+            bail!("Input address {} is outside of the program", addr);
         }
+
+        vm.write(addr, input);
     }
-}
 
-fn parse_input(program_str: &str) -> Result<Vec<usize>, anyhow::Error> {
-    program_str
-        .split(",")
-        .map(|opcode_str| {
-            opcode_str
-                .trim()
-                .parse()
-                .map_err(|_| anyhow!("Could not parse opcode as usize: '{}'", opcode_str))
-        })
-        .try_collect()
+    vm.run_collect(iter::empty())?;
+
+    Ok(vm.into_memory())
 }
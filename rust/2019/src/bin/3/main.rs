@@ -1,26 +1,112 @@
-#![feature(box_syntax, iterator_fold_self)]
+#![feature(iterator_fold_self)]
 
-use anyhow::bail;
+use anyhow::{anyhow, bail};
+use aoc_2019_rust::{
+    geometry::{Direction, Point2},
+    input::read_input,
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
-use derive_more::From;
 use indexmap::IndexSet;
 use itertools::Itertools;
-use std::{fmt, fs, iter, str::FromStr};
 
 fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2019-3")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
+        .arg(Arg::from_usage(
+            "[dense] --dense 'Find intersections by expanding every point on the wires'",
+        ))
+        .arg(Arg::from_usage(
+            "[time] --time 'Print how long parsing and solving take'",
+        ))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
-    let all_wire_sections_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
+    let parse_timer = Timer::start("Parsing", time);
+    let all_wire_sections_str = read_input(input_filename)?;
 
     let all_wire_sections: Vec<_> = all_wire_sections_str
         .lines()
         .map(parse_wire_sections)
         .try_collect()?;
+    drop(parse_timer);
 
-    let all_wire_points: Vec<Vec<Point>> = all_wire_sections
+    let dense = matches.is_present("dense");
+
+    let (closest_point, (point, min_steps)) = timing::repeat("Solving", repeat, time, || {
+        closest_and_fewest_steps(all_wire_sections.clone(), dense)
+    })?;
+
+    println!(
+        "Closest intersection point to central port: {:?}, {} away",
+        closest_point,
+        closest_point.manhattan_distance(&Point2::origin())
+    );
+
+    println!(
+        "Point {:?} is {} = {} steps from the wire starts",
+        point,
+        min_steps
+            .iter()
+            .map(|n| n.to_string())
+            .collect_vec()
+            .join(" + "),
+        min_steps.iter().sum::<usize>()
+    );
+
+    Ok(())
+}
+
+// Gives the intersection closest to the central port, and the one the wires
+// reach in the fewest steps altogether, along with each wire's steps to it.
+fn closest_and_fewest_steps(
+    all_wire_sections: Vec<Vec<(Direction, usize)>>,
+    dense: bool,
+) -> Result<(Point2, (Point2, Vec<usize>)), anyhow::Error> {
+    let mut intersections = if dense {
+        dense_intersections(all_wire_sections)?
+    } else {
+        segment_intersections(all_wire_sections)?
+    };
+
+    // Every wire starts at the central port, so if they all happen to come
+    // back to it, that's not an intersection that counts.
+    intersections.retain(|(p, _)| *p != Point2::origin());
+
+    let closest_point = intersections
+        .iter()
+        .map(|(p, _)| *p)
+        .min_by_key(|p| p.manhattan_distance(&Point2::origin()))
+        .ok_or_else(|| anyhow!("No intersection points found."))?;
+
+    let fewest_steps = intersections
+        .into_iter()
+        .min_by_key(|(_, all_steps)| all_steps.iter().sum::<usize>())
+        .unwrap();
+
+    Ok((closest_point, fewest_steps))
+}
+
+// Every point that all the wires pass through, along with the number of
+// steps each wire takes to first reach it, found by walking every wire
+// one point at a time. This is simple, but needs memory proportional to
+// the total length of the wires.
+fn dense_intersections(
+    all_wire_sections: Vec<Vec<(Direction, usize)>>,
+) -> Result<Vec<(Point2, Vec<usize>)>, anyhow::Error> {
+    let all_wire_points: Vec<Vec<Point2>> = all_wire_sections
         .into_iter()
         .map(expand_to_wire_points)
         .try_collect()?;
@@ -31,90 +117,145 @@ fn main() -> Result<(), anyhow::Error> {
         .fold_first(|s1, s2| &s1 & &s2)
         .unwrap_or_else(IndexSet::new);
 
-    if intersection_points.len() == 0 {
-        bail!("No intersection points found.")
-    }
-
-    if let Some(closest_point) = intersection_points
-        .iter()
-        .min_by_key(|p| p.manhattan_distance(&Point::origin()))
-    {
-        println!(
-            "Closest intersection point to central port: {:?}",
-            closest_point
-        );
-    }
-
-    if let Some((idx, min_steps, min_total_steps)) = intersection_points
-        .iter()
-        .enumerate()
-        .map(|(idx, int_point)| {
+    Ok(intersection_points
+        .into_iter()
+        .map(|int_point| {
             let all_steps = all_wire_points
                 .iter()
-                .map(|wp| wp.iter().position(|p| p == int_point).unwrap() + 1)
+                .map(|wp| wp.iter().position(|&p| p == int_point).unwrap() + 1)
                 .collect_vec();
 
-            let total_steps = all_steps.iter().sum::<usize>();
-
-            (idx, all_steps, total_steps)
+            (int_point, all_steps)
         })
-        .min_by_key(|&(_, _, total_steps)| total_steps)
-    {
-        println!(
-            "Point {:?} is {} = {} steps from the wire starts",
-            intersection_points[idx],
-            min_steps
-                .iter()
-                .map(|n| n.to_string())
-                .collect_vec()
-                .join(" + "),
-            min_total_steps
-        );
-    }
-
-    Ok(())
+        .collect())
 }
 
 fn expand_to_wire_points(
     wire_sections: Vec<(Direction, usize)>,
-) -> Result<Vec<Point>, anyhow::Error> {
+) -> Result<Vec<Point2>, anyhow::Error> {
     let mut wire = Vec::with_capacity(wire_sections.iter().map(|(_, amount)| amount).sum());
-    let mut wire_head = Point::origin();
+    let mut wire_head = Point2::origin();
 
     for (direction, amount) in wire_sections {
-        let amount = amount as isize;
-
-        let Point { x, y } = wire_head;
-
-        use Direction::*;
-
-        let section_end = Point::from(match direction {
-            Up => (x + amount, y),
-            Right => (x, y + amount),
-            Down => (x - amount, y),
-            Left => (x, y - amount),
-        });
-
-        // We have to use a Box with dyn because the Iterator
-        // concrete types are technically different.
-        let coordinate_range: Box<dyn Iterator<Item = isize>> = match direction {
-            Up => box (x + 1..=section_end.x),
-            Right => box (y + 1..=section_end.y),
-            Down => box (section_end.x..=x - 1).rev(),
-            Left => box (section_end.y..=y - 1).rev(),
-        };
+        for _ in 0..amount {
+            wire_head = direction.step(wire_head);
+            wire.push(wire_head);
+        }
+    }
 
-        let section_points: Box<dyn Iterator<Item = (isize, isize)>> = match direction {
-            Up | Down => box coordinate_range.zip(iter::repeat(y)),
-            Right | Left => box iter::repeat(x).zip(coordinate_range),
-        };
+    Ok(wire)
+}
+
+// Same as dense_intersections, but only ever stores the segments of each
+// wire, so it works with wires of any length. Intersections are found by
+// overlapping every segment of the first wire with every segment of the
+// second, and then only keeping the points every other wire also reaches.
+fn segment_intersections(
+    all_wire_sections: Vec<Vec<(Direction, usize)>>,
+) -> Result<Vec<(Point2, Vec<usize>)>, anyhow::Error> {
+    let all_wire_segments = all_wire_sections
+        .into_iter()
+        .map(to_wire_segments)
+        .collect_vec();
 
-        wire_head = section_end;
+    let (first_wire, second_wire) = match all_wire_segments.as_slice() {
+        [first_wire, second_wire, ..] => (first_wire, second_wire),
+        _ => bail!("Need at least 2 wires to find intersections"),
+    };
 
-        wire.extend(section_points.map(Point::from))
+    let intersection_points: IndexSet<Point2> = first_wire
+        .iter()
+        .cartesian_product(second_wire)
+        .flat_map(|(s1, s2)| s1.overlap(s2))
+        .collect();
+
+    Ok(intersection_points
+        .into_iter()
+        .filter_map(|int_point| {
+            let all_steps = all_wire_segments
+                .iter()
+                .map(|segments| segments.iter().find_map(|s| s.steps_to(int_point)))
+                .collect::<Option<Vec<_>>>()?;
+
+            Some((int_point, all_steps))
+        })
+        .collect())
+}
+
+// A straight part of a wire, which covers the len points after start
+// (but not start itself, since the previous segment already covers it).
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    start: Point2,
+    direction: Direction,
+    len: usize,
+    // How many steps along the wire it takes to get to start.
+    steps_before: usize,
+}
+
+impl Segment {
+    fn end(&self) -> Point2 {
+        let Point2 { x: dx, y: dy } = self.direction.step(Point2::origin());
+        let len = self.len as isize;
+
+        Point2::new(self.start.x + dx * len, self.start.y + dy * len)
     }
 
-    Ok(wire)
+    // Corners of the box that the segment covers (inclusive). Since the
+    // segment is axis-aligned, the box is only ever 1 point thick.
+    fn bounds(&self) -> (Point2, Point2) {
+        let (first, last) = (self.direction.step(self.start), self.end());
+
+        (
+            Point2::new(first.x.min(last.x), first.y.min(last.y)),
+            Point2::new(first.x.max(last.x), first.y.max(last.y)),
+        )
+    }
+
+    fn steps_to(&self, point: Point2) -> Option<usize> {
+        let (min, max) = self.bounds();
+
+        if (min.x..=max.x).contains(&point.x) && (min.y..=max.y).contains(&point.y) {
+            Some(self.steps_before + self.start.manhattan_distance(&point))
+        } else {
+            None
+        }
+    }
+
+    // Both segments are boxes, so the points they share are just the
+    // overlap of those boxes. That's a single point if they cross, a line
+    // if they run along each other, or nothing at all.
+    fn overlap(&self, other: &Self) -> impl Iterator<Item = Point2> {
+        let ((min1, max1), (min2, max2)) = (self.bounds(), other.bounds());
+        let (min, max) = (
+            Point2::new(min1.x.max(min2.x), min1.y.max(min2.y)),
+            Point2::new(max1.x.min(max2.x), max1.y.min(max2.y)),
+        );
+
+        (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| Point2::new(x, y)))
+    }
+}
+
+fn to_wire_segments(wire_sections: Vec<(Direction, usize)>) -> Vec<Segment> {
+    let mut segments = Vec::with_capacity(wire_sections.len());
+    let (mut wire_head, mut steps) = (Point2::origin(), 0);
+
+    // A section with no length has no points, so it can't intersect
+    // anything, and its bounds wouldn't make sense anyway.
+    for (direction, len) in wire_sections.into_iter().filter(|&(_, len)| len > 0) {
+        let segment = Segment {
+            start: wire_head,
+            direction,
+            len,
+            steps_before: steps,
+        };
+
+        wire_head = segment.end();
+        steps += len;
+        segments.push(segment);
+    }
+
+    segments
 }
 
 fn parse_wire_sections(wire_sections_str: &str) -> Result<Vec<(Direction, usize)>, anyhow::Error> {
@@ -125,49 +266,59 @@ fn parse_wire_sections(wire_sections_str: &str) -> Result<Vec<(Direction, usize)
         .try_collect()
 }
 
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl FromStr for Direction {
-    type Err = anyhow::Error;
+    // Gives the closest intersection's distance and the fewest total steps,
+    // checking that both ways of finding intersections agree on them.
+    fn answers(wires_str: &str) -> (usize, usize) {
+        let all_wire_sections: Vec<_> = wires_str
+            .lines()
+            .map(parse_wire_sections)
+            .try_collect()
+            .unwrap();
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
-            "U" => Self::Up,
-            "D" => Self::Down,
-            "L" => Self::Left,
-            "R" => Self::Right,
-            _ => bail!("Unknown direction: {}", s),
-        })
-    }
-}
+        let (dense_answers, segment_answers) = [true, false]
+            .iter()
+            .map(|&dense| {
+                let (closest_point, (_, min_steps)) =
+                    closest_and_fewest_steps(all_wire_sections.clone(), dense).unwrap();
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy, From)]
-struct Point {
-    x: isize,
-    y: isize,
-}
+                (
+                    closest_point.manhattan_distance(&Point2::origin()),
+                    min_steps.iter().sum(),
+                )
+            })
+            .collect_tuple()
+            .unwrap();
+
+        assert_eq!(dense_answers, segment_answers);
 
-impl fmt::Debug for Point {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_tuple("P").field(&self.x).field(&self.y).finish()
+        dense_answers
     }
-}
 
-impl Point {
-    fn new(x: isize, y: isize) -> Self {
-        Self { x, y }
+    #[test]
+    fn samples() {
+        assert_eq!(answers(include_str!("input_small.txt")), (6, 30));
+        assert_eq!(answers(include_str!("input_small_2.txt")), (159, 610));
+        assert_eq!(answers(include_str!("input_small_3.txt")), (135, 410));
     }
 
-    fn origin() -> Self {
-        Self::new(0, 0)
+    #[test]
+    fn origin_revisited() {
+        // Both wires come back through the central port, which doesn't count,
+        // leaving the crossings 1 step right of it and 1 step up from it.
+        assert_eq!(answers(include_str!("input_revisit.txt")), (1, 4));
     }
 
-    fn manhattan_distance(&self, other: &Self) -> usize {
-        ((self.x - other.x).abs() + (self.y - other.y).abs()) as usize
+    #[test]
+    fn only_crossing_at_origin() {
+        let all_wire_sections = vec![
+            parse_wire_sections("R1,L1").unwrap(),
+            parse_wire_sections("U1,D1").unwrap(),
+        ];
+
+        assert!(closest_and_fewest_steps(all_wire_sections, false).is_err());
     }
 }
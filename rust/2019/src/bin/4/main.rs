@@ -1,69 +1,195 @@
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
+use aoc_2019_rust::{
+    input::read_input_trimmed,
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
-use digits_iterator::*;
 use itertools::Itertools;
-use std::fs;
+use std::mem;
 
 fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2019-4")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
+        .arg(Arg::from_usage(
+            "[list] -l --list 'Print every valid password as well as the count'",
+        ))
+        .arg(
+            Arg::from_usage("[radix] -r --radix 'Base to read the digits of each password in'")
+                .takes_value(true)
+                .default_value("10"),
+        )
+        .arg(
+            Arg::from_usage(
+                "[run_len] --run-len 'Length of the run of matching digits a password needs'",
+            )
+            .takes_value(true)
+            .default_value("2"),
+        )
+        .arg(Arg::from_usage(
+            "[time] --time 'Print how long parsing and solving take'",
+        ))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
-    let password_range_str = fs::read_to_string(input_filename)?;
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
+    let parse_timer = Timer::start("Parsing", time);
+    let password_range_str = read_input_trimmed(input_filename)?;
 
     let (password_min, password_max) = parse_input(&password_range_str)?;
+    drop(parse_timer);
 
-    println!(
-        "Number of valid passwords: {}",
-        (password_min..=password_max)
-            .filter(|&num| is_valid_password(num, true))
-            .count()
-    );
+    let list = matches.is_present("list");
+
+    let radix = matches
+        .value_of("radix")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided radix is not a number"))?;
+
+    if radix < 2 {
+        bail!("Radix must be at least 2");
+    }
+
+    let run_len = matches
+        .value_of("run_len")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided run length is not a number"))?;
+
+    // Listing the passwords on every single run would bury everything else.
+    let mut first_run = true;
+
+    let (num_valid, num_valid_exact_runs) = timing::repeat("Solving", repeat, time, || {
+        let list = list && mem::take(&mut first_run);
+
+        // Most passwords are valid under both rules, so each list needs a
+        // heading to tell them apart.
+        if list {
+            println!("Valid passwords:");
+        }
+
+        let num_valid =
+            count_valid_passwords(password_min, password_max, radix, run_len, true, list);
+
+        if list {
+            println!("\nValid passwords if longer runs of matching digits are considered invalid:");
+        }
+
+        let num_valid_exact_runs =
+            count_valid_passwords(password_min, password_max, radix, run_len, false, list);
+
+        if list {
+            println!();
+        }
+
+        (num_valid, num_valid_exact_runs)
+    });
+
+    println!("Number of valid passwords: {}", num_valid);
 
     println!(
-        "Number of valid passwords if >2 matching digits is considered invalid: {}",
-        (password_min..=password_max)
-            .filter(|&num| is_valid_password(num, false))
-            .count()
+        "Number of valid passwords if longer runs of matching digits are considered invalid: {}",
+        num_valid_exact_runs
     );
 
     Ok(())
 }
 
-fn is_valid_password(num: usize, multiple_matching_digits_valid: bool) -> bool {
+pub fn count_valid_passwords(
+    password_min: usize,
+    password_max: usize,
+    radix: usize,
+    run_len: usize,
+    longer_runs_valid: bool,
+    list: bool,
+) -> usize {
+    (password_min..=password_max)
+        .filter(|&num| is_valid_password(num, radix, run_len, longer_runs_valid))
+        .inspect(|num| {
+            if list {
+                println!("{}", num);
+            }
+        })
+        .count()
+}
+
+// A password is valid if its digits in the given radix never decrease,
+// and it has a run of exactly run_len equal digits (or at least run_len,
+// if longer_runs_valid is set). In base 10 with run_len = 2, 112233 and
+// 111122 are valid under both rules, but 123444 is only valid if
+// longer_runs_valid is set, since its only repetition is a run of three.
+fn is_valid_password(num: usize, radix: usize, run_len: usize, longer_runs_valid: bool) -> bool {
+    let run_matches = |len| {
+        if longer_runs_valid {
+            len >= run_len
+        } else {
+            len == run_len
+        }
+    };
+
     let mut all_increasing = true;
     let mut any_repeated = false;
+    // Length of the run of equal digits ending at d1.
     let mut repeated_len = 1;
 
     // 1234 -> [(1, 2), (2, 3), (3, 4)]
-    for (d1, d2) in num.digits().tuple_windows() {
+    for (d1, d2) in digits(num, radix).into_iter().tuple_windows() {
         if d1 > d2 {
             all_increasing = false;
             break;
         }
 
-        if d1 == d2 && !any_repeated {
-            if multiple_matching_digits_valid {
-                any_repeated = true;
-                continue;
-            }
+        if any_repeated {
+            // We've already found what we need, we're only
+            // still looping to check the digits are increasing.
+            continue;
+        }
 
+        if d1 == d2 {
             repeated_len += 1;
-        } else if repeated_len == 2 {
+        } else if run_matches(repeated_len) {
+            // The run that just ended was the right length.
             any_repeated = true;
         } else {
             repeated_len = 1;
         }
     }
 
-    // The very last pair of digits could've formed the
+    // The very last run of digits could've formed the
     // required repetition, so we need to check repeated_len
     // again here in case any_repeated couldn't be updated.
-    all_increasing && (any_repeated || repeated_len == 2)
+    all_increasing && (any_repeated || run_matches(repeated_len))
+}
+
+// Most significant digit first, so 1234 in base 10 -> [1, 2, 3, 4].
+fn digits(mut num: usize, radix: usize) -> Vec<usize> {
+    let mut digits = vec![];
+
+    loop {
+        digits.push(num % radix);
+        num /= radix;
+
+        if num == 0 {
+            break;
+        }
+    }
+
+    digits.reverse();
+    digits
 }
 
-fn parse_input(password_range_str: &str) -> Result<(usize, usize), anyhow::Error> {
+pub fn parse_input(password_range_str: &str) -> Result<(usize, usize), anyhow::Error> {
     let (min, max) = password_range_str
         .split("-")
         .map(|s| s.trim())
@@ -72,3 +198,41 @@ fn parse_input(password_range_str: &str) -> Result<(usize, usize), anyhow::Error
 
     Ok((min.parse()?, max.parse()?))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longer_runs_valid() {
+        assert!(is_valid_password(111111, 10, 2, true));
+        assert!(!is_valid_password(223450, 10, 2, true));
+        assert!(!is_valid_password(123789, 10, 2, true));
+        assert!(is_valid_password(123444, 10, 2, true));
+    }
+
+    #[test]
+    fn exact_runs_only() {
+        assert!(is_valid_password(112233, 10, 2, false));
+        assert!(!is_valid_password(123444, 10, 2, false));
+        // The run of four 1s doesn't count, but the 22 does.
+        assert!(is_valid_password(111122, 10, 2, false));
+        assert!(!is_valid_password(111111, 10, 2, false));
+    }
+
+    #[test]
+    fn other_run_lengths() {
+        assert!(is_valid_password(123444, 10, 3, false));
+        assert!(!is_valid_password(112233, 10, 3, true));
+        assert!(!is_valid_password(111122, 10, 3, false));
+        assert!(is_valid_password(111122, 10, 3, true));
+    }
+
+    #[test]
+    fn other_radixes() {
+        // 0b111 has a run of three 1s, and 0xaa has a run of two as.
+        assert!(is_valid_password(0b111, 2, 3, false));
+        assert!(is_valid_password(0xaa, 16, 2, false));
+        assert!(!is_valid_password(0xba, 16, 2, true));
+    }
+}
@@ -1,36 +1,106 @@
-use anyhow::anyhow;
+use anyhow::{anyhow, bail, ensure};
+use aoc_2019_rust::{
+    input::read_input,
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
 use itertools::Itertools;
 use multimap::MultiMap;
-use std::{collections::HashMap, fs, hash::Hash, mem};
+use std::{
+    cmp,
+    collections::{HashMap, HashSet, VecDeque},
+    hash::Hash,
+};
 
 fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2019-6")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
+        .arg(
+            Arg::from_usage("[method] --method 'How to find the path between us and Santa'")
+                .takes_value(true)
+                .possible_values(&["lca", "bfs"])
+                .default_value("lca"),
+        )
+        .arg(Arg::from_usage(
+            "[time] --time 'Print how long parsing and solving take'",
+        ))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
+    let use_bfs = matches.value_of("method") == Some("bfs");
 
-    let orbits_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
+    let parse_timer = Timer::start("Parsing", time);
+    let orbits_str = read_input(input_filename)?;
     let orbits = parse_input(&orbits_str)?;
+    validate_orbits(&orbits)?;
+    drop(parse_timer);
 
-    let mut depths = HashMap::with_capacity(orbits.len());
-    let mut euler_walk = Vec::with_capacity(orbits.len());
+    let (num_orbits, num_transfers) = timing::repeat("Solving", repeat, time, || {
+        let mut depths = HashMap::with_capacity(orbits.len());
+        let mut euler_walk = Vec::with_capacity(orbits.len());
 
-    depth_first_traversal(&orbits, &mut depths, &mut euler_walk, &"COM".to_owned(), 0);
+        depth_first_traversal(&orbits, &mut depths, &mut euler_walk, &"COM".to_owned(), 0);
 
-    println!("Total number of orbits: {}", depths.values().sum::<usize>());
+        (
+            total_orbits(&depths),
+            transfers_to_santa(&orbits, &depths, &euler_walk, use_bfs),
+        )
+    });
+
+    println!("Total number of orbits: {}", num_orbits);
 
     println!(
         "Shortest path between us and Santa is {} orbital transfers long",
-        find_path_length(&depths, &euler_walk, &"YOU".to_owned(), &"SAN".to_owned())
-            .map(|e| e.saturating_sub(2)) // Skip the starting and destination
-            .ok_or_else(|| anyhow!("Couldn't find a path between us and Santa"))?,
+        num_transfers?,
     );
 
     Ok(())
 }
 
+// Everything orbits everything between it and COM, so an object's depth is
+// exactly how many direct and indirect orbits it's a part of.
+pub fn total_orbits(depths: &HashMap<String, usize>) -> usize {
+    depths.values().sum()
+}
+
+fn transfers_to_santa(
+    orbits: &MultiMap<String, String>,
+    depths: &HashMap<String, usize>,
+    euler_walk: &[String],
+    use_bfs: bool,
+) -> Result<usize, anyhow::Error> {
+    // Transfers are counted between the objects we and Santa are orbiting,
+    // not between us and Santa ourselves. We can't just subtract 2 from the
+    // length of the path between YOU and SAN, because if one of them is an
+    // ancestor of the other its parent won't be on that path at all.
+    let (our_parent, santas_parent) = (
+        find_parent(orbits, &"YOU".to_owned())
+            .ok_or_else(|| anyhow!("We aren't orbiting anything"))?,
+        find_parent(orbits, &"SAN".to_owned())
+            .ok_or_else(|| anyhow!("Santa isn't orbiting anything"))?,
+    );
+
+    let path_length = if use_bfs {
+        find_path_length_bfs(orbits, our_parent, santas_parent)
+    } else {
+        find_path_length(depths, euler_walk, our_parent, santas_parent)
+    };
+
+    path_length.ok_or_else(|| anyhow!("Couldn't find a path between us and Santa"))
+}
+
 // GeeksForGeeks comes in clutch, unexpectedly!
 // https://www.geeksforgeeks.org/lca-n-ary-tree-constant-query-o1/
 fn find_path_length<T: Eq + Hash>(
@@ -39,27 +109,71 @@ fn find_path_length<T: Eq + Hash>(
     start: &T,
     destination: &T,
 ) -> Option<usize> {
-    let (mut start_pos, mut end_pos) = euler_walk
-        .iter()
-        .positions(|e| e == start || e == destination)
-        .collect_tuple()?;
-
-    if start_pos > end_pos {
-        mem::swap(&mut start_pos, &mut end_pos);
-    }
+    // Non-leaf elements show up in the walk once per child (plus one), so we
+    // specifically need the first occurrence of each of them.
+    let (start_pos, end_pos) = (
+        euler_walk.iter().position(|e| e == start)?,
+        euler_walk.iter().position(|e| e == destination)?,
+    );
 
-    let lowest_common_ancestor_depth = euler_walk[start_pos..end_pos]
+    // The range has to include both ends, because if one of them is an
+    // ancestor of the other (or they're the same element), it's the
+    // lowest common ancestor itself.
+    let lowest_common_ancestor_depth = euler_walk
+        [cmp::min(start_pos, end_pos)..=cmp::max(start_pos, end_pos)]
         .iter()
-        // Skip the starting element. If we added 1 to the start_pos
-        // we could end up panicking from an invalid index.
-        .skip(1)
         .map(|e| depths.get(e).unwrap())
         .min()?;
 
     Some((depths[start] + depths[destination]) - (lowest_common_ancestor_depth * 2))
 }
 
-fn depth_first_traversal<T: Eq + Hash + Clone>(
+// The obvious way of doing it, which is much easier to trust than the
+// Euler walk, so it's handy for double checking it. Orbits are treated as
+// going both ways, since we can transfer inwards as well as outwards.
+fn find_path_length_bfs<T: Eq + Hash>(
+    elements: &MultiMap<T, T>,
+    start: &T,
+    destination: &T,
+) -> Option<usize> {
+    let mut neighbours: HashMap<&T, Vec<&T>> = HashMap::new();
+
+    for (parent, children) in elements.iter_all() {
+        for child in children {
+            neighbours.entry(parent).or_default().push(child);
+            neighbours.entry(child).or_default().push(parent);
+        }
+    }
+
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+
+    visited.insert(start);
+    queue.push_back((start, 0));
+
+    while let Some((current, distance)) = queue.pop_front() {
+        if current == destination {
+            return Some(distance);
+        }
+
+        for &next in neighbours.get(current).into_iter().flatten() {
+            if visited.insert(next) {
+                queue.push_back((next, distance + 1));
+            }
+        }
+    }
+
+    None
+}
+
+fn find_parent<'a, T: Eq + Hash>(elements: &'a MultiMap<T, T>, child: &T) -> Option<&'a T> {
+    elements
+        .iter_all()
+        .find(|(_, children)| children.contains(child))
+        .map(|(parent, _)| parent)
+}
+
+pub fn depth_first_traversal<T: Eq + Hash + Clone>(
     elements: &MultiMap<T, T>,
     depths: &mut HashMap<T, usize>,
     euler_walk: &mut Vec<T>,
@@ -77,7 +191,67 @@ fn depth_first_traversal<T: Eq + Hash + Clone>(
     }
 }
 
-fn parse_input(orbits_str: &str) -> Result<MultiMap<String, String>, anyhow::Error> {
+// The traversals all assume the map is a tree rooted at COM, and will happily
+// loop forever or miss bits of it otherwise, so we make sure of that first.
+fn validate_orbits(orbits: &MultiMap<String, String>) -> Result<(), anyhow::Error> {
+    let mut parents = HashMap::new();
+
+    // Sorted so that the same input always gives the same error.
+    for (parent, children) in orbits.iter_all().sorted() {
+        for child in children {
+            if let Some(other_parent) = parents.insert(child, parent) {
+                bail!("{} orbits both {} and {}", child, other_parent, parent);
+            }
+        }
+    }
+
+    let roots = orbits
+        .keys()
+        .filter(|object| !parents.contains_key(object))
+        .sorted()
+        .collect_vec();
+
+    match roots.as_slice() {
+        [] => bail!("Everything orbits something, so the map must have a cycle"),
+        [root] if root.as_str() != "COM" => {
+            bail!("The map is centered on {} instead of COM", root)
+        }
+        [_] => {}
+        _ => bail!(
+            "The map has more than one center: {}",
+            roots.iter().format(", ")
+        ),
+    }
+
+    // Anything that isn't reachable from COM by now has to be part of a cycle
+    // that doesn't touch the rest of the map.
+    let mut reachable = HashSet::new();
+    let mut to_visit = vec!["COM".to_owned()];
+
+    while let Some(object) = to_visit.pop() {
+        if let Some(children) = orbits.get_vec(&object) {
+            to_visit.extend(children.iter().cloned());
+        }
+
+        reachable.insert(object);
+    }
+
+    let unreachable = parents
+        .keys()
+        .filter(|object| !reachable.contains(**object))
+        .sorted()
+        .collect_vec();
+
+    ensure!(
+        unreachable.is_empty(),
+        "Some objects are in a cycle that can't be reached from COM: {}",
+        unreachable.iter().format(", ")
+    );
+
+    Ok(())
+}
+
+pub fn parse_input(orbits_str: &str) -> Result<MultiMap<String, String>, anyhow::Error> {
     orbits_str
         .lines()
         .map(|orbit| {
@@ -89,3 +263,108 @@ fn parse_input(orbits_str: &str) -> Result<MultiMap<String, String>, anyhow::Err
         })
         .try_collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Both ways of finding the path, which should always agree.
+    fn transfers_both_ways(orbits_str: &str) -> (usize, usize) {
+        let orbits = parse_input(orbits_str).unwrap();
+        let mut depths = HashMap::new();
+        let mut euler_walk = vec![];
+
+        depth_first_traversal(&orbits, &mut depths, &mut euler_walk, &"COM".to_owned(), 0);
+
+        (
+            transfers_to_santa(&orbits, &depths, &euler_walk, false).unwrap(),
+            transfers_to_santa(&orbits, &depths, &euler_walk, true).unwrap(),
+        )
+    }
+
+    #[test]
+    fn sample() {
+        assert_eq!(transfers_both_ways(include_str!("input_small.txt")), (4, 4));
+    }
+
+    #[test]
+    fn orbiting_the_same_object() {
+        assert_eq!(transfers_both_ways("COM)A\nA)YOU\nA)SAN"), (0, 0));
+    }
+
+    #[test]
+    fn ancestor() {
+        // We're orbiting A, which Santa's parent C is two orbits out from.
+        assert_eq!(transfers_both_ways("COM)A\nA)YOU\nA)B\nB)C\nC)SAN"), (2, 2));
+        // The other way around, with us two orbits out from Santa, whose
+        // parent is COM.
+        assert_eq!(transfers_both_ways("COM)SAN\nSAN)B\nB)YOU"), (2, 2));
+    }
+
+    #[test]
+    fn invalid_maps() {
+        let validate = |orbits_str| validate_orbits(&parse_input(orbits_str).unwrap());
+
+        assert!(validate(include_str!("input_small.txt")).is_ok());
+
+        for &(orbits_str, error) in &[
+            (
+                include_str!("input_cycle.txt"),
+                "Everything orbits something, so the map must have a cycle",
+            ),
+            (
+                include_str!("input_disconnected.txt"),
+                "Some objects are in a cycle that can't be reached from COM: X, Y, Z",
+            ),
+            (
+                include_str!("input_orphaned.txt"),
+                "The map has more than one center: COM, X",
+            ),
+            (include_str!("input_wrong.txt"), "SAN orbits both B and C"),
+        ] {
+            assert_eq!(validate(orbits_str).unwrap_err().to_string(), error);
+        }
+    }
+
+    #[test]
+    fn same_start_and_destination() {
+        let orbits = parse_input(include_str!("input_small.txt")).unwrap();
+        let mut depths = HashMap::new();
+        let mut euler_walk = vec![];
+
+        depth_first_traversal(&orbits, &mut depths, &mut euler_walk, &"COM".to_owned(), 0);
+
+        // D shows up in the walk several times, once for each of its children.
+        for object in ["D", "K", "COM"].iter().map(|s| s.to_string()) {
+            assert_eq!(
+                find_path_length(&depths, &euler_walk, &object, &object),
+                Some(0)
+            );
+        }
+    }
+
+    #[test]
+    fn repeated_nodes_in_walk() {
+        let orbits = parse_input(include_str!("input_small.txt")).unwrap();
+        let mut depths = HashMap::new();
+        let mut euler_walk = vec![];
+
+        depth_first_traversal(&orbits, &mut depths, &mut euler_walk, &"COM".to_owned(), 0);
+
+        // B, D and E all have more than one child, so they're in the walk
+        // more than once, and every pair should still match the BFS.
+        for (start, destination) in ["B", "D", "E", "L", "H", "SAN"]
+            .iter()
+            .map(|s| s.to_string())
+            .tuple_combinations()
+        {
+            assert_eq!(
+                find_path_length(&depths, &euler_walk, &start, &destination),
+                find_path_length_bfs(&orbits, &start, &destination),
+                "{} to {}",
+                start,
+                destination
+            );
+        }
+    }
+}
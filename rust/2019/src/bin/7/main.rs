@@ -1,30 +1,63 @@
-use anyhow::{anyhow, bail, ensure};
+use anyhow::{anyhow, bail, ensure, Context};
+use aoc_2019_rust::{
+    input::read_input_trimmed,
+    intcode::{parse_program, run_program, IntcodeError},
+    search::{best_permutation, Extend},
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
-use digits_iterator::*;
 use itertools::Itertools;
-use rayon::prelude::*;
-use std::{cmp, convert::TryFrom, fs};
-use tokio::{pin, task};
-use tokio_stream::{Stream, StreamExt};
+use std::ops::RangeInclusive;
+use tokio::task;
+use tokio_stream::StreamExt;
 
 fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2019-7")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
+        .arg(Arg::from_usage("[num_amps] -n --num-amps 'Number of amplifiers in the pipeline'").takes_value(true).default_value("5"))
+        .arg(Arg::from_usage("[phase_range] -p --phase-range 'Range of phase settings to use without feedback loops, as <min>-<max>'").takes_value(true).default_value("0-4"))
+        .arg(Arg::from_usage("[feedback_phase_range] -f --feedback-phase-range 'Range of phase settings to use with feedback loops, as <min>-<max>'").takes_value(true).default_value("5-9"))
+        .arg(Arg::from_usage("[time] --time 'Print how long parsing and solving take'"))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
+    let num_amps = matches.value_of("num_amps").unwrap().parse::<usize>()?;
+    let phase_range = parse_phase_range(matches.value_of("phase_range").unwrap(), num_amps)?;
+    let feedback_phase_range =
+        parse_phase_range(matches.value_of("feedback_phase_range").unwrap(), num_amps)?;
+
+    let parse_timer = Timer::start("Parsing", time);
+    let program_str = read_input_trimmed(input_filename)?;
+    let program = parse_program(&program_str)?;
+    drop(parse_timer);
+
+    let (without_feedback, with_feedback) = timing::repeat("Solving", repeat, time, || {
+        (
+            find_max_thruster_val(program.clone(), phase_range.clone(), num_amps),
+            find_max_thruster_val(program.clone(), feedback_phase_range.clone(), num_amps),
+        )
+    });
 
-    let program_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
-    let program = parse_input(&program_str)?;
-
-    let (max_thruster_val, max_phase_settings) = find_max_thruster_val(program.clone(), 0..=4)?;
+    let (max_thruster_val, max_phase_settings) = without_feedback?;
 
     println!(
         "Maximum thruster value: {} achieved with phase settings {:?}, without feedback loops",
         max_thruster_val, max_phase_settings
     );
 
-    let (max_thruster_val, max_phase_settings) = find_max_thruster_val(program, 5..=9)?;
+    let (max_thruster_val, max_phase_settings) = with_feedback?;
 
     println!(
         "Maximum thruster value: {} achieved with phase settings {:?}, with feedback loops",
@@ -34,28 +67,23 @@ fn main() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
-fn find_max_thruster_val(
+pub fn find_max_thruster_val(
     program: Vec<isize>,
     phase_settings_range: impl IntoIterator<Item = usize>,
+    num_amps: usize,
 ) -> Result<(isize, Vec<usize>), anyhow::Error> {
-    // Using Rayon is definitely overkill but hey, whatever.
-    phase_settings_range
-        .into_iter()
-        .permutations(5)
-        // We must collect because the Permuations iterator's Item
-        // isn't Send, which is required by Rayon.
-        .collect_vec()
-        .into_par_iter()
-        .map(|phase_settings_permutation| {
-            Ok((
-                run_amplifiers(program.clone(), phase_settings_permutation.clone())?,
-                phase_settings_permutation,
-            ))
-        })
-        .try_reduce(
-            || (isize::MIN, vec![]),
-            |current_max, thruster_result| Ok(cmp::max(thruster_result, current_max)),
-        )
+    let phase_settings = phase_settings_range.into_iter().collect_vec();
+
+    best_permutation(&phase_settings, num_amps, |phase_settings_permutation| {
+        // There's no way to tell how good a pipeline is going to be until
+        // every amplifier has a phase setting, so nothing gets pruned.
+        if phase_settings_permutation.len() < num_amps {
+            return Ok(Extend::Continue);
+        }
+
+        run_amplifiers(program.clone(), phase_settings_permutation.to_vec()).map(Extend::Score)
+    })?
+    .ok_or_else(|| anyhow!("There are no phase settings to try"))
 }
 
 // Eric asks us to effectively implement Intcode multithreading, or at
@@ -83,6 +111,7 @@ async fn run_amplifiers(
     main_tx.send(0)?;
 
     let mut curr_rx = first_rx;
+    let mut amplifiers = Vec::with_capacity(phase_settings.len());
 
     for &current_phase_setting in phase_settings.iter() {
         let (output_tx, next_rx) = flume::unbounded();
@@ -92,7 +121,7 @@ async fn run_amplifiers(
         let program = program.clone();
         let mut disconnected_tx = false;
 
-        task::spawn(run_program(
+        amplifiers.push(task::spawn(run_program(
             program,
             tokio_stream::once(current_phase_setting as isize).chain(input_rx.into_stream()),
             move |output| {
@@ -100,9 +129,9 @@ async fn run_amplifiers(
                     if output_tx.send(output).is_err() {
                         disconnected_tx = true;
 
-                        // Propogating errors is still kind of a question mark for me, and this is
-                        // a scenario that theoretically "shouldn't happen" anyway, so just inform
-                        // the user in case it does.
+                        // If the next amplifier died because of an error, that gets reported
+                        // once we join all the tasks below. But if it halted normally, this is a
+                        // scenario that theoretically "shouldn't happen", so just inform the user.
                         eprintln!(concat!(
                             "An amplifier has disconnected while output is still available. ",
                             "This usually means the amplifier Intcode program is written incorrectly."
@@ -110,180 +139,74 @@ async fn run_amplifiers(
                     }
                 }
             },
-        ));
+        )));
     }
 
     let main_rx = curr_rx;
+    let mut last_thruster_val = None;
 
     while let Ok(thruster_val) = main_rx.recv_async().await {
+        last_thruster_val = Some(thruster_val);
+
         // Loop back around, unless the first amplifier is done.
         if main_tx.send(thruster_val).is_err() {
-            return Ok(thruster_val);
+            break;
         }
     }
 
-    bail!("Thruster value cannot be computed.");
-}
-
-async fn run_program(
-    mut program: Vec<isize>,
-    input: impl Stream<Item = isize>,
-    mut output_fn: impl FnMut(isize),
-) -> Result<Vec<isize>, anyhow::Error> {
-    pin!(input);
-
-    let mut instruction_pointer = 0;
-
-    loop {
-        let opcode = usize::try_from(program[instruction_pointer])
-            .map_err(|_| anyhow!("Found a negative integer where an opcode was expected"))?;
-
-        let parameter_modes = get_parameter_modes(opcode)?;
-
-        let parameter_mode_of = |param: usize| {
-            parameter_modes
-                .get(param)
-                .unwrap_or(&ParameterModes::Position)
-        };
-
-        let get_param = |param: usize, need_write: bool| {
-            let param_value = program
-                .get(instruction_pointer + param + 1)
-                .copied()
-                .ok_or(anyhow!("Parameter not found"))?;
-
-            let param_mode = parameter_mode_of(param);
-
-            if need_write {
-                ensure!(
-                    param_mode == &ParameterModes::Position,
-                    "Invalid argument for opcode {}: {}",
-                    opcode,
-                    param_value
-                );
+    // If we got here because the last amplifier stopped, nothing is ever going
+    // to be fed back into the first one, so let it see the end of its input
+    // instead of waiting on us forever.
+    drop(main_tx);
+
+    // When one amplifier fails, every amplifier after it is going to run out of
+    // input as a consequence, so a MissingInput is only the actual cause of the
+    // failure if no other error happened.
+    let mut starved_amplifier = None;
+
+    for (amp_num, amplifier) in amplifiers.into_iter().enumerate() {
+        match amplifier.await? {
+            Ok(_) => {}
+            Err(IntcodeError::MissingInput { .. }) => {
+                starved_amplifier.get_or_insert(amp_num + 1);
             }
-
-            Ok(match param_mode {
-                ParameterModes::Position => {
-                    let idx = usize::try_from(param_value).map_err(|_| {
-                        anyhow!("Found a negative integer where a position param was expected")
-                    })?;
-
-                    if !need_write {
-                        ensure!(
-                            idx < program.len(),
-                            "Invalid result index for opcode {}: {}",
-                            opcode,
-                            idx
-                        );
-
-                        program[idx]
-                    } else {
-                        param_value
-                    }
-                }
-                ParameterModes::Immediate => param_value,
-            })
-        };
-
-        // x % 100 gets the last 2 digits of a number,
-        // no matter how long it is.
-        match opcode % 100 {
-            1 | 2 | 7 | 8 => {
-                let (x, y, result_idx) = (
-                    get_param(0, false)?,
-                    get_param(1, false)?,
-                    get_param(2, true)? as usize,
-                );
-
-                match opcode % 100 {
-                    1 => program[result_idx] = x + y,
-                    2 => program[result_idx] = x * y,
-                    7 => program[result_idx] = (x < y) as isize,
-                    8 => program[result_idx] = (x == y) as isize,
-                    _ => unsafe { std::hint::unreachable_unchecked() },
-                }
-
-                instruction_pointer += 4;
-            }
-            5 | 6 => {
-                let (checked_value, jump_point) = (
-                    get_param(0, false)?,
-                    usize::try_from(get_param(1, false)?).map_err(|_| {
-                        anyhow!("Found a negative integer where a jump point was expected")
-                    })?,
-                );
-
-                let should_jump = match opcode % 100 {
-                    5 => checked_value != 0,
-                    6 => checked_value == 0,
-                    _ => unsafe { std::hint::unreachable_unchecked() },
-                };
-
-                if should_jump {
-                    instruction_pointer = jump_point;
-                } else {
-                    instruction_pointer += 3;
-                }
-            }
-            3 | 4 => {
-                match opcode % 100 {
-                    3 => {
-                        let input = input
-                            .next()
-                            .await
-                            .ok_or(anyhow!("Found an input opcode but no input was provided"))?;
-                        let input_storage = get_param(0, true)? as usize;
-
-                        program[input_storage] = input;
-                    }
-                    4 => output_fn(get_param(0, false)?),
-                    _ => unsafe { std::hint::unreachable_unchecked() },
-                }
-
-                instruction_pointer += 2;
-            }
-            99 => return Ok(program),
-            op => bail!("Encountered an unknown opcode: {}", op),
+            Err(e) => return Err(e).with_context(|| format!("Amplifier {} failed", amp_num + 1)),
         }
     }
-}
 
-fn get_parameter_modes(opcode: usize) -> Result<Vec<ParameterModes>, anyhow::Error> {
-    opcode
-        .digits()
-        .rev()
-        .skip(2)
-        .map(ParameterModes::try_from)
-        .try_collect()
-}
+    if let Some(amp_num) = starved_amplifier {
+        bail!(
+            "Amplifier {} ran out of input. This usually means the amplifier Intcode program is written incorrectly.",
+            amp_num
+        );
+    }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum ParameterModes {
-    Position,
-    Immediate,
+    last_thruster_val.context("Thruster value cannot be computed.")
 }
 
-impl TryFrom<u8> for ParameterModes {
-    type Error = anyhow::Error;
-
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        Ok(match value {
-            0 => Self::Position,
-            1 => Self::Immediate,
-            _ => bail!("Unknown parameter mode: {}", value),
-        })
-    }
-}
+// Every amplifier needs its own phase setting, so there have to be at least
+// as many settings in the range as there are amplifiers.
+fn parse_phase_range(
+    phase_range_str: &str,
+    num_amps: usize,
+) -> Result<RangeInclusive<usize>, anyhow::Error> {
+    let (min, max) = phase_range_str
+        .split('-')
+        .map(|s| s.trim())
+        .collect_tuple()
+        .ok_or_else(|| anyhow!("Phase ranges should look like <min>-<max>"))?;
+
+    let phase_range = min.parse()?..=max.parse()?;
+    let num_settings = phase_range.clone().count();
+
+    ensure!(num_amps > 0, "There has to be at least one amplifier");
+    ensure!(
+        num_settings >= num_amps,
+        "The phase range {} only has {} settings, which isn't enough for {} amplifiers",
+        phase_range_str,
+        num_settings,
+        num_amps
+    );
 
-fn parse_input(program_str: &str) -> Result<Vec<isize>, anyhow::Error> {
-    program_str
-        .split(",")
-        .map(|num_str| {
-            num_str
-                .trim()
-                .parse()
-                .map_err(|_| anyhow!("Could not parse number in program as isize: '{}'", num_str))
-        })
-        .try_collect()
+    Ok(phase_range)
 }
@@ -1,61 +1,248 @@
 use anyhow::{anyhow, bail, ensure};
+use aoc_2019_rust::{
+    geometry::Point2,
+    grid::Grid,
+    input::read_input_trimmed,
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
+use colored::Colorize;
 use itertools::Itertools;
 use std::{convert::TryFrom, fs};
 
 fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2019-8")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
+        .arg(
+            Arg::from_usage("[width] --width 'Width of the image in pixels'")
+                .takes_value(true)
+                .default_value("25"),
+        )
+        .arg(
+            Arg::from_usage("[height] --height 'Height of the image in pixels'")
+                .takes_value(true)
+                .default_value("6"),
+        )
+        .arg(
+            Arg::from_usage("[transparent_char] -t --transparent-char 'Character to draw pixels that are transparent in every layer with'")
+                .takes_value(true)
+                .default_value("?"),
+        )
+        .arg(Arg::from_usage(
+            "[strict] --strict 'Fail if any pixel is transparent in every layer'",
+        ))
+        .arg(
+            Arg::from_usage("[format] --format 'Draw the image as text in the terminal, or write it to --out as a PPM'")
+                .takes_value(true)
+                .possible_values(&["text", "ppm"])
+                .default_value("text"),
+        )
+        .arg(
+            Arg::from_usage("[out] -o --out 'File to write the image to'")
+                .takes_value(true)
+                .required_if("format", "ppm"),
+        )
+        .arg(
+            Arg::from_usage("[scale] --scale 'Size of the square each pixel becomes in the PPM'")
+                .takes_value(true)
+                .default_value("1"),
+        )
+        .arg(
+            Arg::from_usage("[black_color] --black-color 'Hex RGB colour of black pixels in the PPM'")
+                .takes_value(true)
+                .default_value("000000"),
+        )
+        .arg(
+            Arg::from_usage("[white_color] --white-color 'Hex RGB colour of white pixels in the PPM'")
+                .takes_value(true)
+                .default_value("ffffff"),
+        )
+        .arg(
+            Arg::from_usage("[transparent_color] --transparent-color 'Hex RGB colour of transparent pixels in the PPM'")
+                .takes_value(true)
+                .default_value("ff00ff"),
+        )
+        .arg(Arg::from_usage("[time] --time 'Print how long parsing and solving take'"))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
 
-    let image_layers_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
+    let width = matches
+        .value_of("width")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided width is not a number"))?;
 
-    let image_layers = parse_input(&image_layers_str, 25, 6)?;
+    let height = matches
+        .value_of("height")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided height is not a number"))?;
+
+    let transparent_char = matches
+        .value_of("transparent_char")
+        .unwrap()
+        .chars()
+        .exactly_one()
+        .map_err(|_| anyhow!("Provided transparent character isn't a single character"))?;
+
+    let parse_timer = Timer::start("Parsing", time);
+    let image_layers_str = read_input_trimmed(input_filename)?;
+    let image_layers = parse_input(&image_layers_str, width, height)?;
+    drop(parse_timer);
 
     ensure!(!image_layers.is_empty(), "Input image is empty");
 
-    let min_black_layer = image_layers
-        .iter()
-        .min_by_key(|&l| count_pixel_type(l, Pixel::Black))
-        .unwrap();
+    let (checksum, image) = timing::repeat("Solving", repeat, time, || {
+        (
+            image_checksum(&image_layers),
+            decode_image_layers(&image_layers),
+        )
+    });
 
-    println!(
-        "Image checksum: {}",
-        count_pixel_type(min_black_layer, Pixel::White)
-            * count_pixel_type(min_black_layer, Pixel::Transparent)
-    );
+    println!("Image checksum: {}", checksum);
 
-    let image = decode_image_layers(&image_layers);
+    if matches.is_present("strict") {
+        ensure_fully_decoded(&image)?;
+    }
+
+    if matches.value_of("format") == Some("ppm") {
+        let out_filename = matches.value_of("out").unwrap();
+
+        let scale = matches
+            .value_of("scale")
+            .unwrap()
+            .parse()
+            .map_err(|_| anyhow!("Provided scale is not a number"))?;
+
+        ensure!(scale > 0, "Scale must be at least 1");
 
-    render_image(&image)?;
+        let palette = Palette {
+            black: parse_color(matches.value_of("black_color").unwrap())?,
+            white: parse_color(matches.value_of("white_color").unwrap())?,
+            transparent: parse_color(matches.value_of("transparent_color").unwrap())?,
+        };
+
+        fs::write(out_filename, encode_ppm(&image, &palette, scale))?;
+
+        println!("Wrote image to {}", out_filename);
+    } else {
+        render_image(&image, transparent_char);
+    }
 
     Ok(())
 }
 
-fn render_image(image: &ImageLayer) -> Result<(), anyhow::Error> {
-    for row in image {
-        for pixel in row {
-            use Pixel::*;
-
-            print!(
-                "{}",
-                match pixel {
-                    Black => '█',
-                    White => ' ',
-                    Transparent => bail!("Found transparent pixel in image"),
-                }
+// A pixel can only still be transparent after decoding if it was transparent
+// in every single layer, which means the image doesn't say what colour it is.
+fn ensure_fully_decoded(image: &ImageLayer) -> Result<(), anyhow::Error> {
+    for (y, row) in image.iter().enumerate() {
+        for (x, &pixel) in row.iter().enumerate() {
+            ensure!(
+                pixel != Pixel::Transparent,
+                "Found transparent pixel in image at ({}, {})",
+                x,
+                y
             );
         }
-
-        println!();
     }
 
     Ok(())
 }
 
-fn decode_image_layers(image_layers: &[ImageLayer]) -> ImageLayer {
+fn render_image(image: &ImageLayer, transparent_char: char) {
+    let mut pixels = Grid::dense(image.first().map_or(0, |row| row.len()), image.len());
+
+    for (y, row) in image.iter().enumerate() {
+        for (x, &pixel) in row.iter().enumerate() {
+            pixels.set(Point2::new(x as isize, y as isize), pixel);
+        }
+    }
+
+    print!(
+        "{}",
+        pixels.render(|pixel| match pixel {
+            Some(Pixel::Black) => "█".normal(),
+            Some(Pixel::Transparent) => transparent_char.to_string().normal(),
+            _ => " ".normal(),
+        })
+    );
+}
+
+struct Palette {
+    black: [u8; 3],
+    white: [u8; 3],
+    transparent: [u8; 3],
+}
+
+// Binary PPM (P6) is about the simplest raster format there is: a short text
+// header, then 3 bytes per pixel, row by row. Each pixel in the image becomes
+// a scale x scale block, since 25x6 is pretty tiny on its own.
+// See http://netpbm.sourceforge.net/doc/ppm.html
+fn encode_ppm(image: &ImageLayer, palette: &Palette, scale: usize) -> Vec<u8> {
+    let (width, height) = (image.first().map_or(0, |row| row.len()), image.len());
+
+    let mut ppm = format!("P6\n{} {}\n255\n", width * scale, height * scale).into_bytes();
+
+    for row in image {
+        for _ in 0..scale {
+            for pixel in row {
+                let color = match pixel {
+                    Pixel::Black => palette.black,
+                    Pixel::White => palette.white,
+                    Pixel::Transparent => palette.transparent,
+                };
+
+                for _ in 0..scale {
+                    ppm.extend_from_slice(&color);
+                }
+            }
+        }
+    }
+
+    ppm
+}
+
+// Accepts colours like "ff8000", with or without a leading #.
+fn parse_color(color_str: &str) -> Result<[u8; 3], anyhow::Error> {
+    let hex = color_str.trim_start_matches('#');
+
+    ensure!(
+        hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        "Colour must be 6 hex digits: {}",
+        color_str
+    );
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16);
+
+    Ok([channel(0)?, channel(1)?, channel(2)?])
+}
+
+// The number of white pixels times the number of transparent ones, in the
+// layer with the fewest black pixels. There has to be at least one layer.
+pub fn image_checksum(image_layers: &[ImageLayer]) -> usize {
+    let min_black_layer = image_layers
+        .iter()
+        .min_by_key(|&l| count_pixel_type(l, Pixel::Black))
+        .unwrap();
+
+    count_pixel_type(min_black_layer, Pixel::White)
+        * count_pixel_type(min_black_layer, Pixel::Transparent)
+}
+
+pub fn decode_image_layers(image_layers: &[ImageLayer]) -> ImageLayer {
     let (width, height) = (image_layers[0][0].len(), image_layers[0].len());
 
     let mut image = vec![vec![Pixel::Transparent; width]; height];
@@ -85,13 +272,27 @@ fn count_pixel_type(layer: &ImageLayer, pixel_type: Pixel) -> usize {
         .count()
 }
 
-fn parse_input(
+pub fn parse_input(
     image_layers_str: &str,
     width: usize,
     height: usize,
 ) -> Result<Vec<ImageLayer>, anyhow::Error> {
+    let image_layers_str = image_layers_str.trim();
+    let layer_size = width * height;
+
+    ensure!(layer_size > 0, "Image dimensions can't be 0");
+
+    // Otherwise the last layer would just silently be missing some pixels.
+    let num_pixels = image_layers_str.chars().count();
+    ensure!(
+        num_pixels % layer_size == 0,
+        "Input has {} pixels, which can't be split into {}x{} layers",
+        num_pixels,
+        width,
+        height
+    );
+
     image_layers_str
-        .trim()
         .chars()
         .map(|c| {
             let digit = c
@@ -110,15 +311,22 @@ fn parse_input(
         .try_collect()
 }
 
-type ImageLayer = Vec<Vec<Pixel>>;
+pub type ImageLayer = Vec<Vec<Pixel>>;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum Pixel {
+pub enum Pixel {
     Black,
     White,
     Transparent,
 }
 
+// A pixel that nothing's been drawn on yet lets everything show through.
+impl Default for Pixel {
+    fn default() -> Self {
+        Self::Transparent
+    }
+}
+
 impl TryFrom<u8> for Pixel {
     type Error = anyhow::Error;
 
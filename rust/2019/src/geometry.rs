@@ -0,0 +1,92 @@
+use anyhow::bail;
+use derive_more::From;
+use std::{fmt, str::FromStr};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, From, Default)]
+pub struct Point2 {
+    pub x: isize,
+    pub y: isize,
+}
+
+impl fmt::Debug for Point2 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("").field(&self.x).field(&self.y).finish()
+    }
+}
+
+impl Point2 {
+    pub fn origin() -> Self {
+        Self::new(0, 0)
+    }
+
+    pub fn new(x: isize, y: isize) -> Self {
+        Self::from((x, y))
+    }
+
+    pub fn manhattan_distance(&self, other: &Self) -> usize {
+        ((self.x - other.x).abs() + (self.y - other.y).abs()) as usize
+    }
+}
+
+// Directions are in screen terms, the same way grid::render draws things: up
+// is towards negative y.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn turn_left(self) -> Self {
+        use Direction::*;
+
+        match self {
+            Up => Left,
+            Left => Down,
+            Down => Right,
+            Right => Up,
+        }
+    }
+
+    pub fn turn_right(self) -> Self {
+        self.turn_left().opposite()
+    }
+
+    pub fn opposite(self) -> Self {
+        use Direction::*;
+
+        match self {
+            Up => Down,
+            Down => Up,
+            Left => Right,
+            Right => Left,
+        }
+    }
+
+    pub fn step(self, point: Point2) -> Point2 {
+        use Direction::*;
+
+        match self {
+            Up => Point2::new(point.x, point.y - 1),
+            Down => Point2::new(point.x, point.y + 1),
+            Left => Point2::new(point.x - 1, point.y),
+            Right => Point2::new(point.x + 1, point.y),
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "U" | "^" => Self::Up,
+            "D" | "v" => Self::Down,
+            "L" | "<" => Self::Left,
+            "R" | ">" => Self::Right,
+            _ => bail!("Unknown direction: {}", s),
+        })
+    }
+}
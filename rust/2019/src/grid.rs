@@ -0,0 +1,268 @@
+use crate::geometry::Point2;
+use anyhow::{bail, Context};
+use colored::ColoredString;
+use itertools::{Either, Itertools};
+use std::{collections::HashMap, mem};
+
+pub type StyledChar = ColoredString;
+
+// A grid of cells addressed by Point2, with y increasing downwards. It can
+// either be dense, a fixed-size rectangle with (0, 0) in the top left corner
+// where every cell always has a value, or sparse, where only the cells that
+// have been set exist and they can be anywhere at all. Dense grids are faster
+// and smaller when the size is known up front, while sparse grids are for
+// when things can wander off in any direction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid<T> {
+    cells: Cells<T>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Cells<T> {
+    // Stored row by row.
+    Dense {
+        width: usize,
+        height: usize,
+        cells: Vec<T>,
+    },
+    Sparse(HashMap<Point2, T>),
+}
+
+impl<T: Default + Clone> Grid<T> {
+    // Every cell starts off as T::default().
+    pub fn dense(width: usize, height: usize) -> Self {
+        Self {
+            cells: Cells::Dense {
+                width,
+                height,
+                cells: vec![T::default(); width * height],
+            },
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn sparse() -> Self {
+        Self {
+            cells: Cells::Sparse(HashMap::new()),
+        }
+    }
+
+    // For a sparse grid, these are the size of the box that fits around
+    // all the cells that have been set.
+    pub fn width(&self) -> usize {
+        match &self.cells {
+            Cells::Dense { width, .. } => *width,
+            Cells::Sparse(_) => self
+                .bounds()
+                .map_or(0, |(min, max)| (max.x - min.x) as usize + 1),
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        match &self.cells {
+            Cells::Dense { height, .. } => *height,
+            Cells::Sparse(_) => self
+                .bounds()
+                .map_or(0, |(min, max)| (max.y - min.y) as usize + 1),
+        }
+    }
+
+    // How many cells have a value, which for a dense grid is all of them.
+    pub fn len(&self) -> usize {
+        match &self.cells {
+            Cells::Dense { cells, .. } => cells.len(),
+            Cells::Sparse(cells) => cells.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The top left and bottom right points of the grid, both inclusive, or
+    // None if the grid is empty.
+    pub fn bounds(&self) -> Option<(Point2, Point2)> {
+        match &self.cells {
+            Cells::Dense {
+                width,
+                height,
+                cells,
+            } => {
+                if cells.is_empty() {
+                    return None;
+                }
+
+                Some((
+                    Point2::origin(),
+                    Point2::new(*width as isize - 1, *height as isize - 1),
+                ))
+            }
+            Cells::Sparse(cells) => {
+                let (min_x, max_x) = cells.keys().map(|p| p.x).minmax().into_option()?;
+                let (min_y, max_y) = cells.keys().map(|p| p.y).minmax().into_option()?;
+
+                Some((Point2::new(min_x, min_y), Point2::new(max_x, max_y)))
+            }
+        }
+    }
+
+    pub fn get(&self, point: Point2) -> Option<&T> {
+        match &self.cells {
+            Cells::Dense { width, cells, .. } => cells.get(dense_idx(*width, point)?),
+            Cells::Sparse(cells) => cells.get(&point),
+        }
+    }
+
+    // Returns the value that was there before, if there was one. Dense
+    // grids can't grow, so setting a point outside of one panics.
+    pub fn set(&mut self, point: Point2, value: T) -> Option<T> {
+        match &mut self.cells {
+            Cells::Dense {
+                width,
+                height,
+                cells,
+            } => {
+                let idx = dense_idx(*width, point)
+                    .filter(|&idx| idx < cells.len())
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "{:?} is outside of a {}x{} dense grid",
+                            point, width, height
+                        )
+                    });
+
+                Some(mem::replace(&mut cells[idx], value))
+            }
+            Cells::Sparse(cells) => cells.insert(point, value),
+        }
+    }
+
+    // Goes through every cell in reading order, for both kinds of grid.
+    pub fn iter_points(&self) -> impl Iterator<Item = (Point2, &T)> {
+        match &self.cells {
+            Cells::Dense { width, cells, .. } => {
+                let width = *width;
+
+                Either::Left(cells.iter().enumerate().map(move |(idx, cell)| {
+                    (
+                        Point2::new((idx % width) as isize, (idx / width) as isize),
+                        cell,
+                    )
+                }))
+            }
+            Cells::Sparse(cells) => Either::Right(
+                cells
+                    .iter()
+                    .map(|(&point, cell)| (point, cell))
+                    .sorted_by_key(|(point, _)| (point.y, point.x)),
+            ),
+        }
+    }
+
+    // Same as the free render function, but for a grid of either kind.
+    pub fn render(&self, style_fn: impl Fn(Option<&T>) -> StyledChar) -> String {
+        let (min, max) = self
+            .bounds()
+            .unwrap_or((Point2::origin(), Point2::origin()));
+
+        render_box(min, max, |point| style_fn(self.get(point)))
+    }
+}
+
+fn dense_idx(width: usize, point: Point2) -> Option<usize> {
+    if point.x < 0 || point.y < 0 || point.x as usize >= width {
+        return None;
+    }
+
+    Some(point.y as usize * width + point.x as usize)
+}
+
+// Turns each character of a block of text into a cell of a grid, failing if
+// any of the lines are a different length from the first one.
+pub fn parse<T>(
+    grid_str: &str,
+    mut cell_fn: impl FnMut(char, Point2) -> Result<T, anyhow::Error>,
+) -> Result<Grid<T>, anyhow::Error> {
+    let mut cells = vec![];
+    let mut width = None;
+    let mut height = 0;
+
+    for (y, row) in grid_str.lines().enumerate() {
+        let row_width = row.chars().count();
+
+        match width {
+            None => width = Some(row_width),
+            Some(width) if width != row_width => bail!(
+                "Row {} is {} characters wide, but the rows above it are {}",
+                y,
+                row_width,
+                width
+            ),
+            _ => {}
+        }
+
+        for (x, cell_char) in row.chars().enumerate() {
+            let point = Point2::new(x as isize, y as isize);
+
+            cells.push(
+                cell_fn(cell_char, point)
+                    .with_context(|| format!("Invalid cell at {:?}", point))?,
+            );
+        }
+
+        height += 1;
+    }
+
+    Ok(Grid {
+        cells: Cells::Dense {
+            width: width.unwrap_or(0),
+            height,
+            cells,
+        },
+    })
+}
+
+// Draws every cell in the bounding box of the grid, one row per line, with y
+// increasing downwards. Cells inside the box that aren't in the grid at all
+// are passed to the styling function as None, so that it can decide what an
+// empty cell should look like.
+pub fn render<T>(
+    cells: &HashMap<Point2, T>,
+    style_fn: impl Fn(Option<&T>) -> StyledChar,
+) -> String {
+    let ((min_x, max_x), (min_y, max_y)) = (
+        cells
+            .keys()
+            .map(|p| p.x)
+            .minmax()
+            .into_option()
+            .unwrap_or_default(),
+        cells
+            .keys()
+            .map(|p| p.y)
+            .minmax()
+            .into_option()
+            .unwrap_or_default(),
+    );
+
+    render_box(
+        Point2::new(min_x, min_y),
+        Point2::new(max_x, max_y),
+        |point| style_fn(cells.get(&point)),
+    )
+}
+
+fn render_box(min: Point2, max: Point2, style_fn: impl Fn(Point2) -> StyledChar) -> String {
+    let mut grid_str = String::new();
+
+    for y in min.y..=max.y {
+        for x in min.x..=max.x {
+            grid_str.push_str(&style_fn(Point2::new(x, y)).to_string());
+        }
+
+        grid_str.push('\n');
+    }
+
+    grid_str
+}
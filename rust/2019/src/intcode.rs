@@ -0,0 +1,733 @@
+use anyhow::{anyhow, bail, ensure, Context};
+use digits_iterator::*;
+use itertools::Itertools;
+use parking_lot::Mutex;
+use std::{
+    collections::VecDeque,
+    convert::TryFrom,
+    fmt,
+    io::{self, Write},
+    iter,
+    sync::Arc,
+};
+use tokio::pin;
+use tokio_stream::{Stream, StreamExt};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum IntcodeError {
+    #[error("Encountered an unknown opcode at {}: {}", ip, opcode)]
+    UnknownOpcode { ip: usize, opcode: isize },
+    #[error(
+        "The program is attempting to access a negative index at {}: {}",
+        ip,
+        index
+    )]
+    NegativeIndex { ip: usize, index: isize },
+    #[error("Invalid parameter mode for the instruction at {}: {}", ip, mode)]
+    BadParameterMode { ip: usize, mode: u8 },
+    #[error("Found an input opcode at {} but no input was provided", ip)]
+    MissingInput { ip: usize },
+    #[error("The instruction at {} runs past the end of the program", ip)]
+    OutOfBounds { ip: usize },
+    #[error("Exceeded the step limit of {} instructions at {}", max_steps, ip)]
+    StepLimitExceeded { ip: usize, max_steps: u64 },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StepResult {
+    Output(isize),
+    NeedInput,
+    Halted,
+    Continue,
+}
+
+// A single Intcode machine that can be driven one instruction at a time.
+// Since it never has to wait on anything by itself (it just reports
+// StepResult::NeedInput and lets the caller decide what to do), it doesn't
+// need async or a runtime for callers that don't need concurrency.
+#[derive(Debug, Clone)]
+pub struct Vm {
+    memory: Vec<isize>,
+    instruction_pointer: usize,
+    relative_base: isize,
+    inputs: VecDeque<isize>,
+    instructions_executed: u64,
+    max_steps: Option<u64>,
+    trace: Option<Tracer>,
+}
+
+// Everything about an instruction that's just about to be executed. The
+// parameters are what the instruction is going to see: parameters that are
+// read from are already resolved to the values they point at, and the ones
+// that are written to are the addresses that are going to be written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub ip: usize,
+    pub opcode: usize,
+    pub mnemonic: &'static str,
+    pub params: Vec<isize>,
+    pub relative_base: isize,
+}
+
+// Closures can't be cloned or debug printed, so this is what lets Vm stay
+// Clone and Debug. Clones of a traced Vm all report to the same hook.
+#[derive(Clone)]
+struct Tracer(Arc<Mutex<dyn FnMut(&TraceEvent) + Send>>);
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Tracer")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VmState {
+    memory: Vec<isize>,
+    instruction_pointer: usize,
+    relative_base: isize,
+    inputs: VecDeque<isize>,
+    instructions_executed: u64,
+}
+
+impl Vm {
+    pub fn new(program: Vec<isize>) -> Self {
+        Self {
+            memory: program,
+            instruction_pointer: 0,
+            relative_base: 0,
+            inputs: VecDeque::new(),
+            instructions_executed: 0,
+            max_steps: None,
+            trace: None,
+        }
+    }
+
+    // Makes step() fail instead of executing any more instructions once this
+    // many have been executed, so that a program stuck in an infinite loop
+    // can't hang us forever. Halting doesn't count as an instruction.
+    pub fn with_max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    // Calls the hook right before every instruction that gets executed,
+    // which is handy for seeing what a program is actually doing.
+    pub fn with_trace(mut self, trace: impl FnMut(&TraceEvent) + Send + 'static) -> Self {
+        self.trace = Some(Tracer(Arc::new(Mutex::new(trace))));
+        self
+    }
+
+    pub fn instructions_executed(&self) -> u64 {
+        self.instructions_executed
+    }
+
+    pub fn instruction_pointer(&self) -> usize {
+        self.instruction_pointer
+    }
+
+    pub fn push_input(&mut self, input: isize) {
+        self.inputs.push_back(input);
+    }
+
+    // Queues up a line of text for programs that speak ASCII, one input
+    // per byte, along with the newline that terminates every command.
+    pub fn feed_line(&mut self, line: &str) {
+        self.inputs.extend(ascii_input(line));
+    }
+
+    // Memory past the end of the program is treated as all zeroes,
+    // so we don't need to grow anything just to read from it.
+    pub fn read(&self, addr: usize) -> isize {
+        self.memory.get(addr).copied().unwrap_or(0)
+    }
+
+    pub fn write(&mut self, addr: usize, val: isize) {
+        self.grow_to_fit(addr);
+        self.memory[addr] = val;
+    }
+
+    pub fn memory(&self) -> &[isize] {
+        &self.memory
+    }
+
+    pub fn into_memory(self) -> Vec<isize> {
+        self.memory
+    }
+
+    // Captures everything needed to resume the machine from exactly where
+    // it is right now, including any inputs that haven't been consumed yet.
+    // This is much cheaper than re-running a program from the start when
+    // exploring multiple branches from the same point.
+    pub fn snapshot(&self) -> VmState {
+        VmState {
+            memory: self.memory.clone(),
+            instruction_pointer: self.instruction_pointer,
+            relative_base: self.relative_base,
+            inputs: self.inputs.clone(),
+            instructions_executed: self.instructions_executed,
+        }
+    }
+
+    pub fn restore(&mut self, state: VmState) {
+        self.memory = state.memory;
+        self.instruction_pointer = state.instruction_pointer;
+        self.relative_base = state.relative_base;
+        self.inputs = state.inputs;
+        self.instructions_executed = state.instructions_executed;
+    }
+
+    // Executes the instruction at the instruction pointer. If that's an input
+    // instruction and there's no input queued up, nothing happens and we
+    // report StepResult::NeedInput, so calling this again after a push_input
+    // will pick up right where we left off. Likewise, a halted machine stays
+    // halted no matter how many times this is called.
+    pub fn step(&mut self) -> Result<StepResult, IntcodeError> {
+        let result = self.execute_instruction()?;
+
+        if let StepResult::Output(_) | StepResult::Continue = result {
+            self.instructions_executed += 1;
+        }
+
+        Ok(result)
+    }
+
+    // Runs until the program halts, with every input it's going to need
+    // queued up front, and gives back everything it output. Running out of
+    // input is an error here, since there's no way to get any more.
+    pub fn run_collect(
+        &mut self,
+        inputs: impl IntoIterator<Item = isize>,
+    ) -> Result<Vec<isize>, IntcodeError> {
+        self.inputs.extend(inputs);
+
+        let mut outputs = vec![];
+
+        loop {
+            match self.step()? {
+                StepResult::Output(output) => outputs.push(output),
+                StepResult::NeedInput => {
+                    return Err(IntcodeError::MissingInput {
+                        ip: self.instruction_pointer,
+                    })
+                }
+                StepResult::Halted => return Ok(outputs),
+                StepResult::Continue => {}
+            }
+        }
+    }
+
+    fn execute_instruction(&mut self) -> Result<StepResult, IntcodeError> {
+        let ip = self.instruction_pointer;
+
+        let raw_opcode = *self
+            .memory
+            .get(ip)
+            .ok_or(IntcodeError::OutOfBounds { ip })?;
+        let opcode = usize::try_from(raw_opcode).map_err(|_| IntcodeError::UnknownOpcode {
+            ip,
+            opcode: raw_opcode,
+        })?;
+
+        if let Some(max_steps) = self.max_steps {
+            if opcode % 100 != 99 && self.instructions_executed >= max_steps {
+                return Err(IntcodeError::StepLimitExceeded { ip, max_steps });
+            }
+        }
+
+        let parameter_modes = get_parameter_modes(opcode, ip)?;
+
+        if let Some(Tracer(trace)) = &self.trace {
+            // An input instruction that's still waiting for input doesn't get
+            // executed yet. And if we can't even build the event, the
+            // instruction is going to fail anyway, and that error is much more
+            // useful than a trace.
+            if !(opcode % 100 == 3 && self.inputs.is_empty()) {
+                if let Some(event) = self.trace_event(opcode, &parameter_modes) {
+                    (trace.lock())(&event);
+                }
+            }
+        }
+
+        // x % 100 gets the last 2 digits of a number,
+        // no matter how long it is.
+        Ok(match opcode % 100 {
+            1 | 2 | 7 | 8 => {
+                let (x, y, result_idx) = (
+                    self.get_param(&parameter_modes, 0)?,
+                    self.get_param(&parameter_modes, 1)?,
+                    self.get_write_idx(&parameter_modes, 2)?,
+                );
+
+                self.memory[result_idx] = match opcode % 100 {
+                    1 => x + y,
+                    2 => x * y,
+                    7 => (x < y) as isize,
+                    8 => (x == y) as isize,
+                    _ => unsafe { std::hint::unreachable_unchecked() },
+                };
+
+                self.instruction_pointer += 4;
+
+                StepResult::Continue
+            }
+            5 | 6 => {
+                let (checked_value, jump_point) = (
+                    self.get_param(&parameter_modes, 0)?,
+                    self.get_param(&parameter_modes, 1)?,
+                );
+
+                let should_jump = match opcode % 100 {
+                    5 => checked_value != 0,
+                    6 => checked_value == 0,
+                    _ => unsafe { std::hint::unreachable_unchecked() },
+                };
+
+                if should_jump {
+                    self.instruction_pointer =
+                        usize::try_from(jump_point).map_err(|_| IntcodeError::NegativeIndex {
+                            ip,
+                            index: jump_point,
+                        })?;
+                } else {
+                    self.instruction_pointer += 3;
+                }
+
+                StepResult::Continue
+            }
+            3 | 4 | 9 => {
+                let result = match opcode % 100 {
+                    3 => {
+                        let input = match self.inputs.pop_front() {
+                            Some(input) => input,
+                            None => return Ok(StepResult::NeedInput),
+                        };
+                        let input_storage = self.get_write_idx(&parameter_modes, 0)?;
+
+                        self.memory[input_storage] = input;
+
+                        StepResult::Continue
+                    }
+                    4 => StepResult::Output(self.get_param(&parameter_modes, 0)?),
+                    9 => {
+                        self.relative_base += self.get_param(&parameter_modes, 0)?;
+
+                        StepResult::Continue
+                    }
+                    _ => unsafe { std::hint::unreachable_unchecked() },
+                };
+
+                self.instruction_pointer += 2;
+
+                result
+            }
+            99 => StepResult::Halted,
+            _ => {
+                return Err(IntcodeError::UnknownOpcode {
+                    ip,
+                    opcode: raw_opcode,
+                })
+            }
+        })
+    }
+
+    fn trace_event(&self, opcode: usize, parameter_modes: &[ParameterModes]) -> Option<TraceEvent> {
+        let (mnemonic, num_params, write_param) = opcode_info(opcode % 100)?;
+
+        let params = (0..num_params)
+            .map(|param| {
+                let value = self.raw_param(param).ok()?;
+
+                let idx = match parameter_mode_of(parameter_modes, param) {
+                    ParameterModes::Immediate if write_param == Some(param) => return None,
+                    ParameterModes::Immediate => return Some(value),
+                    ParameterModes::Position => value,
+                    ParameterModes::Relative => self.relative_base + value,
+                };
+
+                if write_param == Some(param) {
+                    Some(idx)
+                } else {
+                    Some(self.read(usize::try_from(idx).ok()?))
+                }
+            })
+            .collect::<Option<_>>()?;
+
+        Some(TraceEvent {
+            ip: self.instruction_pointer,
+            opcode: opcode % 100,
+            mnemonic,
+            params,
+            relative_base: self.relative_base,
+        })
+    }
+
+    fn get_param(
+        &mut self,
+        parameter_modes: &[ParameterModes],
+        param: usize,
+    ) -> Result<isize, IntcodeError> {
+        let param_value = self.raw_param(param)?;
+
+        Ok(match parameter_mode_of(parameter_modes, param) {
+            ParameterModes::Position | ParameterModes::Relative => {
+                let idx = self.param_idx(parameter_modes, param)?;
+
+                self.memory[idx]
+            }
+            ParameterModes::Immediate => param_value,
+        })
+    }
+
+    fn get_write_idx(
+        &mut self,
+        parameter_modes: &[ParameterModes],
+        param: usize,
+    ) -> Result<usize, IntcodeError> {
+        if parameter_mode_of(parameter_modes, param) == ParameterModes::Immediate {
+            return Err(IntcodeError::BadParameterMode {
+                ip: self.instruction_pointer,
+                mode: ParameterModes::Immediate as u8,
+            });
+        }
+
+        self.param_idx(parameter_modes, param)
+    }
+
+    // Works out which memory address a position or relative mode parameter
+    // is pointing at, growing the memory if it's past the end.
+    fn param_idx(
+        &mut self,
+        parameter_modes: &[ParameterModes],
+        param: usize,
+    ) -> Result<usize, IntcodeError> {
+        let param_value = self.raw_param(param)?;
+
+        let raw_idx = if parameter_mode_of(parameter_modes, param) == ParameterModes::Relative {
+            self.relative_base + param_value
+        } else {
+            param_value
+        };
+
+        let idx = usize::try_from(raw_idx).map_err(|_| IntcodeError::NegativeIndex {
+            ip: self.instruction_pointer,
+            index: raw_idx,
+        })?;
+
+        self.grow_to_fit(idx);
+
+        Ok(idx)
+    }
+
+    fn grow_to_fit(&mut self, idx: usize) {
+        if idx >= self.memory.len() {
+            self.memory.resize_with(idx + 1, || 0);
+        }
+    }
+
+    fn raw_param(&self, param: usize) -> Result<isize, IntcodeError> {
+        self.memory
+            .get(self.instruction_pointer + param + 1)
+            .copied()
+            .ok_or(IntcodeError::OutOfBounds {
+                ip: self.instruction_pointer,
+            })
+    }
+}
+
+// Programs that speak ASCII print their text one character at a time, but
+// usually report their actual answer as a single number that's way too big
+// to be a character, so we need to tell the two apart.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AsciiChunk {
+    Char(char),
+    Number(isize),
+}
+
+// The inputs that make up a line of text, including the newline at the end.
+pub fn ascii_input(line: &str) -> impl Iterator<Item = isize> + '_ {
+    line.bytes()
+        .chain(iter::once(b'\n'))
+        .map(|byte| byte as isize)
+}
+
+pub fn ascii_output(output: isize) -> AsciiChunk {
+    match u8::try_from(output) {
+        Ok(byte) if byte.is_ascii() => AsciiChunk::Char(byte as char),
+        _ => AsciiChunk::Number(output),
+    }
+}
+
+// Lets a person play an ASCII program (like the day 25 text adventure) by
+// hand. Whatever the program has printed gets shown whenever it stops to wait
+// for a command, and each line typed in is fed to it as the next command.
+// Running out of input (Ctrl-D) just stops the program where it is, rather
+// than being an error. Gives back the last number that was too big to be a
+// character, since that's usually the answer.
+pub fn interactive_ascii_repl(program: Vec<isize>) -> Result<Option<isize>, anyhow::Error> {
+    let mut vm = Vm::new(program);
+    let mut text = String::new();
+    let mut answer = None;
+
+    let show = |text: &mut String| -> io::Result<()> {
+        let mut stdout = io::stdout();
+
+        stdout.write_all(text.as_bytes())?;
+        stdout.flush()?;
+        text.clear();
+
+        Ok(())
+    };
+
+    loop {
+        match vm.step()? {
+            StepResult::Output(output) => match ascii_output(output) {
+                AsciiChunk::Char(c) => text.push(c),
+                AsciiChunk::Number(number) => {
+                    text += &format!("{}\n", number);
+                    answer = Some(number);
+                }
+            },
+            StepResult::NeedInput => {
+                show(&mut text)?;
+
+                let mut line = String::new();
+
+                if io::stdin().read_line(&mut line)? == 0 {
+                    // Leave the prompt on a line of its own.
+                    println!();
+                    return Ok(answer);
+                }
+
+                vm.feed_line(line.trim_end_matches(&['\r', '\n'][..]));
+            }
+            StepResult::Halted => {
+                show(&mut text)?;
+                return Ok(answer);
+            }
+            StepResult::Continue => {}
+        }
+    }
+}
+
+// The springdroid (day 21) only has room for this many instructions, not
+// counting the WALK or RUN at the end.
+pub const MAX_SPRINGSCRIPT_INSTRUCTIONS: usize = 15;
+
+// Checks a springscript program and turns it into the ASCII input the
+// springdroid program expects. Every line is an AND, OR or NOT that reads
+// from a sensor register or T/J and writes to T or J, and the last line is
+// either WALK or RUN. Only RUN gets to see the sensors past D, so the
+// script is checked against whichever one it ends with. Catching a typo
+// here is a lot nicer than reading the droid's error message after it's
+// already run.
+pub fn springscript(script: &[&str]) -> Result<Vec<isize>, anyhow::Error> {
+    let (mode, instructions) = script
+        .split_last()
+        .ok_or_else(|| anyhow!("Springscript is empty"))?;
+
+    let sensors = match mode.trim() {
+        "WALK" => "ABCD",
+        "RUN" => "ABCDEFGHI",
+        other => bail!("Springscript must end with WALK or RUN, not {:?}", other),
+    };
+
+    ensure!(
+        instructions.len() <= MAX_SPRINGSCRIPT_INSTRUCTIONS,
+        "Springscript has {} instructions, but the springdroid only fits {}",
+        instructions.len(),
+        MAX_SPRINGSCRIPT_INSTRUCTIONS
+    );
+
+    let mut input = vec![];
+
+    for instruction in instructions {
+        let (op, x, y) = instruction
+            .split_whitespace()
+            .collect_tuple()
+            .ok_or_else(|| {
+                anyhow!(
+                    "Springscript line {:?} isn't in the form OP X Y",
+                    instruction
+                )
+            })?;
+
+        ensure!(
+            matches!(op, "AND" | "OR" | "NOT"),
+            "Unknown springscript instruction {:?}",
+            op
+        );
+        ensure!(
+            x.len() == 1 && (sensors.contains(x) || matches!(x, "T" | "J")),
+            "Springscript line {:?} reads from {:?}, which isn't a register it can read with {}",
+            instruction,
+            x,
+            mode.trim()
+        );
+        ensure!(
+            matches!(y, "T" | "J"),
+            "Springscript line {:?} writes to {:?}, but only T and J can be written",
+            instruction,
+            y
+        );
+
+        input.extend(ascii_input(&format!("{} {} {}", op, x, y)));
+    }
+
+    input.extend(ascii_input(mode.trim()));
+
+    Ok(input)
+}
+
+impl From<Vec<isize>> for Vm {
+    fn from(program: Vec<isize>) -> Self {
+        Self::new(program)
+    }
+}
+
+// Runs a program to completion, pulling inputs from the stream only when
+// they're actually needed. This is what lets concurrently running machines
+// (like the day 7 amplifiers) wait on each other's outputs.
+pub async fn run_program(
+    program: impl Into<Vm>,
+    input: impl Stream<Item = isize>,
+    mut output_fn: impl FnMut(isize),
+) -> Result<Vec<isize>, IntcodeError> {
+    pin!(input);
+
+    let mut vm: Vm = program.into();
+
+    loop {
+        match vm.step()? {
+            StepResult::Output(output) => output_fn(output),
+            StepResult::NeedInput => {
+                vm.push_input(input.next().await.ok_or(IntcodeError::MissingInput {
+                    ip: vm.instruction_pointer(),
+                })?)
+            }
+            StepResult::Halted => return Ok(vm.into_memory()),
+            StepResult::Continue => {}
+        }
+    }
+}
+
+// Walks the program linearly, decoding everything it can into mnemonics.
+// Position mode parameters are shown as [addr], relative mode ones as
+// [rb+offset] and immediate ones as plain numbers. Anything that doesn't
+// decode cleanly (unknown opcodes, bad parameter modes, writes to immediate
+// parameters or instructions cut off by the end of the program) is shown
+// as raw data, one cell at a time. Since Intcode programs mix code and data
+// freely and can modify themselves, this is only ever a best guess.
+pub fn disassemble(program: &[isize]) -> Vec<String> {
+    let mut lines = Vec::with_capacity(program.len());
+    let mut addr = 0;
+
+    while addr < program.len() {
+        if let Some((instruction, len)) = decode_instruction(program, addr) {
+            lines.push(format!("{:>5}: {}", addr, instruction));
+            addr += len;
+        } else {
+            lines.push(format!("{:>5}: DATA {}", addr, program[addr]));
+            addr += 1;
+        }
+    }
+
+    lines
+}
+
+fn decode_instruction(program: &[isize], addr: usize) -> Option<(String, usize)> {
+    let opcode = usize::try_from(program[addr]).ok()?;
+
+    let (mnemonic, num_params, write_param) = opcode_info(opcode % 100)?;
+
+    let parameter_modes = get_parameter_modes(opcode, addr).ok()?;
+
+    if parameter_modes.len() > num_params {
+        return None;
+    }
+
+    let operands: Vec<_> = program
+        .get(addr + 1..addr + 1 + num_params)?
+        .iter()
+        .enumerate()
+        .map(
+            |(param, &value)| match parameter_mode_of(&parameter_modes, param) {
+                ParameterModes::Immediate if write_param == Some(param) => None,
+                ParameterModes::Immediate => Some(value.to_string()),
+                ParameterModes::Position => Some(format!("[{}]", value)),
+                ParameterModes::Relative => Some(format!("[rb{:+}]", value)),
+            },
+        )
+        .collect::<Option<_>>()?;
+
+    Some((
+        format!("{} {}", mnemonic, operands.join(", "))
+            .trim_end()
+            .to_owned(),
+        num_params + 1,
+    ))
+}
+
+// The mnemonic, number of parameters and which parameter (if any) gets
+// written to for each opcode, without the parameter modes.
+fn opcode_info(opcode: usize) -> Option<(&'static str, usize, Option<usize>)> {
+    Some(match opcode {
+        1 => ("ADD", 3, Some(2)),
+        2 => ("MUL", 3, Some(2)),
+        3 => ("IN", 1, Some(0)),
+        4 => ("OUT", 1, None),
+        5 => ("JNZ", 2, None),
+        6 => ("JZ", 2, None),
+        7 => ("LT", 3, Some(2)),
+        8 => ("EQ", 3, Some(2)),
+        9 => ("ARB", 1, None),
+        99 => ("HALT", 0, None),
+        _ => return None,
+    })
+}
+
+fn get_parameter_modes(opcode: usize, ip: usize) -> Result<Vec<ParameterModes>, IntcodeError> {
+    opcode
+        .digits()
+        .rev()
+        .skip(2)
+        .map(|mode| {
+            ParameterModes::from_digit(mode).ok_or(IntcodeError::BadParameterMode { ip, mode })
+        })
+        .try_collect()
+}
+
+fn parameter_mode_of(parameter_modes: &[ParameterModes], param: usize) -> ParameterModes {
+    parameter_modes
+        .get(param)
+        .copied()
+        .unwrap_or(ParameterModes::Position)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum ParameterModes {
+    Position = 0,
+    Immediate = 1,
+    Relative = 2,
+}
+
+impl ParameterModes {
+    fn from_digit(digit: u8) -> Option<Self> {
+        Some(match digit {
+            0 => Self::Position,
+            1 => Self::Immediate,
+            2 => Self::Relative,
+            _ => return None,
+        })
+    }
+}
+
+pub fn parse_program(program_str: &str) -> Result<Vec<isize>, anyhow::Error> {
+    program_str
+        .split(',')
+        .map(|num_str| {
+            num_str.trim().parse().with_context(|| {
+                format!("Could not parse number in program as isize: '{}'", num_str)
+            })
+        })
+        .try_collect()
+}
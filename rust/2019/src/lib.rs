@@ -0,0 +1,6 @@
+pub mod geometry;
+pub mod grid;
+pub mod input;
+pub mod intcode;
+pub mod search;
+pub mod timing;
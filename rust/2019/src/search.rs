@@ -0,0 +1,184 @@
+use rayon::prelude::*;
+
+// Finds the arrangement of len of the items (each used at most once) that
+// gets the highest score, along with that score. Rather than scoring every
+// full permutation, arrangements are built up one item at a time and score
+// is called on each partial one along the way, so that it can prune
+// everything that starts with a hopeless one (see Extend). Only the scores of
+// full arrangements are compared. When two full arrangements tie, whichever
+// one comes first (in the order of the items) wins.
+pub fn best_permutation<T, S, E>(
+    items: &[T],
+    len: usize,
+    score: impl Fn(&[T]) -> Result<Extend<S>, E> + Sync,
+) -> Result<Option<(S, Vec<T>)>, E>
+where
+    T: Clone + Send + Sync,
+    S: Ord + Send,
+    E: Send,
+{
+    if len == 0 || len > items.len() {
+        return Ok(None);
+    }
+
+    // Each choice of first item is its own independent search, which makes
+    // for an easy place to split the work up.
+    (0..items.len())
+        .into_par_iter()
+        .map(|first| {
+            let mut used = vec![false; items.len()];
+            used[first] = true;
+
+            best_extension(
+                items,
+                len,
+                &score,
+                &mut vec![items[first].clone()],
+                &mut used,
+            )
+        })
+        .try_reduce(|| None, |best, found| Ok(better(best, found)))
+}
+
+fn best_extension<T, S, E>(
+    items: &[T],
+    len: usize,
+    score: &impl Fn(&[T]) -> Result<Extend<S>, E>,
+    arrangement: &mut Vec<T>,
+    used: &mut [bool],
+) -> Result<Option<(S, Vec<T>)>, E>
+where
+    T: Clone,
+    S: Ord,
+{
+    let is_full = arrangement.len() == len;
+
+    match score(arrangement)? {
+        Extend::Prune => return Ok(None),
+        Extend::Continue if is_full => return Ok(None),
+        Extend::Score(arrangement_score) if is_full => {
+            return Ok(Some((arrangement_score, arrangement.clone())))
+        }
+        _ => {}
+    }
+
+    let mut best = None;
+
+    for (idx, item) in items.iter().enumerate() {
+        if used[idx] {
+            continue;
+        }
+
+        used[idx] = true;
+        arrangement.push(item.clone());
+
+        let found = best_extension(items, len, score, arrangement, used)?;
+
+        arrangement.pop();
+        used[idx] = false;
+
+        best = better(best, found);
+    }
+
+    Ok(best)
+}
+
+// What best_permutation's score thinks of an arrangement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extend<S> {
+    // Nothing that starts with this arrangement can be the best one, so
+    // don't bother going any further.
+    Prune,
+    // Keep going, even though there's no score yet. A full arrangement that
+    // gets this doesn't count at all.
+    Continue,
+    // How good the arrangement is. Partial arrangements can have one too,
+    // but it's only the full ones' scores that get compared.
+    Score(S),
+}
+
+fn better<S: Ord, T>(first: Option<(S, T)>, second: Option<(S, T)>) -> Option<(S, T)> {
+    match (first, second) {
+        (Some(first), Some(second)) if second.0 > first.0 => Some(second),
+        (first, second) => first.or(second),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    // Scores an arrangement of digits as the number they spell out.
+    fn as_number(digits: &[u32]) -> u32 {
+        digits.iter().fold(0, |n, d| n * 10 + d)
+    }
+
+    #[test]
+    fn no_pruning() {
+        let best = best_permutation(&[1, 3, 2], 3, |digits| {
+            Ok::<_, Infallible>(Extend::Score(as_number(digits)))
+        });
+
+        assert_eq!(best, Ok(Some((321, vec![3, 2, 1]))));
+    }
+
+    #[test]
+    fn pruning() {
+        // Nothing starting with 3 is allowed, so the best has to start with 2.
+        let best = best_permutation(&[1, 3, 2], 3, |digits| {
+            Ok::<_, Infallible>(if digits[0] == 3 {
+                Extend::Prune
+            } else {
+                Extend::Score(as_number(digits))
+            })
+        });
+
+        assert_eq!(best, Ok(Some((231, vec![2, 3, 1]))));
+
+        let best = best_permutation(&[1, 3, 2], 3, |_| {
+            Ok::<Extend<u32>, Infallible>(Extend::Prune)
+        });
+
+        assert_eq!(best, Ok(None));
+    }
+
+    #[test]
+    fn unscored_partial_arrangements() {
+        // Partial arrangements can't be mistaken for scores, even ones that
+        // would beat every full arrangement.
+        let best = best_permutation(&[1, 3, 2], 2, |digits| {
+            Ok::<_, Infallible>(if digits.len() < 2 {
+                Extend::Score(u32::MAX)
+            } else {
+                Extend::Score(as_number(digits))
+            })
+        });
+
+        assert_eq!(best, Ok(Some((32, vec![3, 2]))));
+
+        // A full arrangement with no score doesn't count.
+        let best = best_permutation(&[1, 3, 2], 2, |digits| {
+            Ok::<_, Infallible>(if digits == [1, 2] {
+                Extend::Score(as_number(digits))
+            } else {
+                Extend::Continue
+            })
+        });
+
+        assert_eq!(best, Ok(Some((12, vec![1, 2]))));
+    }
+
+    #[test]
+    fn errors_stop_the_search() {
+        let best = best_permutation(&[1, 3, 2], 3, |digits| {
+            if digits == [2, 1] {
+                Err("no")
+            } else {
+                Ok(Extend::Score(as_number(digits)))
+            }
+        });
+
+        assert_eq!(best, Err("no"));
+    }
+}
@@ -0,0 +1,228 @@
+// Runs each day's solver on the sample inputs from its puzzle description,
+// and checks the answers against the ones the puzzle gives. Each day is
+// pulled in from its binary as a module, so it's solved exactly the same way
+// main solves it, just without going through the command line. Answers that
+// a day's own tests already check aren't checked again here.
+
+// The days' own #![feature]s don't do anything inside a module, so they have
+// to be turned on here instead.
+#![feature(default_free_fn, duration_zero)]
+
+use aoc_2019_rust::intcode::{parse_program, Vm};
+use std::collections::{HashMap, HashSet};
+
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/1/main.rs"]
+mod day1;
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/12/main.rs"]
+mod day12;
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/13/main.rs"]
+mod day13;
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/14/main.rs"]
+mod day14;
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/2/main.rs"]
+mod day2;
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/4/main.rs"]
+mod day4;
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/5/main.rs"]
+mod day5;
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/6/main.rs"]
+mod day6;
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/7/main.rs"]
+mod day7;
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/8/main.rs"]
+mod day8;
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/9/main.rs"]
+mod day9;
+
+// Most days' inputs are a single Intcode program.
+fn program(program_str: &str) -> Vec<isize> {
+    parse_program(program_str.trim()).unwrap()
+}
+
+#[test]
+fn day_1() {
+    let module_masses = day1::parse_input(include_str!("../src/bin/1/input_small.txt")).unwrap();
+
+    // 2 + 2 + 654 + 33583, and 2 + 2 + 966 + 50346.
+    assert_eq!(day1::part1(&module_masses), Some(34241));
+    assert_eq!(day1::part2(&module_masses), Some(51316));
+}
+
+#[test]
+fn day_2() {
+    let program = program(include_str!("../src/bin/2/input_small.txt"));
+
+    assert_eq!(
+        day2::run_program_with_inputs(&program, &[], &[]).unwrap(),
+        [3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50]
+    );
+    assert_eq!(
+        day2::find_inputs(&program, &[1, 2], 11, 3500).unwrap(),
+        Some((vec![9, 10], 3500))
+    );
+}
+
+#[test]
+fn day_4() {
+    let (password_min, password_max) =
+        day4::parse_input(include_str!("../src/bin/4/input_small.txt")).unwrap();
+
+    // 111111 to 111119, and 111122.
+    assert_eq!(
+        day4::count_valid_passwords(password_min, password_max, 10, 2, true, false),
+        10
+    );
+    // Only 111122.
+    assert_eq!(
+        day4::count_valid_passwords(password_min, password_max, 10, 2, false, false),
+        1
+    );
+}
+
+#[test]
+fn day_5() {
+    let program = program(include_str!("../src/bin/5/input_small.txt"));
+
+    for &(system_id, code) in &[(7, 999), (8, 1000), (9, 1001)] {
+        assert_eq!(day5::diagnostic_code(&program, system_id).unwrap(), code);
+    }
+}
+
+#[test]
+fn day_6() {
+    let orbits = day6::parse_input(include_str!("../src/bin/6/input_small.txt")).unwrap();
+    let mut depths = HashMap::new();
+    let mut euler_walk = vec![];
+    day6::depth_first_traversal(&orbits, &mut depths, &mut euler_walk, &"COM".to_owned(), 0);
+
+    // The sample is the part 1 one with YOU and SAN added, which adds their
+    // 7 and 5 orbits on top of the 42 it has on its own.
+    assert_eq!(day6::total_orbits(&depths), 54);
+}
+
+#[test]
+fn day_7() {
+    assert_eq!(
+        day7::find_max_thruster_val(
+            program(include_str!("../src/bin/7/input_small_1.txt")),
+            0..=4,
+            5
+        )
+        .unwrap(),
+        (65210, vec![1, 0, 4, 3, 2])
+    );
+    assert_eq!(
+        day7::find_max_thruster_val(
+            program(include_str!("../src/bin/7/input_small_2.txt")),
+            5..=9,
+            5
+        )
+        .unwrap(),
+        (139629729, vec![9, 8, 7, 6, 5])
+    );
+}
+
+#[test]
+fn day_8() {
+    use day8::Pixel::*;
+
+    let image_layers =
+        day8::parse_input(include_str!("../src/bin/8/input_small.txt"), 2, 2).unwrap();
+
+    // The layer with no black pixels has two white and two transparent ones.
+    assert_eq!(day8::image_checksum(&image_layers), 4);
+    assert_eq!(
+        day8::decode_image_layers(&image_layers),
+        [[Black, White], [White, Black]]
+    );
+}
+
+#[test]
+fn day_9() {
+    // A quine, so it's the same whatever mode it's run in.
+    let program = program(include_str!("../src/bin/9/input_small.txt"));
+
+    assert_eq!(day9::run_boost(&program, 1, false).unwrap(), program);
+}
+
+#[test]
+fn day_12() {
+    for &(positions_str, num_steps, energy) in &[
+        (include_str!("../src/bin/12/input_small_1.txt"), 10, 179),
+        (include_str!("../src/bin/12/input_small_2.txt"), 100, 1940),
+    ] {
+        let positions = day12::parse_input(positions_str).unwrap();
+
+        assert_eq!(day12::energy_after_steps(&positions, num_steps), energy);
+    }
+}
+
+#[test]
+fn day_13() {
+    // There's no sample game, so this plays the real one, which is what
+    // --auto is there for.
+    let game = Vm::new(program(include_str!("../src/bin/13/input.txt")));
+
+    assert_eq!(day13::blocks_without_quarters(&game).unwrap(), 341);
+    assert_eq!(day13::auto_play(&game).unwrap(), (17138, 0));
+}
+
+#[test]
+fn day_14() {
+    let ore = || vec!["ORE".to_owned()];
+
+    for &(reactions_str, ore_per_fuel, max_fuel) in &[
+        (include_str!("../src/bin/14/input_small_1.txt"), 31, None),
+        (
+            include_str!("../src/bin/14/input_medium_1.txt"),
+            13312,
+            Some(82892753),
+        ),
+        (
+            include_str!("../src/bin/14/input_medium_2.txt"),
+            180697,
+            Some(5586022),
+        ),
+        (
+            include_str!("../src/bin/14/input_medium_3.txt"),
+            2210736,
+            Some(460664),
+        ),
+    ] {
+        let possible_reactions = day14::parse_input(reactions_str).unwrap();
+        let bases = ore().into_iter().collect::<HashSet<_>>();
+
+        let (requirements, _) = day14::find_requirements_and_leftovers(
+            &possible_reactions,
+            &bases,
+            "FUEL".to_owned(),
+            1,
+        )
+        .unwrap();
+        assert_eq!(requirements["ORE"], ore_per_fuel);
+
+        if let Some(max_fuel) = max_fuel {
+            assert_eq!(
+                day14::max_fuel(
+                    &possible_reactions,
+                    &bases,
+                    &"FUEL".to_owned(),
+                    &ore(),
+                    1_000_000_000_000
+                ),
+                Some(max_fuel)
+            );
+        }
+    }
+}
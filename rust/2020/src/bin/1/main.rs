@@ -1,7 +1,11 @@
 use anyhow::anyhow;
+use aoc_2020_rust::{
+    input::read_input,
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
 use itertools::Itertools;
-use std::{fs, num};
+use std::{cmp::Ordering, collections::HashSet, num};
 
 fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2020-1")
@@ -18,9 +22,22 @@ fn main() -> Result<(), anyhow::Error> {
             )
             .default_value("2"),
         )
+        .arg(Arg::from_usage(
+            "[product_only] -p --product-only 'Only print the product of the values, which is the puzzle answer'",
+        ))
+        .arg(Arg::from_usage(
+            "[time] --time 'Print how long parsing and solving take'",
+        ))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
     let required_sum = matches
         .value_of("required_sum")
         .and_then(|n| n.parse::<usize>().ok())
@@ -29,20 +46,101 @@ fn main() -> Result<(), anyhow::Error> {
         .value_of("num_parts")
         .and_then(|n| n.parse::<usize>().ok())
         .ok_or_else(|| anyhow!("Num parts parameter is not a positive integer"))?;
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
 
-    let numbers_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
+    let parse_timer = Timer::start("Parsing", time);
+    let numbers_str = read_input(input_filename)?;
 
     let numbers = parse_input(&numbers_str)?;
+    drop(parse_timer);
 
-    let parts = find_required_sum(&numbers, required_sum, num_parts)
-        .ok_or_else(|| anyhow!("Couldn't find {} values that sum to the required sum", num_parts))?;
+    let parts = timing::repeat("Solving", repeat, time, || {
+        find_required_sum(&numbers, required_sum, num_parts)
+    })
+    .ok_or_else(|| {
+        anyhow!(
+            "Couldn't find {} values that sum to the required sum",
+            num_parts
+        )
+    })?;
 
-    println!("{} = {}", parts.iter().join(" + "), required_sum);
+    let product = parts.iter().product::<usize>();
+
+    if matches.is_present("product_only") {
+        println!("{}", product);
+    } else {
+        println!("{} = {}", parts.iter().join(" + "), required_sum);
+        println!("{} = {}", parts.iter().join(" * "), product);
+    }
 
     Ok(())
 }
 
+// The general case has to try every combination of num_parts numbers, which
+// gets slow very quickly, so the 2 and 3 part cases that the puzzle actually
+// asks for have faster versions of their own.
 fn find_required_sum(numbers: &[usize], req_sum: usize, num_parts: usize) -> Option<Vec<usize>> {
+    match num_parts {
+        2 => find_required_sum_2(numbers, req_sum),
+        3 => find_required_sum_3(numbers, req_sum),
+        _ => find_required_sum_combinations(numbers, req_sum, num_parts),
+    }
+}
+
+// O(n): for each number we only need to know if we've already seen the
+// number that would complete the sum.
+fn find_required_sum_2(numbers: &[usize], req_sum: usize) -> Option<Vec<usize>> {
+    let mut seen = HashSet::with_capacity(numbers.len());
+
+    for &n in numbers {
+        if let Some(complement) = req_sum.checked_sub(n) {
+            if seen.contains(&complement) {
+                return Some(vec![complement, n]);
+            }
+        }
+
+        seen.insert(n);
+    }
+
+    None
+}
+
+// O(n^2): fix the smallest number, then close in on the other two from both
+// ends of the sorted list, moving whichever end gets the sum closer.
+fn find_required_sum_3(numbers: &[usize], req_sum: usize) -> Option<Vec<usize>> {
+    let sorted_numbers = numbers.iter().copied().sorted().collect_vec();
+
+    for (i, &smallest) in sorted_numbers.iter().enumerate() {
+        // Everything after this is at least as big, so nothing can fit.
+        let remaining_sum = req_sum.checked_sub(smallest)?;
+
+        let (mut lo, mut hi) = (i + 1, sorted_numbers.len().saturating_sub(1));
+
+        while lo < hi {
+            let sum = sorted_numbers[lo] + sorted_numbers[hi];
+
+            match sum.cmp(&remaining_sum) {
+                Ordering::Equal => {
+                    return Some(vec![smallest, sorted_numbers[lo], sorted_numbers[hi]])
+                }
+                Ordering::Less => lo += 1,
+                Ordering::Greater => hi -= 1,
+            }
+        }
+    }
+
+    None
+}
+
+fn find_required_sum_combinations(
+    numbers: &[usize],
+    req_sum: usize,
+    num_parts: usize,
+) -> Option<Vec<usize>> {
     for parts in numbers.iter().combinations(num_parts) {
         let parts = parts.into_iter().copied().collect_vec();
 
@@ -60,3 +158,70 @@ fn parse_input(numbers_str: &str) -> Result<Vec<usize>, num::ParseIntError> {
         .map(|num_str| num_str.parse())
         .try_collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(parts: Option<Vec<usize>>) -> Option<Vec<usize>> {
+        parts.map(|parts| parts.into_iter().sorted().collect())
+    }
+
+    #[test]
+    fn fast_paths_match_combinations() {
+        let numbers = parse_input(include_str!("input_small.txt")).unwrap();
+
+        assert_eq!(
+            sorted(find_required_sum_2(&numbers, 2020)),
+            Some(vec![299, 1721])
+        );
+        assert_eq!(
+            sorted(find_required_sum_3(&numbers, 2020)),
+            Some(vec![366, 675, 979])
+        );
+
+        // Every sum of 2 or 3 of these numbers can only be made one way, so
+        // it doesn't matter which order each approach finds them in.
+        let req_sums = (2..=3)
+            .flat_map(|num_parts| numbers.iter().combinations(num_parts))
+            .map(|parts| parts.into_iter().sum())
+            .chain(vec![0, 1, 2019, 10_000]);
+
+        for req_sum in req_sums {
+            for &num_parts in &[2, 3] {
+                assert_eq!(
+                    sorted(find_required_sum(&numbers, req_sum, num_parts)),
+                    sorted(find_required_sum_combinations(&numbers, req_sum, num_parts)),
+                    "{} parts summing to {}",
+                    num_parts,
+                    req_sum
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn repeated_numbers() {
+        // Each number can only be used once, even if it'd fit twice.
+        assert_eq!(find_required_sum_2(&[1010, 5], 2020), None);
+        assert_eq!(
+            find_required_sum_2(&[1010, 5, 1010], 2020),
+            Some(vec![1010, 1010])
+        );
+        assert_eq!(find_required_sum_3(&[10, 20], 30), None);
+    }
+
+    #[test]
+    fn sample_products() {
+        let numbers = parse_input(include_str!("input_small.txt")).unwrap();
+        let product = |num_parts| {
+            find_required_sum(&numbers, 2020, num_parts)
+                .unwrap()
+                .iter()
+                .product::<usize>()
+        };
+
+        assert_eq!(product(2), 514579);
+        assert_eq!(product(3), 241861950);
+    }
+}
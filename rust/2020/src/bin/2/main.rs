@@ -1,40 +1,96 @@
 #![feature(pattern, try_blocks)]
 
-use anyhow::{anyhow, bail};
+use anyhow::{anyhow, bail, Context};
+use aoc_2020_rust::{
+    input::read_input,
+    timing::{self, Timer},
+};
 use clap::{App, Arg};
 use itertools::Itertools;
-use std::{fmt, fs, marker::PhantomData, ops::RangeInclusive, str::pattern::Pattern};
+use std::{fmt, marker::PhantomData, ops::RangeInclusive, str::pattern::Pattern};
 
 fn main() -> Result<(), anyhow::Error> {
     let matches = App::new("2020-2")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
+        .arg(Arg::from_usage(
+            "[substrings] -s --substrings 'Allow policies to require substrings rather than just characters'",
+        ))
+        .arg(Arg::from_usage("[time] --time 'Print how long parsing and solving take'"))
+        .arg(
+            Arg::from_usage(
+                "[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'",
+            )
+            .default_value("1"),
+        )
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
-
-    let passwords_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
+
+    let parse_timer = Timer::start("Parsing", time);
+    let passwords_str = read_input(input_filename)?;
     let policies_and_passwords = parse_input(&passwords_str)?;
+    let char_policies_and_passwords = to_char_policies(&policies_and_passwords);
+    drop(parse_timer);
+
+    // Without --substrings, a longer pattern is most likely a mistake in the
+    // input, so we complain about it before printing anything.
+    if let (false, Err(e)) = (
+        matches.is_present("substrings"),
+        &char_policies_and_passwords,
+    ) {
+        bail!("{} (use --substrings to allow longer patterns)", e);
+    }
+
+    let (num_valid_in_range, num_valid_in_positions) =
+        timing::repeat("Solving", repeat, time, || {
+            (
+                num_valid_in_range(&policies_and_passwords),
+                char_policies_and_passwords
+                    .as_ref()
+                    .ok()
+                    .map(|c| num_valid_in_positions(c)),
+            )
+        });
 
     println!(
         "Number of valid passwords in the list by num occurences policy: {}",
-        policies_and_passwords
-            .iter()
-            .filter(|(policy, password)| policy.is_valid_in_range(password))
-            .count()
+        num_valid_in_range
     );
 
+    // This can only fail with --substrings, since otherwise it would've
+    // been caught above.
+    char_policies_and_passwords
+        .context("The positions policy only works with single character patterns")?;
+
     println!(
         "Number of valid passwords in the list by positions policy: {}",
-        policies_and_passwords
-            .iter()
-            .filter(|(policy, password)| policy.is_valid_in_positions(password))
-            .count()
+        num_valid_in_positions.unwrap()
     );
 
     Ok(())
 }
 
-fn parse_input(passwords_str: &str) -> Result<Vec<(Policy<char>, &str)>, anyhow::Error> {
+pub fn num_valid_in_range(policies_and_passwords: &[(Policy<&str>, &str)]) -> usize {
+    policies_and_passwords
+        .iter()
+        .filter(|(policy, password)| policy.is_valid_in_range(password))
+        .count()
+}
+
+pub fn num_valid_in_positions(policies_and_passwords: &[(Policy<char>, &str)]) -> usize {
+    policies_and_passwords
+        .iter()
+        .filter(|(policy, password)| policy.is_valid_in_positions(password))
+        .count()
+}
+
+pub fn parse_input(passwords_str: &str) -> Result<Vec<(Policy<&str>, &str)>, anyhow::Error> {
     passwords_str
         .lines()
         .map(|password_line| {
@@ -50,12 +106,6 @@ fn parse_input(passwords_str: &str) -> Result<Vec<(Policy<char>, &str)>, anyhow:
                     .collect_tuple()
                     .ok_or_else(|| anyhow!("Invalid policy format"))?;
 
-            let required_pattern = if required_pattern_str.len() == 1 {
-                required_pattern_str.chars().next().unwrap()
-            } else {
-                bail!("Required pattern is not a character")
-            };
-
             let (min, max) = range_str
                 .split('-')
                 .map(|n| {
@@ -65,13 +115,33 @@ fn parse_input(passwords_str: &str) -> Result<Vec<(Policy<char>, &str)>, anyhow:
                 .collect_tuple()
                 .ok_or_else(|| anyhow!("Invalid amount of rules in policy"))?;
 
-            Ok((Policy::new(min?..=max?, required_pattern), password_str))
+            Ok((Policy::new(min?..=max?, required_pattern_str), password_str))
+        })
+        .try_collect()
+}
+
+// Counting occurrences works for any pattern, but checking positions
+// only makes sense for patterns that are a single character long.
+pub fn to_char_policies<'a>(
+    policies_and_passwords: &[(Policy<'a, &'a str>, &'a str)],
+) -> Result<Vec<(Policy<'a, char>, &'a str)>, anyhow::Error> {
+    policies_and_passwords
+        .iter()
+        .map(|(policy, password)| {
+            let required_char = policy.required_pattern.chars().exactly_one().map_err(|_| {
+                anyhow!(
+                    "Required pattern {:?} is not a character",
+                    policy.required_pattern
+                )
+            })?;
+
+            Ok((Policy::new(policy.range.clone(), required_char), *password))
         })
         .try_collect()
 }
 
 #[derive(Clone)]
-struct Policy<'a, P: Pattern<'a>> {
+pub struct Policy<'a, P: Pattern<'a>> {
     range: RangeInclusive<usize>,
     required_pattern: P,
     phantom: PhantomData<&'a str>,
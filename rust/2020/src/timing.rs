@@ -0,0 +1,71 @@
+use std::{num::NonZeroUsize, time::Instant};
+
+// Prints how long it was alive for to stderr when it's dropped, so that
+// timing a phase of a solver is just a matter of keeping one of these
+// around until the phase is over. If it's not enabled, it does nothing,
+// which saves every caller from checking a --time flag themselves.
+pub struct Timer {
+    label: &'static str,
+    start: Instant,
+    enabled: bool,
+}
+
+impl Timer {
+    pub fn start(label: &'static str, enabled: bool) -> Self {
+        Self {
+            label,
+            start: Instant::now(),
+            enabled,
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if self.enabled {
+            eprintln!("{} took {:?}", self.label, self.start.elapsed());
+        }
+    }
+}
+
+// Runs a phase of a solver some number of times, for benchmarking it by hand
+// with --repeat. Only the first run's result is given back, so whatever the
+// caller prints from it shows up once. A single run is timed like any other
+// phase, but more than that always prints the fastest, median and slowest
+// runs to stderr, since that's the whole point of asking for them.
+pub fn repeat<T>(
+    label: &'static str,
+    times: NonZeroUsize,
+    time: bool,
+    mut phase: impl FnMut() -> T,
+) -> T {
+    if times.get() == 1 {
+        let _timer = Timer::start(label, time);
+
+        return phase();
+    }
+
+    let mut first_result = None;
+    let mut durations = Vec::with_capacity(times.get());
+
+    for _ in 0..times.get() {
+        let start = Instant::now();
+        let result = phase();
+        durations.push(start.elapsed());
+
+        first_result.get_or_insert(result);
+    }
+
+    durations.sort();
+
+    eprintln!(
+        "{} took {:?} at best, {:?} median and {:?} at worst over {} runs",
+        label,
+        durations[0],
+        durations[durations.len() / 2],
+        durations[durations.len() - 1],
+        times
+    );
+
+    first_result.unwrap()
+}
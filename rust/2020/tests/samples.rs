@@ -0,0 +1,26 @@
+// Runs each day's solver on the sample input from its puzzle description,
+// and checks the answers against the ones the puzzle gives. Each day is
+// pulled in from its binary as a module, so it's solved exactly the same way
+// main solves it, just without going through the command line. Answers that
+// a day's own tests already check aren't checked again here.
+
+// The days' own #![feature]s don't do anything inside a module, so they have
+// to be turned on here instead.
+#![feature(pattern, try_blocks)]
+
+#[allow(dead_code, unused_attributes)]
+#[path = "../src/bin/2/main.rs"]
+mod day2;
+
+#[test]
+fn day_2() {
+    let policies_and_passwords =
+        day2::parse_input(include_str!("../src/bin/2/input_small.txt")).unwrap();
+    let char_policies_and_passwords = day2::to_char_policies(&policies_and_passwords).unwrap();
+
+    assert_eq!(day2::num_valid_in_range(&policies_and_passwords), 2);
+    assert_eq!(
+        day2::num_valid_in_positions(&char_policies_and_passwords),
+        1
+    );
+}
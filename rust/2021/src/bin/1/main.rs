@@ -1,57 +1,69 @@
-use anyhow;
-use clap::{Command, Arg};
+use anyhow::anyhow;
+use aoc_2021_rust::{
+    input::read_input,
+    timing::{self, Timer},
+};
+use clap::{Arg, Command};
 use itertools::Itertools;
-use std::{fs, num};
+use std::num;
 
 fn main() -> Result<(), anyhow::Error> {
     let matches = Command::new("2021-1")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
         .arg(Arg::from_usage("[group_length] 'Length of groups to compare for Part 2'").default_value("3"))
+        .arg(Arg::from_usage("[part] --part 'Which part of the problem to solve'").possible_values(["1", "2", "both"]).default_value("both"))
+        .arg(Arg::from_usage("[time] --time 'Print how long parsing and solving take'"))
+        .arg(Arg::from_usage("[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'").default_value("1"))
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
+    let time = matches.is_present("time");
     let group_length = matches.value_of("group_length").unwrap().parse::<usize>()?;
-
-    let depth_measurements_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
+    let part = matches.value_of("part").unwrap();
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
+
+    let parse_timer = Timer::start("Parsing", time);
+    let depth_measurements_str = read_input(input_filename)?;
     let depth_measurements = parse_input(&depth_measurements_str)?;
+    drop(parse_timer);
 
-    let num_increases = find_depth_increases(&depth_measurements);
-
-    println!("The depth increases {num_increases} times.");
+    let (num_increases, num_summed_increases) = timing::repeat("Solving", repeat, time, || {
+        (
+            (part != "2").then(|| count_increases(&depth_measurements, 1)),
+            (part != "1").then(|| count_increases(&depth_measurements, group_length)),
+        )
+    });
 
-    let num_summed_increases = find_summed_depth_increases(&depth_measurements, group_length);
+    if let Some(num_increases) = num_increases {
+        println!("The depth increases {num_increases} times.");
+    }
 
-    println!("In groups of {group_length}, the depths increase {num_summed_increases} times.");
+    if let Some(num_summed_increases) = num_summed_increases {
+        println!("In groups of {group_length}, the depths increase {num_summed_increases} times.");
+    }
 
     Ok(())
 }
 
-fn find_summed_depth_increases(depth_measurements: &Vec<usize>, group_length: usize) -> usize {
+// Counts how many times the sum of a window of depths is bigger than the sum
+// of the window before it. A window of 1 is just comparing the depths themselves.
+fn count_increases(depth_measurements: &[usize], window: usize) -> usize {
     let mut depth_increases = 0;
-    let mut previous_sum = usize::MAX;
+    // The first window doesn't have anything to compare against.
+    let mut previous_sum = None;
 
-    for depths in depth_measurements.windows(group_length) {
-        let sum = depths.iter().sum();
+    for depths in depth_measurements.windows(window) {
+        let sum: usize = depths.iter().sum();
 
-        if previous_sum < sum {
+        if matches!(previous_sum, Some(previous_sum) if previous_sum < sum) {
             depth_increases += 1;
         }
 
-        previous_sum = sum;
-    }
-
-    depth_increases
-}
-
-fn find_depth_increases(depth_measurements: &Vec<usize>) -> usize {
-    let mut depth_increases = 0;
-
-    for depths in depth_measurements.windows(2) {
-        // These are certain to be there but the Rust
-        // type system is not yet smart enough to know that.
-        if depths[0] < depths[1] {
-            depth_increases += 1;
-        }
+        previous_sum = Some(sum);
     }
 
     depth_increases
@@ -62,4 +74,24 @@ fn parse_input(depth_measurements_str: &str) -> Result<Vec<usize>, num::ParseInt
         .lines()
         .map(|depth_str| depth_str.parse())
         .try_collect()
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_increases() {
+        let depth_measurements = parse_input(include_str!("input_small.txt")).unwrap();
+
+        assert_eq!(count_increases(&depth_measurements, 1), 7);
+        assert_eq!(count_increases(&depth_measurements, 3), 5);
+    }
+
+    #[test]
+    fn window_longer_than_input() {
+        assert_eq!(count_increases(&[1, 2, 3], 3), 0);
+        assert_eq!(count_increases(&[1, 2, 3], 4), 0);
+        assert_eq!(count_increases(&[], 1), 0);
+    }
+}
@@ -1,32 +1,60 @@
-use anyhow::bail;
-use clap::{Command, Arg};
+use anyhow::{anyhow, bail, Context};
+use aoc_2021_rust::{
+    input::read_input,
+    timing::{self, Timer},
+};
+use clap::{Arg, Command};
 use itertools::Itertools;
-use std::fs;
 
 fn main() -> Result<(), anyhow::Error> {
     let matches = Command::new("2021-2")
         .arg(Arg::from_usage("[input] 'Problem input file'").default_value("input.txt"))
+        .arg(Arg::from_usage("[time] --time 'Print how long parsing and solving take'"))
+        .arg(Arg::from_usage("[repeat] --repeat 'Solve this many times, and print the fastest, median and slowest times'").default_value("1"))
         .get_matches();
 
     let input_filename = matches.value_of("input").unwrap();
-
-    let submarine_instructions_str = fs::read_to_string(input_filename)?.replace("\r\n", "\n");
+    let time = matches.is_present("time");
+    let repeat = matches
+        .value_of("repeat")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Provided repeat count is not a positive number"))?;
+
+    let parse_timer = Timer::start("Parsing", time);
+    let submarine_instructions_str = read_input(input_filename)?;
     let submarine_instructions = parse_input(&submarine_instructions_str)?;
-
-    let (final_position, final_depth) = path_simple(0, 0, &submarine_instructions);
-
-    println!("You'll end up at ({final_position}, {final_depth}) with the simple approach.");
-
-    let (final_position, final_depth, _) = path_with_aim(0, 0, 0, &submarine_instructions);
-
-    println!("Taking into account aim, you'll end up at ({final_position}, {final_depth})");
+    drop(parse_timer);
+
+    let ((simple_position, simple_depth), (aimed_position, aimed_depth, _)) =
+        timing::repeat("Solving", repeat, time, || {
+            (
+                path_simple(0, 0, &submarine_instructions),
+                path_with_aim(0, 0, 0, &submarine_instructions),
+            )
+        });
+
+    println!("You'll end up at ({simple_position}, {simple_depth}) with the simple approach.");
+    println!(
+        "Multiplied together, that's {}.",
+        simple_position * simple_depth
+    );
+
+    println!("Taking into account aim, you'll end up at ({aimed_position}, {aimed_depth})");
+    println!(
+        "Multiplied together, that's {}.",
+        aimed_position * aimed_depth
+    );
 
     Ok(())
 }
 
-fn path_with_aim(initial_position: usize, initial_depth: usize,
-    initial_aim: usize, submarine_instructions: &Vec<Instruction>)
-    -> (usize, usize, usize) {
+fn path_with_aim(
+    initial_position: usize,
+    initial_depth: usize,
+    initial_aim: usize,
+    submarine_instructions: &Vec<Instruction>,
+) -> (usize, usize, usize) {
     let mut current_position = initial_position;
     let mut current_depth = initial_depth;
     let mut current_aim = initial_aim;
@@ -36,7 +64,7 @@ fn path_with_aim(initial_position: usize, initial_depth: usize,
             Instruction::Forward(units) => {
                 current_position += units;
                 current_depth += current_aim * units;
-            },
+            }
             Instruction::Down(units) => current_aim += units,
             Instruction::Up(units) => current_aim -= units,
         }
@@ -45,8 +73,11 @@ fn path_with_aim(initial_position: usize, initial_depth: usize,
     (current_position, current_depth, current_aim)
 }
 
-fn path_simple(initial_position: usize, initial_depth: usize, submarine_instructions: &Vec<Instruction>)
-    -> (usize, usize) {
+fn path_simple(
+    initial_position: usize,
+    initial_depth: usize,
+    submarine_instructions: &Vec<Instruction>,
+) -> (usize, usize) {
     let mut current_position = initial_position;
     let mut current_depth = initial_depth;
 
@@ -64,17 +95,23 @@ fn path_simple(initial_position: usize, initial_depth: usize, submarine_instruct
 fn parse_input(submarine_instructions_str: &str) -> Result<Vec<Instruction>, anyhow::Error> {
     submarine_instructions_str
         .lines()
+        .filter(|instruction_str| !instruction_str.trim().is_empty())
         .map(|instruction_str| {
-            let instruction = instruction_str.split(" ").collect_vec();
-            let direction_str = instruction[0];
-            let units_str = instruction[1];
-
-            let units = units_str.parse()?;
+            let (direction_str, units_str) = instruction_str
+                .split_whitespace()
+                .collect_tuple()
+                .ok_or_else(|| {
+                    anyhow!("Expected a direction and a number of units: '{instruction_str}'")
+                })?;
+
+            let units = units_str
+                .parse()
+                .with_context(|| format!("Invalid number of units: '{instruction_str}'"))?;
             let direction = match direction_str {
                 "forward" => Instruction::Forward(units),
                 "down" => Instruction::Down(units),
                 "up" => Instruction::Up(units),
-                _ => bail!("Invalid instruction")
+                _ => bail!("Invalid instruction: '{instruction_str}'"),
             };
 
             Ok(direction)
@@ -82,9 +119,47 @@ fn parse_input(submarine_instructions_str: &str) -> Result<Vec<Instruction>, any
         .try_collect()
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum Instruction {
     Forward(usize),
     Down(usize),
-    Up(usize)
-}
\ No newline at end of file
+    Up(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_paths() {
+        let submarine_instructions = parse_input(include_str!("input_small.txt")).unwrap();
+
+        let (simple_position, simple_depth) = path_simple(0, 0, &submarine_instructions);
+        assert_eq!((simple_position, simple_depth), (15, 10));
+        assert_eq!(simple_position * simple_depth, 150);
+
+        let (aimed_position, aimed_depth, aim) = path_with_aim(0, 0, 0, &submarine_instructions);
+        assert_eq!((aimed_position, aimed_depth, aim), (15, 60, 10));
+        assert_eq!(aimed_position * aimed_depth, 900);
+    }
+
+    #[test]
+    fn messy_input() {
+        // Blank lines, a trailing newline, and more spaces than needed.
+        assert_eq!(
+            parse_input(include_str!("input_messy.txt")).unwrap(),
+            parse_input(include_str!("input_small.txt")).unwrap()
+        );
+    }
+
+    #[test]
+    fn malformed_input() {
+        let error = parse_input(include_str!("input_wrong.txt")).unwrap_err();
+        assert!(error.to_string().contains("down five"));
+
+        assert!(parse_input("forward").is_err());
+        assert!(parse_input("forward 5 6").is_err());
+        assert!(parse_input("backward 5").is_err());
+        assert!(parse_input("up -3").is_err());
+    }
+}